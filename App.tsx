@@ -3,7 +3,6 @@ import { useAbel } from './hooks/useAbel';
 import { AbelFace } from './components/AbelFace';
 import { ControlPanel } from './components/ControlPanel';
 import { HelpModal } from './components/HelpModal';
-import { SEQUENCES } from './constants';
 import { Usb, Terminal, HeartCrack, CircleHelp, Flower, Mic, MicOff } from 'lucide-react';
 
 const App: React.FC = () => {
@@ -14,6 +13,7 @@ const App: React.FC = () => {
     logs,
     isRunningSequence,
     isListening,
+    sequences,
     connect,
     disconnect,
     moveServo,
@@ -27,7 +27,7 @@ const App: React.FC = () => {
   const [isHelpOpen, setIsHelpOpen] = useState(false);
 
   const handleSequence = (name: string) => {
-    const seq = (SEQUENCES as any)[name];
+    const seq = sequences[name];
     if (seq) {
       runSequence(seq);
     }
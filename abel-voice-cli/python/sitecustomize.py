@@ -0,0 +1,240 @@
+"""Safety envelope and emergency stop for generated scripts.
+
+abel-voice puts this directory on PYTHONPATH so Python imports it at startup,
+and passes the envelope as JSON in ABEL_SAFETY. Every servo command written to
+a serial port is checked with the same rules as the Rust driver before any of
+it reaches the arm, starting from the pose the firmware reports when the
+script asks for it. Like the firmware, the check works on whole lines: a line
+split across writes is held back until it is complete, so only checked lines
+reach the port. On SIGINT or SIGTERM the firmware stop command is sent to
+every port the script has used, so motion in flight halts with the script.
+
+For dry runs ABEL_DRY_RUN names a file instead: serial.Serial is replaced by a
+recorder that answers like the firmware, time.sleep only advances a virtual
+clock, and every command is written to that file with its virtual timestamp
+and the write it came in when the script exits. The Rust side checks the recording afterwards.
+"""
+
+import atexit
+import itertools
+import json
+import os
+import re
+import signal
+import sys
+import time
+import types
+import weakref
+
+# A number as the firmware's String.toInt() reads it: leading whitespace and a sign allowed
+NUMBER = re.compile(rb"[ \t\n\v\f\r]*[+-]?[0-9]+")
+# The firmware ends a command at either line ending
+LINE_END = re.compile(rb"[\r\n]")
+NAMES = ["Base", "Shoulder", "Elbow", "Gripper"]
+
+
+class SafetyViolation(Exception):
+    pass
+
+
+def parse_command(line):
+    """Servo and angle of a move or queued segment, split up the way the firmware does it
+
+    Moves are #<servo>M<angle>T<ms> and segments #<servo>Q<angle>T<ms>V<v0>,<vf>.
+    Returns None for lines that aren't servo commands, and raises SafetyViolation
+    for lines starting with '#' whose servo or angle can't be read.
+    """
+    if not line.startswith(b"#"):
+        return None
+
+    target = line.find(b"Q") if b"Q" in line else line.find(b"M")
+    end = line.find(b"T")
+    if 0 < target < end:
+        servo, angle = line[1:target], line[target + 1 : end]
+        if NUMBER.fullmatch(servo) and NUMBER.fullmatch(angle):
+            return int(servo), int(angle)
+
+    raise SafetyViolation(f"Unreadable servo command {line.decode(errors='replace')!r}")
+
+
+def check(envelope, positions, moves):
+    """Returns the pose after moves sent together, or raises SafetyViolation
+
+    The same rule as SafetyEnvelope::check in safety.rs: every line of one write
+    is one command, with limits and step checked per move and zones checked on
+    the pose the command ends in.
+    """
+    pose = list(positions)
+    for servo, angle in moves:
+        if not 0 <= servo < len(NAMES):
+            # The firmware rejects it with ERR
+            continue
+
+        name = NAMES[servo]
+        lo, hi = envelope["limits"][servo]
+        if not lo <= angle <= hi:
+            raise SafetyViolation(f"{name} angle {angle}° is outside its soft limits {lo}-{hi}°")
+
+        start = positions[servo]
+        if abs(angle - start) > envelope["max_step"]:
+            raise SafetyViolation(
+                f"{name} step {start}° -> {angle}° is larger than the "
+                f"{envelope['max_step']}° allowed in one command"
+            )
+
+        pose[servo] = angle
+
+    for zone in envelope["zones"]:
+        ranges = [(i, zone.get(n.lower())) for i, n in enumerate(NAMES)]
+        ranges = [(i, r) for i, r in ranges if r]
+        if ranges and all(lo <= pose[i] <= hi for i, (lo, hi) in ranges):
+            raise SafetyViolation(
+                f"Base {pose[0]}° Shoulder {pose[1]}° Elbow {pose[2]}° Gripper {pose[3]}° "
+                f"is in the forbidden zone '{zone['name']}'"
+            )
+
+    return pose
+
+
+def install(envelope):
+    import serial
+
+    positions = list(envelope["positions"])
+    ports = weakref.WeakSet()
+    # Written bytes after each port's last line end, not yet passed on
+    pending = weakref.WeakKeyDictionary()
+    write = serial.Serial.write
+    readline = serial.Serial.readline
+
+    def checked_write(self, data):
+        buffered = pending.pop(self, b"") + bytes(data)
+        end = max(buffered.rfind(b"\n"), buffered.rfind(b"\r")) + 1
+        lines, rest = buffered[:end], buffered[end:]
+
+        moves = [command for command in map(parse_command, LINE_END.split(lines)) if command]
+        positions[:] = check(envelope, positions, moves)
+        pending[self] = rest
+        ports.add(self)
+
+        if lines:
+            write(self, lines)
+        return len(data)
+
+    def tracking_readline(self, *args, **kwargs):
+        line = readline(self, *args, **kwargs)
+        # STATUS <servo> <current> <target> <velocity> <moving>, the reply to "?"
+        words = line.split()
+        if len(words) == 6 and words[0] == b"STATUS":
+            try:
+                positions[int(words[1])] = round(float(words[3]))
+            except (ValueError, IndexError):
+                pass
+        return line
+
+    def halt(signum, frame):
+        for port in list(ports):
+            try:
+                write(port, b"!\n")
+                port.flush()
+            except Exception:
+                pass
+        os._exit(128 + signum)
+
+    serial.Serial.write = checked_write
+    serial.Serial.readline = tracking_readline
+    signal.signal(signal.SIGINT, halt)
+    signal.signal(signal.SIGTERM, halt)
+
+
+def install_recorder(path, positions):
+    positions = list(positions)
+    clock = [0.0]
+    commands = []
+    # Numbers each write, since the lines of one write are checked as one command
+    writes = itertools.count()
+    started = time.time()
+
+    def sleep(seconds):
+        clock[0] += max(0.0, seconds)
+
+    class Recorder:
+        """Stands in for serial.Serial without touching any hardware"""
+
+        def __init__(self, port=None, baudrate=9600, timeout=None, *args, **kwargs):
+            self.port = port
+            self.baudrate = baudrate
+            self.timeout = timeout
+            self.is_open = True
+            self.replies = []
+
+        def write(self, data):
+            write = next(writes)
+            for line in bytes(data).decode(errors="replace").splitlines():
+                line = line.strip()
+                if not line:
+                    continue
+                commands.append({"t_ms": clock[0] * 1000.0, "write": write, "port": self.port, "command": line})
+                if line == "?":
+                    for servo, angle in enumerate(positions):
+                        self.replies.append(f"STATUS {servo} {angle:.1f} {angle:.1f} 0.0 0\n".encode())
+                    continue
+                # Unreadable commands fail here as they would on the arm
+                command = parse_command(line.encode())
+                if command and 0 <= command[0] < len(positions):
+                    positions[command[0]] = command[1]
+            return len(data)
+
+        def readline(self, *args, **kwargs):
+            if self.replies:
+                return self.replies.pop(0)
+            # Nothing to say, so the read times out
+            sleep(self.timeout if self.timeout is not None else 1.0)
+            return b""
+
+        def read(self, size=1):
+            return self.readline()[:size]
+
+        @property
+        def in_waiting(self):
+            return sum(len(reply) for reply in self.replies)
+
+        def reset_input_buffer(self):
+            self.replies.clear()
+
+        def flush(self):
+            pass
+
+        def close(self):
+            self.is_open = False
+
+        def __enter__(self):
+            return self
+
+        def __exit__(self, *exc):
+            self.close()
+
+    module = types.ModuleType("serial")
+    module.Serial = Recorder
+    module.SerialException = OSError
+    sys.modules["serial"] = module
+
+    time.sleep = sleep
+    time.time = lambda: started + clock[0]
+    time.monotonic = lambda: clock[0]
+    time.perf_counter = lambda: clock[0]
+
+    def save():
+        with open(path, "w") as recording:
+            json.dump({"duration_ms": clock[0] * 1000.0, "commands": commands}, recording)
+
+    atexit.register(save)
+
+
+if os.environ.get("ABEL_DRY_RUN"):
+    install_recorder(os.environ["ABEL_DRY_RUN"], json.loads(os.environ["ABEL_SAFETY"])["positions"])
+elif os.environ.get("ABEL_SAFETY"):
+    try:
+        install(json.loads(os.environ["ABEL_SAFETY"]))
+    except ImportError:
+        # No pyserial, so the script can't reach the arm anyway
+        pass
@@ -77,7 +77,7 @@ impl DeepgramClient {
             let mut played = false;
 
             for player in &players {
-                if let Ok(_) = Command::new(player).arg(path).output() {
+                if Command::new(player).arg(path).output().is_ok() {
                     played = true;
                     break;
                 }
@@ -91,7 +91,7 @@ impl DeepgramClient {
         #[cfg(target_os = "windows")]
         {
             Command::new("powershell")
-                .args(&[
+                .args([
                     "-c",
                     &format!(
                         "(New-Object Media.SoundPlayer '{}').PlaySync()",
@@ -0,0 +1,528 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::sync::mpsc::RecvTimeoutError;
+use std::time::{Duration, Instant};
+
+use crate::estop::StopListener;
+use crate::firmware::{FirmwareEvent, FirmwareReader, ServoStatus};
+use crate::profile::CalibrationProfile;
+use crate::safety::SafetyEnvelope;
+use crate::serial::SerialConfig;
+use crate::trajectory;
+
+/// Shortest and longest move duration accepted by the firmware
+pub const MIN_DURATION: Duration = Duration::from_millis(100);
+pub const MAX_DURATION: Duration = Duration::from_millis(10_000);
+
+/// Highest angle accepted by the firmware
+pub const MAX_ANGLE: u8 = 180;
+
+/// Angle every servo is driven to when the firmware boots
+pub const HOME_ANGLE: u8 = 90;
+
+/// How long to wait for the firmware to print `Ready.` after the port opens
+const BOOT_TIMEOUT: Duration = Duration::from_millis(2500);
+
+/// How long to wait for the firmware to acknowledge a command
+const REPLY_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Extra time given for a queued segment to start before the next one is sent
+const QUEUE_MARGIN: Duration = Duration::from_millis(20);
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[serde(try_from = "u8", into = "u8")]
+pub enum ServoId {
+    Base = 0,
+    Shoulder = 1,
+    Elbow = 2,
+    Gripper = 3,
+}
+
+impl ServoId {
+    pub const ALL: [ServoId; 4] = [
+        ServoId::Base,
+        ServoId::Shoulder,
+        ServoId::Elbow,
+        ServoId::Gripper,
+    ];
+
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ServoId::Base => "Base",
+            ServoId::Shoulder => "Shoulder",
+            ServoId::Elbow => "Elbow",
+            ServoId::Gripper => "Gripper",
+        }
+    }
+}
+
+impl TryFrom<u8> for ServoId {
+    type Error = anyhow::Error;
+
+    fn try_from(id: u8) -> Result<Self> {
+        match id {
+            0 => Ok(ServoId::Base),
+            1 => Ok(ServoId::Shoulder),
+            2 => Ok(ServoId::Elbow),
+            3 => Ok(ServoId::Gripper),
+            _ => anyhow::bail!("Invalid servo {} (0-3)", id),
+        }
+    }
+}
+
+impl From<ServoId> for u8 {
+    fn from(servo: ServoId) -> u8 {
+        servo as u8
+    }
+}
+
+impl std::fmt::Display for ServoId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({})", self.name(), *self as u8)
+    }
+}
+
+/// Builds a `#<servo>M<angle>T<duration>` command, applying the firmware's range checks
+pub fn format_move(servo: ServoId, angle: u8, duration: Duration) -> Result<String> {
+    if angle > MAX_ANGLE {
+        anyhow::bail!("Angle {} out of range (0-{})", angle, MAX_ANGLE);
+    }
+    if duration < MIN_DURATION || duration > MAX_DURATION {
+        anyhow::bail!(
+            "Duration {}ms out of range ({}-{}ms)",
+            duration.as_millis(),
+            MIN_DURATION.as_millis(),
+            MAX_DURATION.as_millis()
+        );
+    }
+
+    Ok(format!(
+        "#{}M{}T{}\n",
+        servo as u8,
+        angle,
+        duration.as_millis()
+    ))
+}
+
+/// Builds a `#<servo>Q<angle>T<duration>V<v0>,<vf>` trajectory segment (firmware v4.3)
+pub fn format_segment(servo: ServoId, segment: &trajectory::Segment) -> Result<String> {
+    let angle = segment.target.round() as u8;
+    format_move(servo, angle, segment.duration)?;
+
+    Ok(format!(
+        "#{}Q{}T{}V{:.1},{:.1}\n",
+        servo as u8,
+        angle,
+        segment.duration.as_millis(),
+        segment.start_velocity,
+        segment.end_velocity
+    ))
+}
+
+/// Spline through `waypoints` (full poses) from `positions`, over the joints that move
+///
+/// `min_durations` optionally sets the least time to reach each waypoint.
+pub fn plan_trajectory(
+    positions: [u8; 4],
+    waypoints: &[[u8; 4]],
+    min_durations: &[Option<Duration>],
+) -> (Vec<ServoId>, trajectory::Spline) {
+    let joints: Vec<ServoId> = ServoId::ALL
+        .into_iter()
+        .filter(|servo| waypoints.iter().any(|w| w[servo.index()] != positions[servo.index()]))
+        .collect();
+
+    let start: Vec<f32> = joints.iter().map(|servo| positions[servo.index()] as f32).collect();
+    let points: Vec<Vec<f32>> = waypoints
+        .iter()
+        .map(|w| joints.iter().map(|servo| w[servo.index()] as f32).collect())
+        .collect();
+
+    (joints, trajectory::Spline::plan(&start, &points, min_durations))
+}
+
+/// Servos whose angle differs between two poses, with their new angle
+pub fn pose_changes(from: [u8; 4], to: [u8; 4]) -> Vec<(ServoId, u8)> {
+    ServoId::ALL
+        .into_iter()
+        .filter(|servo| from[servo.index()] != to[servo.index()])
+        .map(|servo| (servo, to[servo.index()]))
+        .collect()
+}
+
+/// Movement duration scaled by angular distance, same as the generated Python helpers
+pub fn calculate_duration(from: u8, to: u8) -> Duration {
+    let distance = (to as i32 - from as i32).unsigned_abs() as u64;
+    Duration::from_millis((distance * 12).clamp(400, 3000))
+}
+
+/// Duration for servos moving together from `positions`, multiplied by `scale`
+///
+/// Long enough that the firmware won't stretch any single move, or they'd arrive at different times.
+pub fn coordinated_duration(positions: [u8; 4], moves: &[(ServoId, u8)], scale: f32) -> Duration {
+    moves
+        .iter()
+        .map(|&(servo, angle)| {
+            let from = positions[servo.index()];
+            trajectory::firmware_duration(angle as f32 - from as f32, calculate_duration(from, angle))
+        })
+        .max()
+        .unwrap_or(Duration::ZERO)
+        .mul_f32(scale.max(1.0))
+        .min(MAX_DURATION)
+}
+
+/// Pose and motion of the whole arm from the last status query
+#[derive(Serialize, Clone, Copy, Debug)]
+pub struct ArmState {
+    pub servos: [ServoStatus; 4],
+    pub moving: bool,
+}
+
+impl ArmState {
+    fn from_status(servos: [ServoStatus; 4]) -> Self {
+        Self {
+            moving: servos.iter().any(|s| s.moving),
+            servos,
+        }
+    }
+}
+
+/// The reader thread has exited, so the port is gone
+fn port_closed() -> anyhow::Error {
+    std::io::Error::new(std::io::ErrorKind::BrokenPipe, "Serial port closed").into()
+}
+
+/// Owns the serial connection to the arm and speaks the firmware protocol
+pub struct ArmDriver {
+    port: Box<dyn serialport::SerialPort>,
+    events: FirmwareReader,
+    positions: [u8; 4],
+    profile: CalibrationProfile,
+    envelope: SafetyEnvelope,
+}
+
+impl ArmDriver {
+    /// Opens the arm's serial port using the saved calibration profile
+    pub fn open(serial: &SerialConfig) -> Result<Self> {
+        Self::open_with_profile(serial, CalibrationProfile::load()?)
+    }
+
+    /// Opens the arm's serial port and waits for the board to finish booting
+    pub fn open_with_profile(serial: &SerialConfig, profile: CalibrationProfile) -> Result<Self> {
+        let port = serial.open()?;
+        let reader = port.try_clone().context("Failed to clone serial port")?;
+
+        let mut driver = Self {
+            port,
+            events: FirmwareReader::spawn(reader)?,
+            positions: [HOME_ANGLE; 4],
+            envelope: SafetyEnvelope::new(&profile),
+            profile,
+        };
+
+        // Opening the port resets the ESP32; boards that don't reset never print `Ready.`
+        driver.wait_for_ready(BOOT_TIMEOUT);
+
+        // Start from where the arm really is; firmware before v4.2 can't tell us, so assume home
+        let _ = driver.poll_state();
+
+        Ok(driver)
+    }
+
+    fn wait_for_ready(&self, timeout: Duration) {
+        let deadline = Instant::now() + timeout;
+
+        while let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+            match self.events.recv_timeout(remaining) {
+                Ok(FirmwareEvent::Ready) | Err(_) => break,
+                Ok(_) => continue,
+            }
+        }
+    }
+
+    /// Last commanded angle of a servo
+    pub fn position(&self, servo: ServoId) -> u8 {
+        self.positions[servo.index()]
+    }
+
+    /// Last commanded angle of every servo, indexed by servo number
+    pub fn positions(&self) -> [u8; 4] {
+        self.positions
+    }
+
+    pub fn profile(&self) -> &CalibrationProfile {
+        &self.profile
+    }
+
+    pub fn set_profile(&mut self, profile: CalibrationProfile) {
+        self.envelope = SafetyEnvelope::new(&profile);
+        self.profile = profile;
+    }
+
+    /// Builds the command with the servo's trim applied
+    fn command(&self, servo: ServoId, angle: u8, duration: Duration) -> Result<String> {
+        format_move(servo, self.profile.servo(servo).trimmed(angle), duration)
+    }
+
+    /// Moves the arm to a pose saved in the calibration profile
+    pub fn move_pose(&mut self, name: &str) -> Result<Duration> {
+        let moves = self.profile.pose(name)?.moves();
+        self.move_coordinated(&moves)
+    }
+
+    /// Returns every servo to its calibrated home angle
+    pub fn home(&mut self) -> Result<Duration> {
+        let moves = self.profile.home_moves();
+        self.move_coordinated(&moves)
+    }
+
+    /// Sends a single-servo move and returns how long the firmware will take
+    ///
+    /// The returned duration includes any adjustment the firmware made to respect
+    /// its velocity and acceleration limits.
+    pub fn move_servo(&mut self, servo: ServoId, angle: u8, duration: Duration) -> Result<Duration> {
+        self.envelope.check(self.positions, &[(servo, angle)])?;
+        let command = self.command(servo, angle, duration)?;
+        self.send(&command)?;
+        let duration = self.await_motion()?;
+        self.positions[servo.index()] = angle;
+
+        Ok(duration)
+    }
+
+    /// Moves several servos together so they all arrive at the same time
+    pub fn move_coordinated(&mut self, moves: &[(ServoId, u8)]) -> Result<Duration> {
+        self.move_coordinated_scaled(moves, 1.0)
+    }
+
+    /// Coordinated move with its duration multiplied by `scale`, e.g. 2.0 for half speed
+    pub fn move_coordinated_scaled(&mut self, moves: &[(ServoId, u8)], scale: f32) -> Result<Duration> {
+        let duration = coordinated_duration(self.positions, moves, scale);
+        self.move_coordinated_over(moves, duration)
+    }
+
+    /// Coordinated move that takes `duration`, as far as the firmware's limits allow
+    pub fn move_coordinated_over(&mut self, moves: &[(ServoId, u8)], duration: Duration) -> Result<Duration> {
+        self.envelope.check(self.positions, moves)?;
+
+        // Build everything up front so a bad entry doesn't leave the arm half-moved
+        let commands = moves
+            .iter()
+            .map(|&(servo, angle)| self.command(servo, angle, duration))
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut longest = Duration::ZERO;
+        for (command, &(servo, angle)) in commands.iter().zip(moves) {
+            self.send(command)?;
+            longest = longest.max(self.await_motion()?);
+            self.positions[servo.index()] = angle;
+        }
+
+        Ok(longest)
+    }
+
+    /// Moves through `waypoints` (full poses) without stopping at each one
+    ///
+    /// The path is planned as a spline and streamed to the firmware as queued segments,
+    /// never more than it can hold. Returns how long until the last waypoint is reached.
+    /// Firmware before v4.3 has no segment command, so the arm visits the waypoints one
+    /// after another instead, stopping at each. Either way a stop while waiting for the
+    /// firmware halts the arm and returns an error.
+    pub fn move_trajectory(
+        &mut self,
+        waypoints: &[[u8; 4]],
+        min_durations: &[Option<Duration>],
+        stop: &StopListener,
+    ) -> Result<Duration> {
+        let mut pose = self.positions;
+        for &waypoint in waypoints {
+            pose = self.envelope.check(pose, &pose_changes(pose, waypoint))?;
+        }
+
+        let (joints, spline) = plan_trajectory(self.positions, waypoints, min_durations);
+        let Some(&last) = waypoints.last().filter(|_| !joints.is_empty()) else {
+            return Ok(Duration::ZERO);
+        };
+
+        // Build everything up front so a bad entry doesn't leave the arm half-moved
+        let commands = (0..spline.durations.len())
+            .map(|i| {
+                joints
+                    .iter()
+                    .zip(&spline.segments)
+                    .map(|(&servo, segments)| {
+                        let segment = trajectory::Segment {
+                            target: self.profile.servo(servo).trimmed(segments[i].target.round() as u8) as f32,
+                            ..segments[i]
+                        };
+                        format_segment(servo, &segment)
+                    })
+                    .collect::<Result<Vec<_>>>()
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let ends = spline.ends();
+        let mut started: Option<Instant> = None;
+        for (i, segments) in commands.iter().enumerate() {
+            // Segment i fits in the queue once segment i - SEGMENT_QUEUE - 1 has finished
+            let mut wait = Duration::ZERO;
+            if let (Some(start), Some(done)) = (started, i.checked_sub(trajectory::SEGMENT_QUEUE + 1)) {
+                let ready = start + ends[done] + QUEUE_MARGIN;
+                wait = ready.saturating_duration_since(Instant::now());
+            }
+            self.sleep_unless_stopped(wait, stop)?;
+
+            for command in segments {
+                self.send(command)?;
+                match self.await_segment()? {
+                    Ok(()) => {}
+                    Err(reason) if started.is_none() && reason == "Invalid command format" => {
+                        return self.move_waypoints(waypoints, &spline.durations, stop);
+                    }
+                    Err(reason) => {
+                        // Don't leave the joints that were already sent running on their own
+                        if started.is_some() {
+                            self.stop()?;
+                        }
+                        anyhow::bail!("Firmware rejected trajectory segment: {}", reason);
+                    }
+                }
+                started.get_or_insert_with(Instant::now);
+            }
+        }
+
+        self.positions = last;
+        let end = started.unwrap_or_else(Instant::now) + spline.duration();
+        Ok(end.saturating_duration_since(Instant::now()))
+    }
+
+    /// Point-to-point fallback for `move_trajectory`, waiting out every move but the last
+    fn move_waypoints(&mut self, waypoints: &[[u8; 4]], durations: &[Duration], stop: &StopListener) -> Result<Duration> {
+        let mut remaining = Duration::ZERO;
+        for (i, (&waypoint, &duration)) in waypoints.iter().zip(durations).enumerate() {
+            self.sleep_unless_stopped(remaining, stop)?;
+            let moves = pose_changes(self.positions, waypoint);
+            remaining = if moves.is_empty() {
+                duration
+            } else {
+                self.move_coordinated_over(&moves, duration)?
+            };
+            if i + 1 < waypoints.len() {
+                remaining += QUEUE_MARGIN;
+            }
+        }
+        Ok(remaining)
+    }
+
+    /// Waits for the firmware while holding the port, halting the arm if a stop comes in
+    ///
+    /// Whoever triggered the stop can't reach the driver until this returns.
+    fn sleep_unless_stopped(&mut self, duration: Duration, stop: &StopListener) -> Result<()> {
+        if stop.sleep(duration) {
+            self.stop()?;
+            anyhow::bail!("Trajectory stopped by emergency stop");
+        }
+        Ok(())
+    }
+
+    /// Asks the firmware where every servo is and how it is moving
+    ///
+    /// Commanded positions are resynchronised to the reported targets, so later
+    /// moves and safety checks start from the real pose.
+    pub fn poll_state(&mut self) -> Result<ArmState> {
+        self.send("?\n")?;
+
+        let mut servos: [Option<ServoStatus>; 4] = [None; 4];
+        let deadline = Instant::now() + REPLY_TIMEOUT;
+        while let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+            match self.events.recv_timeout(remaining) {
+                Ok(FirmwareEvent::Status(status)) => {
+                    servos[status.servo.index()] = Some(status);
+                    if servos.iter().all(Option::is_some) {
+                        let state = ArmState::from_status(servos.map(Option::unwrap));
+                        for status in state.servos {
+                            self.positions[status.servo.index()] =
+                                self.profile.servo(status.servo).untrimmed(status.target);
+                        }
+                        return Ok(state);
+                    }
+                }
+                Ok(_) => continue,
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => return Err(port_closed()),
+            }
+        }
+
+        anyhow::bail!("Firmware did not report its status; it may predate v4.2")
+    }
+
+    /// Emergency stop: the firmware holds every servo wherever it is along its trajectory
+    ///
+    /// Returns the angles the servos stopped at, which become the new positions.
+    pub fn stop(&mut self) -> Result<[u8; 4]> {
+        self.send("!\n")?;
+
+        let deadline = Instant::now() + REPLY_TIMEOUT;
+        while let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+            match self.events.recv_timeout(remaining) {
+                Ok(FirmwareEvent::Stopped(angles)) => {
+                    for servo in ServoId::ALL {
+                        let held = angles[servo.index()];
+                        self.positions[servo.index()] = self.profile.servo(servo).untrimmed(held);
+                    }
+                    return Ok(self.positions);
+                }
+                Ok(_) => continue,
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => return Err(port_closed()),
+            }
+        }
+
+        anyhow::bail!("Firmware did not acknowledge the stop; it may predate v4.1")
+    }
+
+    /// Waits for the firmware to accept or reject the last command
+    fn await_motion(&self) -> Result<Duration> {
+        loop {
+            match self.events.recv_timeout(REPLY_TIMEOUT) {
+                Ok(FirmwareEvent::Motion { duration, .. }) => return Ok(duration),
+                Ok(FirmwareEvent::Error(reason)) => {
+                    anyhow::bail!("Firmware rejected command: {}", reason)
+                }
+                Ok(_) => continue,
+                Err(RecvTimeoutError::Timeout) => anyhow::bail!("No reply from firmware"),
+                Err(RecvTimeoutError::Disconnected) => return Err(port_closed()),
+            }
+        }
+    }
+
+    /// Waits for the firmware to queue the last segment, or the reason it rejected it
+    fn await_segment(&self) -> Result<std::result::Result<(), String>> {
+        loop {
+            match self.events.recv_timeout(REPLY_TIMEOUT) {
+                Ok(FirmwareEvent::Segment { .. }) => return Ok(Ok(())),
+                Ok(FirmwareEvent::Error(reason)) => return Ok(Err(reason)),
+                Ok(_) => continue,
+                Err(RecvTimeoutError::Timeout) => anyhow::bail!("No reply from firmware"),
+                Err(RecvTimeoutError::Disconnected) => return Err(port_closed()),
+            }
+        }
+    }
+
+    fn send(&mut self, command: &str) -> Result<()> {
+        // Discard replies to earlier commands so they aren't mistaken for this one
+        while self.events.try_recv().is_some() {}
+
+        self.port
+            .write_all(command.as_bytes())
+            .context("Failed to write to serial port")?;
+        self.port.flush().context("Failed to flush serial port")?;
+        Ok(())
+    }
+}
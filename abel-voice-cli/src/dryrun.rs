@@ -0,0 +1,298 @@
+use colored::Colorize;
+use serde::Deserialize;
+use std::time::Duration;
+
+use crate::driver::ServoId;
+use crate::safety::SafetyEnvelope;
+use crate::trajectory;
+
+/// Commands a dry run sent, as written by the recorder in `sitecustomize.py`
+#[derive(Deserialize, Debug, Default)]
+pub struct Recording {
+    /// Virtual time the script ran for, including its sleeps
+    pub duration_ms: f64,
+    pub commands: Vec<RecordedCommand>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct RecordedCommand {
+    /// Virtual time the command was written
+    pub t_ms: f64,
+    /// Commands sent in the same write are one command to the safety envelope
+    pub write: usize,
+    pub command: String,
+}
+
+/// One servo move as the firmware would run it
+pub struct TimelineMove {
+    pub at: Duration,
+    pub servo: ServoId,
+    pub from: u8,
+    pub to: u8,
+    /// After the firmware's velocity and acceleration adjustment
+    pub duration: Duration,
+}
+
+/// What a dry run would have done to the arm
+#[derive(Default)]
+pub struct DryRunReport {
+    pub moves: Vec<TimelineMove>,
+    /// Times the firmware stop command was sent
+    pub stops: Vec<Duration>,
+    pub violations: Vec<(Duration, String)>,
+    /// Moves that start while the same servo is still finishing the previous one
+    pub overlaps: usize,
+    pub duration: Duration,
+}
+
+/// Replays a recording from `start` against the safety envelope and the firmware's timing
+///
+/// Each write is checked as one command, the way the script shim checks it.
+pub fn analyze(recording: &Recording, envelope: &SafetyEnvelope, start: [u8; 4]) -> DryRunReport {
+    let mut report = DryRunReport {
+        duration: Duration::from_secs_f64(recording.duration_ms.max(0.0) / 1000.0),
+        ..Default::default()
+    };
+    let mut pose = start;
+    let mut busy_until = [Duration::ZERO; 4];
+
+    for group in recording.commands.chunk_by(|a, b| a.write == b.write) {
+        let mut moves = Vec::new();
+        for recorded in group {
+            let at = Duration::from_secs_f64(recorded.t_ms.max(0.0) / 1000.0);
+
+            if recorded.command == "!" {
+                report.stops.push(at);
+                busy_until = [at; 4];
+                continue;
+            }
+            if !recorded.command.starts_with('#') {
+                continue;
+            }
+            let Some((servo, angle, requested, queued)) = parse_move(&recorded.command) else {
+                // The script shim refuses these, so the script would fail here on the arm
+                report
+                    .violations
+                    .push((at, format!("Unreadable servo command '{}'", recorded.command)));
+                continue;
+            };
+            let Some(servo) = u8::try_from(servo).ok().and_then(|s| ServoId::try_from(s).ok()) else {
+                report
+                    .violations
+                    .push((at, format!("Servo {} does not exist (0-3)", servo)));
+                continue;
+            };
+            let Ok(angle) = u8::try_from(angle) else {
+                let [min, max] = envelope.limits[servo.index()];
+                report.violations.push((
+                    at,
+                    format!("{} angle {}° is outside its soft limits {}-{}°", servo.name(), angle, min, max),
+                ));
+                continue;
+            };
+            moves.push((at, servo, angle, requested, queued));
+        }
+
+        let targets: Vec<(ServoId, u8)> = moves.iter().map(|&(_, servo, angle, _, _)| (servo, angle)).collect();
+        if let (Some(&(at, ..)), Err(violation)) = (moves.first(), envelope.check(pose, &targets)) {
+            report.violations.push((at, violation.to_string()));
+        }
+
+        for (at, servo, angle, requested, queued) in moves {
+            let from = pose[servo.index()];
+            let (at, duration) = if queued {
+                // Segments wait for the one before and run exactly as long as they ask
+                (at.max(busy_until[servo.index()]), requested)
+            } else {
+                if at < busy_until[servo.index()] {
+                    report.overlaps += 1;
+                }
+                (at, trajectory::firmware_duration(angle as f32 - from as f32, requested))
+            };
+            busy_until[servo.index()] = at + duration;
+            report.duration = report.duration.max(at + duration);
+
+            report.moves.push(TimelineMove {
+                at,
+                servo,
+                from,
+                to: angle,
+                duration,
+            });
+            pose[servo.index()] = angle;
+        }
+    }
+
+    report
+}
+
+/// `#<servo>M<angle>T<duration>`, or a queued `#<servo>Q<angle>T<duration>V<v0>,<vf>` segment
+///
+/// Split up as the firmware does it, with every number read by `firmware_int`.
+fn parse_move(command: &str) -> Option<(i64, i64, Duration, bool)> {
+    let command = command.strip_prefix('#')?;
+    let queued = command.contains('Q');
+    let (servo, rest) = command.split_once(if queued { 'Q' } else { 'M' })?;
+    let (angle, rest) = rest.split_once('T')?;
+    let duration = if queued { rest.split_once('V')?.0 } else { rest };
+    Some((
+        firmware_int(servo)?,
+        firmware_int(angle)?,
+        Duration::from_millis(firmware_int(duration)?.max(0) as u64),
+        queued,
+    ))
+}
+
+/// A whole field as the firmware's `String::toInt` reads it: leading whitespace and a sign allowed
+///
+/// Matches `NUMBER` in `sitecustomize.py`.
+fn firmware_int(text: &str) -> Option<i64> {
+    let text = text.trim_start_matches([' ', '\t', '\n', '\x0b', '\x0c', '\r']);
+    let digits = text.strip_prefix(['+', '-']).unwrap_or(text);
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    text.parse().ok()
+}
+
+impl DryRunReport {
+    pub fn print(&self) {
+        println!("{}", "Dry run timeline:".bright_white().underline());
+
+        let mut stops = self.stops.iter().peekable();
+        for m in &self.moves {
+            while let Some(stop) = stops.next_if(|&&stop| stop <= m.at) {
+                println!("  {:>7.2}s  {}", stop.as_secs_f32(), "Stop".red());
+            }
+            println!(
+                "  {:>7.2}s  {:<8} {:>3}° → {:>3}°  over {:.2}s",
+                m.at.as_secs_f32(),
+                m.servo.name(),
+                m.from,
+                m.to,
+                m.duration.as_secs_f32()
+            );
+        }
+        for stop in stops {
+            println!("  {:>7.2}s  {}", stop.as_secs_f32(), "Stop".red());
+        }
+
+        println!();
+        println!(
+            "{}: {:.1}s, {} moves",
+            "Total duration".bright_white(),
+            self.duration.as_secs_f32(),
+            self.moves.len()
+        );
+        if self.overlaps > 0 {
+            println!(
+                "{} {} moves start before the same servo finished its previous move",
+                "⚠".yellow(),
+                self.overlaps
+            );
+        }
+
+        if self.violations.is_empty() {
+            println!("{} No safety violations", "✓".green());
+        } else {
+            println!("{} {} safety violations:", "✗".red(), self.violations.len());
+            for (at, violation) in &self.violations {
+                println!("  {:>7.2}s  {}", at.as_secs_f32(), violation);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::profile::CalibrationProfile;
+
+    /// Commands as (t_ms, write, command)
+    fn replay(commands: &[(f64, usize, &str)]) -> DryRunReport {
+        let recording = Recording {
+            duration_ms: 0.0,
+            commands: commands
+                .iter()
+                .map(|&(t_ms, write, command)| RecordedCommand {
+                    t_ms,
+                    write,
+                    command: command.to_string(),
+                })
+                .collect(),
+        };
+        analyze(&recording, &SafetyEnvelope::new(&CalibrationProfile::default()), [90; 4])
+    }
+
+    #[test]
+    fn firmware_int_allows_leading_whitespace_and_a_sign() {
+        assert_eq!(firmware_int("45"), Some(45));
+        assert_eq!(firmware_int(" 45"), Some(45));
+        assert_eq!(firmware_int("\t+0"), Some(0));
+        assert_eq!(firmware_int("-5"), Some(-5));
+        assert_eq!(firmware_int("45 "), None);
+        assert_eq!(firmware_int("+"), None);
+        assert_eq!(firmware_int("4_5"), None);
+        assert_eq!(firmware_int(""), None);
+    }
+
+    #[test]
+    fn parse_move_reads_moves_and_segments() {
+        assert_eq!(parse_move("#1M90T800"), Some((1, 90, Duration::from_millis(800), false)));
+        assert_eq!(parse_move("#2Q45T300V0.0,-12.5"), Some((2, 45, Duration::from_millis(300), true)));
+        assert_eq!(parse_move("#1M 0T500"), Some((1, 0, Duration::from_millis(500), false)));
+        assert_eq!(parse_move("#1M+0T500"), Some((1, 0, Duration::from_millis(500), false)));
+        assert_eq!(parse_move("#1Mx5T500"), None);
+        assert_eq!(parse_move("#1M90"), None);
+        assert_eq!(parse_move("?"), None);
+    }
+
+    #[test]
+    fn angles_with_whitespace_or_a_sign_are_still_checked() {
+        for command in ["#1M 0T500", "#1M+0T500", "#1M -5T500"] {
+            let report = replay(&[(0.0, 0, command)]);
+            assert_eq!(report.violations.len(), 1, "{}", command);
+            assert!(report.violations[0].1.contains("soft limits"), "{}", report.violations[0].1);
+        }
+    }
+
+    #[test]
+    fn unreadable_commands_are_violations() {
+        let report = replay(&[(0.0, 0, "#1Mx5T500"), (0.0, 1, "?"), (0.0, 2, "#7M90T500")]);
+        let messages: Vec<&str> = report.violations.iter().map(|(_, m)| m.as_str()).collect();
+        assert_eq!(messages, ["Unreadable servo command '#1Mx5T500'", "Servo 7 does not exist (0-3)"]);
+        assert!(report.moves.is_empty());
+    }
+
+    #[test]
+    fn one_write_is_checked_as_one_command() {
+        // Shoulder to 15 with the elbow at 50 is in the default zone, but only on the way
+        let together = replay(&[(0.0, 0, "#2M50T500"), (0.0, 0, "#1M15T500"), (0.0, 0, "#1M90T500")]);
+        assert!(together.violations.is_empty());
+
+        let apart = replay(&[(0.0, 0, "#2M50T500"), (0.0, 1, "#1M15T500"), (0.0, 2, "#1M90T500")]);
+        assert_eq!(apart.violations.len(), 1);
+        assert!(apart.violations[0].1.contains("forbidden zone"));
+    }
+
+    #[test]
+    fn moves_follow_the_firmware_timing() {
+        let report = replay(&[
+            (0.0, 0, "#0M100T100"),
+            (50.0, 1, "#0M110T1000"),
+            (2000.0, 2, "!"),
+            (2000.0, 3, "#1Q80T300V0,0"),
+            (2000.0, 3, "#1Q70T300V0,0"),
+        ]);
+
+        assert!(report.violations.is_empty());
+        assert_eq!(report.stops, [Duration::from_secs(2)]);
+        // The firmware stretches the first move, so the second starts while it still runs
+        assert_eq!(report.moves[0].duration, trajectory::firmware_duration(10.0, Duration::from_millis(100)));
+        assert!(report.moves[0].duration > Duration::from_millis(100));
+        assert_eq!(report.overlaps, 1);
+        // Queued segments run back to back for exactly as long as they ask
+        assert_eq!(report.moves[3].at, report.moves[2].at + Duration::from_millis(300));
+        assert_eq!((report.moves[3].from, report.moves[3].to), (80, 70));
+    }
+}
@@ -0,0 +1,84 @@
+use colored::Colorize;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+
+/// How often blocking waits check for a stop
+const STOP_POLL: Duration = Duration::from_millis(20);
+
+/// Emergency-stop broadcast shared by everything that can run motion
+///
+/// Each trigger is a pulse: listeners created before it fire, later ones don't,
+/// so nothing has to be reset after a stop.
+#[derive(Clone)]
+pub struct StopSignal(Arc<watch::Sender<u64>>);
+
+impl Default for StopSignal {
+    fn default() -> Self {
+        Self(Arc::new(watch::channel(0).0))
+    }
+}
+
+impl StopSignal {
+    pub fn trigger(&self) {
+        self.0.send_modify(|stops| *stops += 1);
+    }
+
+    pub fn listen(&self) -> StopListener {
+        StopListener(self.0.subscribe())
+    }
+
+    /// Whether anything is currently listening, i.e. there is motion to interrupt
+    pub fn is_armed(&self) -> bool {
+        self.0.receiver_count() > 0
+    }
+}
+
+pub struct StopListener(watch::Receiver<u64>);
+
+impl StopListener {
+    /// Completes on the first trigger after the listener was created
+    pub async fn stopped(&mut self) {
+        if self.0.changed().await.is_err() {
+            // Nobody can trigger a stop any more
+            std::future::pending::<()>().await;
+        }
+    }
+
+    /// Non-blocking check for code running outside the async runtime
+    pub fn is_stopped(&self) -> bool {
+        self.0.has_changed().unwrap_or(false)
+    }
+
+    /// Blocks for `duration`, returning early with `true` if a stop comes first
+    pub fn sleep(&self, duration: Duration) -> bool {
+        let deadline = Instant::now() + duration;
+        loop {
+            if self.is_stopped() {
+                return true;
+            }
+            match deadline.checked_duration_since(Instant::now()) {
+                Some(remaining) => std::thread::sleep(remaining.min(STOP_POLL)),
+                None => return false,
+            }
+        }
+    }
+}
+
+/// Turns Ctrl+C into an emergency stop while something is running, and exits otherwise
+pub fn stop_on_ctrl_c(signal: &StopSignal) {
+    let signal = signal.clone();
+
+    tokio::spawn(async move {
+        while tokio::signal::ctrl_c().await.is_ok() {
+            if !signal.is_armed() {
+                println!();
+                std::process::exit(130);
+            }
+
+            println!();
+            println!("{}", "⛔ Emergency stop".red().bold());
+            signal.trigger();
+        }
+    });
+}
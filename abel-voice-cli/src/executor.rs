@@ -1,78 +1,255 @@
 use anyhow::{Context, Result};
+use serde::Serialize;
 use std::path::Path;
-use std::process::Command;
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, BufReader};
 
-/// Execute a Python script in a virtual environment
-pub async fn run_script(script_path: &Path) -> Result<String> {
-    // Ensure venv exists
-    let venv_path = get_or_create_venv().await?;
-
-    // Determine python executable in venv
-    let python_exe = if cfg!(windows) {
-        venv_path.join("Scripts").join("python.exe")
-    } else {
-        venv_path.join("bin").join("python")
-    };
+use crate::driver::HOME_ANGLE;
+use crate::dryrun::Recording;
+use crate::estop::StopSignal;
+use crate::safety::SafetyEnvelope;
+use crate::sandbox::{self, Sandbox, SandboxConfig};
+use crate::venv::PythonEnv;
 
-    // Execute the script
-    let output = Command::new(&python_exe)
-        .arg(script_path)
-        .output()
-        .context("Failed to execute Python script")?;
-
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        anyhow::bail!("Script execution failed:\n{}", stderr);
+/// How long a stopped script gets to halt the arm and exit before it is killed
+const STOP_GRACE: Duration = Duration::from_secs(1);
+
+/// A script ended by an emergency stop
+#[derive(Debug)]
+pub struct ScriptStopped {
+    /// Angles the firmware reported holding (trim included), if it answered
+    pub held: Option<[f32; 4]>,
+}
+
+impl std::fmt::Display for ScriptStopped {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Script stopped by emergency stop")
     }
+}
+
+impl std::error::Error for ScriptStopped {}
 
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    Ok(stdout.to_string())
+/// `sitecustomize.py` that checks every serial write against the safety envelope
+/// and sends the firmware stop command when the script is interrupted
+const SAFETY_SHIM: &str = include_str!("../python/sitecustomize.py");
+
+/// What the shim reads from `ABEL_SAFETY`
+#[derive(Serialize)]
+struct ShimConfig<'a> {
+    #[serde(flatten)]
+    envelope: &'a SafetyEnvelope,
+    /// Opening the port resets the ESP32, which drives every servo to 90°
+    positions: [u8; 4],
 }
 
-async fn get_or_create_venv() -> Result<std::path::PathBuf> {
-    let home = dirs::home_dir().context("Could not find home directory")?;
-    let venv_path = home.join(".abel-voice-venv");
+/// One line printed by a running script
+#[derive(Serialize, Clone, Debug)]
+#[serde(tag = "stream", content = "line", rename_all = "lowercase")]
+pub enum OutputLine {
+    Stdout(String),
+    Stderr(String),
+}
 
-    if !venv_path.exists() {
-        println!("Creating Python virtual environment...");
-        create_venv(&venv_path).await?;
-        install_dependencies(&venv_path).await?;
-    }
+/// How a script run ended
+#[derive(Serialize, Clone, Debug)]
+pub struct ExecutionReport {
+    /// `None` when the script was killed by a signal
+    pub exit_code: Option<i32>,
+    #[serde(rename = "duration_ms", serialize_with = "as_millis")]
+    pub duration: Duration,
+    pub stdout: String,
+    pub stderr: String,
+}
 
-    Ok(venv_path)
+fn as_millis<S: serde::Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u64(duration.as_millis() as u64)
+}
+
+/// Execute a Python script in a virtual environment
+///
+/// Each line the script prints is passed to `on_line` as it arrives. Servo commands
+/// the script writes are checked against `envelope`; a violation stops the script
+/// before the command is sent. Triggering `stop` or running past the sandbox
+/// timeout halts the arm and ends the script with an error.
+pub async fn run_script(
+    script_path: &Path,
+    envelope: &SafetyEnvelope,
+    stop: &StopSignal,
+    sandbox: &SandboxConfig,
+    on_line: impl FnMut(&OutputLine),
+) -> Result<ExecutionReport> {
+    let (report, _) = run_python(script_path, envelope, stop, sandbox, false, on_line).await?;
+    Ok(report)
 }
 
-async fn create_venv(path: &Path) -> Result<()> {
-    let output = Command::new("python3")
-        .args(&["-m", "venv", path.to_str().unwrap()])
-        .output()
-        .context("Failed to create virtual environment. Is python3 installed?")?;
+/// Execute a Python script with `serial.Serial` replaced by a recorder
+///
+/// Nothing is sent to the arm: the script's sleeps run on a virtual clock and every
+/// command it writes is returned in the recording, in the order and at the time it
+/// would have been sent.
+pub async fn dry_run_script(
+    script_path: &Path,
+    envelope: &SafetyEnvelope,
+    stop: &StopSignal,
+    sandbox: &SandboxConfig,
+    on_line: impl FnMut(&OutputLine),
+) -> Result<(ExecutionReport, Recording)> {
+    let (report, recording) = run_python(script_path, envelope, stop, sandbox, true, on_line).await?;
+    let recording = match recording {
+        Some(recording) => serde_json::from_str(&recording).context("Dry run recording is malformed")?,
+        // The script died before its exit handlers ran
+        None => Recording::default(),
+    };
+    Ok((report, recording))
+}
+
+async fn run_python(
+    script_path: &Path,
+    envelope: &SafetyEnvelope,
+    stop: &StopSignal,
+    sandbox: &SandboxConfig,
+    dry_run: bool,
+    mut on_line: impl FnMut(&OutputLine),
+) -> Result<(ExecutionReport, Option<String>)> {
+    let venv = PythonEnv::from_env()?;
+    venv.ensure()?;
+    let python_exe = venv.python();
+
+    let shim_dir = tempfile::tempdir().context("Failed to create safety shim directory")?;
+    std::fs::write(shim_dir.path().join("sitecustomize.py"), SAFETY_SHIM)?;
 
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        anyhow::bail!("Failed to create venv:\n{}", stderr);
+    let mut python_path = vec![shim_dir.path().to_path_buf()];
+    if let Some(existing) = std::env::var_os("PYTHONPATH") {
+        python_path.extend(std::env::split_paths(&existing));
     }
 
-    Ok(())
-}
+    // The script runs in a scratch directory, so everything it reads must be absolute
+    let script_path = script_path
+        .canonicalize()
+        .with_context(|| format!("Script not found: {}", script_path.display()))?;
+
+    let mut readable = vec![script_path.clone(), shim_dir.path().to_path_buf(), venv.path.clone()];
+    // The venv's python is a symlink into the real installation
+    if let Some(prefix) = python_exe.canonicalize()?.parent().and_then(Path::parent) {
+        readable.push(prefix.to_path_buf());
+    }
+    readable.extend(python_path.iter().skip(1).cloned());
+    let jail = Sandbox::prepare(sandbox, &readable)?;
+
+    let config = serde_json::to_string(&ShimConfig {
+        envelope,
+        positions: [HOME_ANGLE; 4],
+    })?;
+
+    // Listen before spawning so a stop during startup isn't missed
+    let mut stopped = stop.listen();
+
+    // Execute the script
+    let mut command = tokio::process::Command::new(&python_exe);
+    jail.apply(&mut command);
+    let recording_path = jail.scratch_dir().join("recording.json");
+    if dry_run {
+        command.env("ABEL_DRY_RUN", &recording_path);
+    }
+    let mut child = command
+        .arg(&script_path)
+        .env("PYTHONPATH", std::env::join_paths(python_path)?)
+        .env("ABEL_SAFETY", config)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .context("Failed to execute Python script in its sandbox")?;
+
+    let started = Instant::now();
+    let mut stdout = BufReader::new(child.stdout.take().context("Script stdout not captured")?).lines();
+    let mut stderr = BufReader::new(child.stderr.take().context("Script stderr not captured")?).lines();
+    let (mut stdout_open, mut stderr_open) = (true, true);
+    let mut report = ExecutionReport {
+        exit_code: None,
+        duration: Duration::ZERO,
+        stdout: String::new(),
+        stderr: String::new(),
+    };
+
+    let deadline = tokio::time::sleep(sandbox.timeout);
+    tokio::pin!(deadline);
+
+    let status = loop {
+        let line = tokio::select! {
+            line = stdout.next_line(), if stdout_open => match line {
+                Ok(Some(line)) => OutputLine::Stdout(line),
+                _ => {
+                    stdout_open = false;
+                    continue;
+                }
+            },
+            line = stderr.next_line(), if stderr_open => match line {
+                Ok(Some(line)) => OutputLine::Stderr(line),
+                _ => {
+                    stderr_open = false;
+                    continue;
+                }
+            },
+            // Only once both pipes are drained, so no output is lost
+            status = child.wait(), if !stdout_open && !stderr_open => {
+                break status.context("Failed to wait for Python script")?;
+            }
+            _ = stopped.stopped() => {
+                let held = interrupt(&mut child, sandbox, dry_run).await;
+                return Err(ScriptStopped { held }.into());
+            }
+            _ = &mut deadline => {
+                interrupt(&mut child, sandbox, dry_run).await;
+                anyhow::bail!("Script timed out after {}s", sandbox.timeout.as_secs());
+            }
+        };
 
-async fn install_dependencies(venv_path: &Path) -> Result<()> {
-    let pip_exe = if cfg!(windows) {
-        venv_path.join("Scripts").join("pip.exe")
-    } else {
-        venv_path.join("bin").join("pip")
+        on_line(&line);
+        let (captured, text) = match &line {
+            OutputLine::Stdout(text) => (&mut report.stdout, text),
+            OutputLine::Stderr(text) => (&mut report.stderr, text),
+        };
+        captured.push_str(text);
+        captured.push('\n');
     };
 
-    println!("Installing pyserial...");
-    let output = Command::new(&pip_exe)
-        .args(&["install", "pyserial"])
-        .output()
-        .context("Failed to install pyserial")?;
+    report.exit_code = status.code();
+    report.duration = started.elapsed();
+    let recording = std::fs::read_to_string(&recording_path).ok();
+    Ok((report, recording))
+}
+
+/// Asks the script to stop the arm and exit, killing it if it doesn't
+///
+/// Once the script is gone the stop command is sent again from here, in case the
+/// script never got to send it, and the angles the firmware holds are returned. A dry
+/// run never opened the port, so it is left alone.
+async fn interrupt(child: &mut tokio::process::Child, sandbox: &SandboxConfig, dry_run: bool) -> Option<[f32; 4]> {
+    let mut exited = false;
 
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        anyhow::bail!("Failed to install pyserial:\n{}", stderr);
+    #[cfg(unix)]
+    if let Some(pid) = child.id() {
+        // The shim's SIGTERM handler sends `!` on the script's open serial ports
+        unsafe {
+            libc::kill(pid as libc::pid_t, libc::SIGTERM);
+        }
+        exited = tokio::time::timeout(STOP_GRACE, child.wait()).await.is_ok();
     }
 
-    Ok(())
+    if !exited {
+        let _ = child.kill().await;
+    }
+
+    if dry_run {
+        return None;
+    }
+    match sandbox::hold_arm(sandbox) {
+        Ok(held) => held,
+        Err(e) => {
+            eprintln!("Could not send stop to the arm: {:#}", e);
+            None
+        }
+    }
 }
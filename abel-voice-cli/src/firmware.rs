@@ -0,0 +1,354 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::io::{BufRead, BufReader, ErrorKind};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::driver::ServoId;
+
+/// Read timeout of the background reader, bounding how long it takes to stop
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// One servo's motion profile as reported by `?`
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+pub struct ServoStatus {
+    pub servo: ServoId,
+    /// Interpolated angle at the last 200Hz update, trim included
+    pub current: f32,
+    pub target: f32,
+    /// Degrees per second
+    pub velocity: f32,
+    pub moving: bool,
+}
+
+/// A line printed by the firmware, parsed into something the host can act on
+#[derive(Clone, Debug, PartialEq)]
+pub enum FirmwareEvent {
+    /// `Ready.` printed at the end of `setup()`
+    Ready,
+    /// `QUINTIC: Servo <n> <from>deg -> <to>deg over <T>ms (delta=<d>deg)`
+    Motion {
+        servo: ServoId,
+        from: f32,
+        to: f32,
+        duration: Duration,
+        delta: f32,
+    },
+    /// `SEGMENT: Servo <n> <from>deg -> <to>deg over <T>ms (v=<v0>-><vf>deg/s, queued=<k>)`
+    ///
+    /// Reply to a trajectory segment; `queued` counts segments waiting behind the running one.
+    Segment {
+        servo: ServoId,
+        from: f32,
+        to: f32,
+        duration: Duration,
+        start_velocity: f32,
+        end_velocity: f32,
+        queued: usize,
+    },
+    /// `WARN: Duration adjusted <requested>ms -> <adjusted>ms (constraint violation)`
+    DurationAdjusted {
+        requested: Duration,
+        adjusted: Duration,
+    },
+    /// `STOPPED: <a0> <a1> <a2> <a3>` - reply to `!`, with the angle each servo is held at
+    Stopped([f32; 4]),
+    /// `STATUS <servo> <current> <target> <velocity> <moving>` - one line of the reply to `?`
+    Status(ServoStatus),
+    /// Any other `WARN:` line
+    Warning(String),
+    /// `ERR: <reason>` - the command was rejected
+    Error(String),
+    /// Banner and help text
+    Info(String),
+}
+
+/// Parses a single line of firmware output
+pub fn parse_line(line: &str) -> Option<FirmwareEvent> {
+    let line = line.trim();
+    if line.is_empty() {
+        return None;
+    }
+
+    if line == "Ready." {
+        return Some(FirmwareEvent::Ready);
+    }
+
+    if let Some(rest) = line.strip_prefix("ERR:") {
+        return Some(FirmwareEvent::Error(rest.trim().to_string()));
+    }
+
+    if let Some(rest) = line.strip_prefix("WARN:") {
+        let rest = rest.trim();
+        return Some(parse_adjustment(rest).unwrap_or_else(|| FirmwareEvent::Warning(rest.to_string())));
+    }
+
+    if let Some(rest) = line.strip_prefix("STOPPED:") {
+        let angles: Vec<f32> = rest.split_whitespace().filter_map(|w| w.parse().ok()).collect();
+        if let Ok(angles) = angles.try_into() {
+            return Some(FirmwareEvent::Stopped(angles));
+        }
+    }
+
+    if let Some(rest) = line.strip_prefix("STATUS ") {
+        if let Some(status) = parse_status(rest) {
+            return Some(FirmwareEvent::Status(status));
+        }
+    }
+
+    if let Some(rest) = line.strip_prefix("SEGMENT:") {
+        if let Some(event) = parse_segment(rest.trim()) {
+            return Some(event);
+        }
+    }
+
+    if let Some(rest) = line.strip_prefix("QUINTIC:") {
+        if let Some(event) = parse_motion(rest.trim()) {
+            return Some(event);
+        }
+    }
+
+    Some(FirmwareEvent::Info(line.to_string()))
+}
+
+fn parse_adjustment(text: &str) -> Option<FirmwareEvent> {
+    // Duration adjusted 100ms -> 450ms (constraint violation)
+    let rest = text.strip_prefix("Duration adjusted")?;
+    let (requested, rest) = rest.split_once("->")?;
+    let adjusted = rest.split_whitespace().next()?;
+
+    Some(FirmwareEvent::DurationAdjusted {
+        requested: parse_millis(requested)?,
+        adjusted: parse_millis(adjusted)?,
+    })
+}
+
+fn parse_motion(text: &str) -> Option<FirmwareEvent> {
+    // Servo 0 90.0deg -> 45deg over 450ms (delta=45.0deg)
+    let mut words = text.split_whitespace();
+    if words.next()? != "Servo" {
+        return None;
+    }
+    let servo = ServoId::try_from(words.next()?.parse::<u8>().ok()?).ok()?;
+    let from = parse_degrees(words.next()?)?;
+    if words.next()? != "->" {
+        return None;
+    }
+    let to = parse_degrees(words.next()?)?;
+    if words.next()? != "over" {
+        return None;
+    }
+    let duration = parse_millis(words.next()?)?;
+    let delta = words
+        .next()
+        .and_then(|w| w.strip_prefix("(delta="))
+        .and_then(|w| parse_degrees(w.trim_end_matches(')')))
+        .unwrap_or((to - from).abs());
+
+    Some(FirmwareEvent::Motion {
+        servo,
+        from,
+        to,
+        duration,
+        delta,
+    })
+}
+
+fn parse_segment(text: &str) -> Option<FirmwareEvent> {
+    // Servo 1 90.0deg -> 50deg over 420ms (v=0.0->-95.2deg/s, queued=1)
+    let (motion, details) = text.split_once('(')?;
+    let Some(FirmwareEvent::Motion {
+        servo, from, to, duration, ..
+    }) = parse_motion(motion.trim())
+    else {
+        return None;
+    };
+
+    let (velocities, queued) = details.trim_end_matches(')').split_once(',')?;
+    let (start_velocity, end_velocity) = velocities.trim().strip_prefix("v=")?.split_once("->")?;
+    let queued = queued.trim().strip_prefix("queued=")?.parse().ok()?;
+
+    Some(FirmwareEvent::Segment {
+        servo,
+        from,
+        to,
+        duration,
+        start_velocity: start_velocity.parse().ok()?,
+        end_velocity: end_velocity.strip_suffix("deg/s")?.parse().ok()?,
+        queued,
+    })
+}
+
+fn parse_status(text: &str) -> Option<ServoStatus> {
+    // 0 112.4 135.0 41.7 1
+    let mut words = text.split_whitespace();
+    let servo = ServoId::try_from(words.next()?.parse::<u8>().ok()?).ok()?;
+    let current = words.next()?.parse().ok()?;
+    let target = words.next()?.parse().ok()?;
+    let velocity = words.next()?.parse().ok()?;
+    let moving = words.next()? == "1";
+
+    Some(ServoStatus {
+        servo,
+        current,
+        target,
+        velocity,
+        moving,
+    })
+}
+
+fn parse_millis(text: &str) -> Option<Duration> {
+    let ms = text.trim().strip_suffix("ms")?.parse().ok()?;
+    Some(Duration::from_millis(ms))
+}
+
+fn parse_degrees(text: &str) -> Option<f32> {
+    text.trim().strip_suffix("deg")?.parse().ok()
+}
+
+/// Reads the port on a background thread and forwards parsed events
+///
+/// The thread exits once the reader is dropped or the port goes away.
+pub struct FirmwareReader {
+    events: Receiver<FirmwareEvent>,
+    stop: Arc<AtomicBool>,
+}
+
+impl FirmwareReader {
+    pub fn spawn(mut port: Box<dyn serialport::SerialPort>) -> Result<Self> {
+        // Wake up regularly so the thread notices when it should stop
+        port.set_timeout(POLL_INTERVAL)?;
+
+        let (tx, rx) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let stop_flag = Arc::clone(&stop);
+
+        std::thread::spawn(move || {
+            let mut reader = BufReader::new(port);
+            let mut buf = Vec::new();
+
+            while !stop_flag.load(Ordering::Relaxed) {
+                match reader.read_until(b'\n', &mut buf) {
+                    Ok(0) => break,
+                    Ok(_) => {
+                        // The ESP32 prints boot noise that isn't valid UTF-8
+                        let line = String::from_utf8_lossy(&buf).into_owned();
+                        buf.clear();
+
+                        if let Some(event) = parse_line(&line) {
+                            if tx.send(event).is_err() {
+                                break;
+                            }
+                        }
+                    }
+                    // Partial lines stay in `buf` until the rest arrives
+                    Err(e) if e.kind() == ErrorKind::TimedOut => continue,
+                    Err(_) => break,
+                }
+            }
+        });
+
+        Ok(Self { events: rx, stop })
+    }
+
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<FirmwareEvent, RecvTimeoutError> {
+        self.events.recv_timeout(timeout)
+    }
+
+    pub fn try_recv(&self) -> Option<FirmwareEvent> {
+        self.events.try_recv().ok()
+    }
+}
+
+impl Drop for FirmwareReader {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_status_lines() {
+        assert_eq!(
+            parse_line("STATUS 2 112.4 135.0 41.7 1\r\n"),
+            Some(FirmwareEvent::Status(ServoStatus {
+                servo: ServoId::Elbow,
+                current: 112.4,
+                target: 135.0,
+                velocity: 41.7,
+                moving: true,
+            }))
+        );
+        // No servo 7, so it is just text
+        assert!(matches!(parse_line("STATUS 7 0.0 0.0 0.0 0"), Some(FirmwareEvent::Info(_))));
+    }
+
+    #[test]
+    fn parses_quintic_and_segment_replies() {
+        assert_eq!(
+            parse_line("QUINTIC: Servo 0 90.0deg -> 45deg over 450ms (delta=45.0deg)"),
+            Some(FirmwareEvent::Motion {
+                servo: ServoId::Base,
+                from: 90.0,
+                to: 45.0,
+                duration: Duration::from_millis(450),
+                delta: 45.0,
+            })
+        );
+        assert_eq!(
+            parse_line("SEGMENT: Servo 1 90.0deg -> 50deg over 420ms (v=0.0->-95.2deg/s, queued=1)\r\n"),
+            Some(FirmwareEvent::Segment {
+                servo: ServoId::Shoulder,
+                from: 90.0,
+                to: 50.0,
+                duration: Duration::from_millis(420),
+                start_velocity: 0.0,
+                end_velocity: -95.2,
+                queued: 1,
+            })
+        );
+    }
+
+    #[test]
+    fn parses_warnings_and_errors() {
+        assert_eq!(
+            parse_line("WARN: Duration adjusted 100ms -> 450ms (constraint violation)"),
+            Some(FirmwareEvent::DurationAdjusted {
+                requested: Duration::from_millis(100),
+                adjusted: Duration::from_millis(450),
+            })
+        );
+        assert_eq!(
+            parse_line("WARN: Servo 1 segment queue ran dry at 42.5deg/s"),
+            Some(FirmwareEvent::Warning("Servo 1 segment queue ran dry at 42.5deg/s".to_string()))
+        );
+        assert_eq!(
+            parse_line("ERR: Invalid servo (0-3)"),
+            Some(FirmwareEvent::Error("Invalid servo (0-3)".to_string()))
+        );
+    }
+
+    #[test]
+    fn parses_stop_replies_with_every_angle() {
+        assert_eq!(
+            parse_line("STOPPED: 90.0 45.5 120.0 60.0\r\n"),
+            Some(FirmwareEvent::Stopped([90.0, 45.5, 120.0, 60.0]))
+        );
+        assert!(matches!(parse_line("STOPPED: 90.0 45.5"), Some(FirmwareEvent::Info(_))));
+    }
+
+    #[test]
+    fn blank_lines_and_banners() {
+        assert_eq!(parse_line("\r\n"), None);
+        assert_eq!(parse_line("Ready.\r\n"), Some(FirmwareEvent::Ready));
+        assert_eq!(
+            parse_line("Abel Arm Firmware"),
+            Some(FirmwareEvent::Info("Abel Arm Firmware".to_string()))
+        );
+    }
+}
@@ -0,0 +1,469 @@
+use crate::driver::ServoId;
+use crate::interpreter::CommandResult;
+use crate::profile::{CalibrationProfile, GripperCalibration, ServoCalibration};
+use crate::sequence::Library;
+
+/// Words that carry no meaning in a command
+const FILLER: &[&str] = &[
+    "please", "the", "a", "an", "to", "by", "degree", "degrees", "deg", "now", "can", "could", "would", "you",
+    "robot", "arm", "your", "its", "it", "position", "sequence", "for", "me", "all", "way", "of",
+];
+
+/// Verbs that only say "do something"; the rest of the command says what
+const VERBS: &[&str] = &[
+    "move", "turn", "rotate", "set", "put", "swing", "go", "bring", "point", "run", "do", "perform", "play",
+    "make", "show", "return", "head", "come", "back",
+];
+
+/// Words naming each joint, shared with the offline rules so both understand the same commands
+pub const JOINTS: &[(ServoId, &[&str])] = &[
+    (ServoId::Base, &["base", "waist", "turntable", "rotation"]),
+    (ServoId::Shoulder, &["shoulder"]),
+    (ServoId::Elbow, &["elbow", "forearm"]),
+    (ServoId::Gripper, &["gripper", "hand", "claw", "jaws", "jaw"]),
+];
+
+/// Directions as a joint and a sign; `None` means the joint has to be named
+const DIRECTIONS: &[(&str, Option<ServoId>, i16)] = &[
+    ("left", Some(ServoId::Base), 1),
+    ("right", Some(ServoId::Base), -1),
+    ("clockwise", Some(ServoId::Base), -1),
+    ("counterclockwise", Some(ServoId::Base), 1),
+    ("anticlockwise", Some(ServoId::Base), 1),
+    ("up", None, 1),
+    ("raise", None, 1),
+    ("lift", None, 1),
+    ("down", None, -1),
+    ("lower", None, -1),
+    ("extend", Some(ServoId::Elbow), 1),
+    ("straighten", Some(ServoId::Elbow), 1),
+    ("bend", Some(ServoId::Elbow), -1),
+    ("fold", Some(ServoId::Elbow), -1),
+];
+
+pub const STOP: &[&str] = &["stop", "halt", "freeze", "emergency", "abort"];
+pub const HOME: &[&str] = &["home", "reset", "rest", "neutral", "center", "centre"];
+pub const OPEN: &[&str] = &["open", "release", "drop", "ungrip", "unclamp"];
+pub const CLOSE: &[&str] = &["close", "shut", "grab", "grip", "grasp", "clamp", "squeeze", "pinch"];
+
+const UNITS: &[&str] = &[
+    "zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine", "ten", "eleven", "twelve",
+    "thirteen", "fourteen", "fifteen", "sixteen", "seventeen", "eighteen", "nineteen",
+];
+const TENS: &[&str] = &["twenty", "thirty", "forty", "fifty", "sixty", "seventy", "eighty", "ninety"];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Token {
+    Joint(ServoId),
+    /// Joint implied by the word, if any, and +1/-1
+    Direction(Option<ServoId>, i16),
+    Number(f32),
+    Axis(char),
+    Home,
+    Open,
+    Close,
+}
+
+/// Parses the everyday commands the prompts list as fixed examples without asking an LLM
+///
+/// Deliberately strict: every word has to be understood, otherwise `parse` returns
+/// `None` and the command goes to the LLM. Handles numbers spoken as words ("forty
+/// five"), synonyms ("claw", "grab", "waist"), sequences by name and moves relative
+/// to the current pose ("rotate left 20 degrees", "raise the shoulder by ten").
+pub struct Grammar {
+    limits: [ServoCalibration; 4],
+    gripper: GripperCalibration,
+    /// Each sequence name with the words it is made of, e.g. ("PICK_PLACE", ["pick", "place"])
+    sequences: Vec<(String, Vec<String>)>,
+}
+
+impl Grammar {
+    pub fn new(profile: &CalibrationProfile, sequences: &Library) -> Self {
+        Self {
+            limits: ServoId::ALL.map(|servo| *profile.servo(servo)),
+            gripper: profile.gripper,
+            sequences: sequence_words(sequences),
+        }
+    }
+
+    /// The command as a `CommandResult`, with relative moves resolved from `positions`
+    pub fn parse(&self, command: &str, positions: [u8; 4]) -> Option<CommandResult> {
+        let mut words = normalize(command);
+        words.retain(|w| !FILLER.contains(&w.as_str()));
+
+        // A stop word anywhere wins over everything else, e.g. "stop the pick and place"
+        if words.iter().any(|w| STOP.contains(&w.as_str())) {
+            return Some(result("stop", |_| {}));
+        }
+
+        // A sequence's words may appear anywhere, e.g. "do the pick and place"
+        if let Some((name, name_words)) = find_sequence(&self.sequences, &words) {
+            let rest: Vec<&String> = words
+                .iter()
+                .filter(|w| !name_words.contains(w) && *w != "and")
+                .collect();
+            if rest.iter().all(|w| VERBS.contains(&w.as_str())) {
+                return Some(result("sequence", |r| r.sequence_name = Some(name.to_string())));
+            }
+            return None;
+        }
+
+        let tokens = tokenize(&words)?;
+        let count = |wanted: fn(&Token) -> bool| tokens.iter().filter(|t| wanted(t)).count();
+        let numbers: Vec<f32> = tokens
+            .iter()
+            .filter_map(|t| match t {
+                Token::Number(n) => Some(*n),
+                _ => None,
+            })
+            .collect();
+        let joints: Vec<ServoId> = tokens
+            .iter()
+            .filter_map(|t| match t {
+                Token::Joint(servo) => Some(*servo),
+                _ => None,
+            })
+            .collect();
+        let directions: Vec<(Option<ServoId>, i16)> = tokens
+            .iter()
+            .filter_map(|t| match t {
+                Token::Direction(servo, sign) => Some((*servo, *sign)),
+                _ => None,
+            })
+            .collect();
+
+        if tokens.iter().all(|t| *t == Token::Home) && !tokens.is_empty() {
+            return Some(result("home", |_| {}));
+        }
+
+        // "move to x 120 y 40 z 20"
+        if count(|t| matches!(t, Token::Axis(_))) == 3 && tokens.len() == 6 {
+            let mut xyz = [None; 3];
+            for pair in tokens.chunks(2) {
+                if let [Token::Axis(axis), Token::Number(n)] = pair {
+                    xyz["xyz".find(*axis)?] = Some(*n);
+                }
+            }
+            let [Some(x), Some(y), Some(z)] = xyz else {
+                return None;
+            };
+            return Some(result("move_to", |r| {
+                r.x = Some(x);
+                r.y = Some(y);
+                r.z = Some(z);
+            }));
+        }
+
+        // "open the gripper", "grab"
+        let grip = count(|t| matches!(t, Token::Open | Token::Close));
+        if grip == 1 && numbers.is_empty() && directions.is_empty() && joints.iter().all(|j| *j == ServoId::Gripper) {
+            let angle = if tokens.contains(&Token::Open) {
+                self.gripper.open
+            } else {
+                self.gripper.closed
+            };
+            return Some(self.movement(ServoId::Gripper, angle));
+        }
+        if grip > 0 || numbers.len() != 1 || joints.len() > 1 || directions.len() > 1 {
+            return None;
+        }
+        let number = numbers[0];
+
+        match (joints.first(), directions.first()) {
+            // "move the base to 45"
+            (Some(&servo), None) => {
+                if number.fract() != 0.0 || !(0.0..=180.0).contains(&number) {
+                    return None;
+                }
+                Some(self.movement(servo, number as u8))
+            }
+            // "rotate left 20", "raise the shoulder by 10"
+            (joint, Some(&(implied, sign))) => {
+                let servo = match (joint, implied) {
+                    (Some(&joint), Some(implied)) if joint != implied => return None,
+                    (Some(&joint), _) => joint,
+                    (None, Some(implied)) => implied,
+                    (None, None) => return None,
+                };
+                if servo == ServoId::Gripper || number <= 0.0 {
+                    return None;
+                }
+
+                let limits = &self.limits[servo.index()];
+                let target = positions[servo.index()] as f32 + sign as f32 * number;
+                Some(self.movement(servo, target.round().clamp(limits.min as f32, limits.max as f32) as u8))
+            }
+            (None, None) => None,
+        }
+    }
+
+    fn movement(&self, servo: ServoId, angle: u8) -> CommandResult {
+        result("move", |r| {
+            r.servo = Some(servo as u8);
+            r.angle = Some(angle);
+        })
+    }
+
+}
+
+/// Each sequence name with the lower-case words it is made of
+pub fn sequence_words(sequences: &Library) -> Vec<(String, Vec<String>)> {
+    sequences
+        .names()
+        .into_iter()
+        .map(|name| {
+            let words = name.to_lowercase().split('_').filter(|w| !w.is_empty()).map(String::from).collect();
+            (name.to_string(), words)
+        })
+        .collect()
+}
+
+/// The sequence whose words all appear, preferring the longest name so PICK_PLACE beats PICK
+pub fn find_sequence<'a>(
+    sequences: &'a [(String, Vec<String>)],
+    words: &[String],
+) -> Option<(&'a str, &'a [String])> {
+    sequences
+        .iter()
+        .filter(|(_, name)| name.iter().all(|w| words.contains(w)))
+        .max_by_key(|(_, name)| name.len())
+        .map(|(name, name_words)| (name.as_str(), name_words.as_slice()))
+}
+
+/// Lower-case words with spoken numbers turned into digits
+///
+/// "Turn the base to forty-five°" -> ["turn", "the", "base", "to", "45"]
+pub fn normalize(text: &str) -> Vec<String> {
+    let words: Vec<String> = text
+        .to_lowercase()
+        .replace("let go", "release")
+        .split(|c: char| !c.is_alphanumeric() && c != '.' && c != '-')
+        .flat_map(|w| {
+            // "forty-five" is two words, "-40" is one number
+            let w = w.trim_start_matches('.').trim_end_matches(['.', '-']);
+            if w.starts_with('-') {
+                vec![w.to_string()]
+            } else {
+                w.split('-').map(String::from).collect()
+            }
+        })
+        .filter(|w| !w.is_empty())
+        .collect();
+
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < words.len() {
+        match spoken_number(&words[i..]) {
+            Some((value, used)) => {
+                out.push(value.to_string());
+                i += used;
+            }
+            None => {
+                out.push(words[i].clone());
+                i += 1;
+            }
+        }
+    }
+    out
+}
+
+/// A number spelled out in words at the start of `words`, and how many words it took
+///
+/// Covers what angles and millimetres need: "minus forty", "a hundred and twenty five", "ninety".
+fn spoken_number(words: &[String]) -> Option<(i32, usize)> {
+    let mut i = 0;
+    let sign = match words.first().map(String::as_str) {
+        Some("minus" | "negative") => {
+            i += 1;
+            -1
+        }
+        _ => 1,
+    };
+    // "minus 40", as transcribed with digits
+    if sign < 0 {
+        if let Some(Ok(digits)) = words.get(i).map(|w| w.parse::<i32>()) {
+            return Some((-digits, i + 1));
+        }
+    }
+
+    let mut value = 0;
+    let mut found = false;
+    // "a hundred"
+    if words.get(i).map(String::as_str) == Some("a") && words.get(i + 1).map(String::as_str) == Some("hundred") {
+        i += 1;
+        value = 1;
+        found = true;
+    }
+    while let Some(word) = words.get(i).map(String::as_str) {
+        if let Some(unit) = UNITS.iter().position(|u| *u == word) {
+            if found && value % 10 != 0 {
+                break;
+            }
+            value += unit as i32;
+        } else if let Some(ten) = TENS.iter().position(|t| *t == word) {
+            if found && value % 100 != 0 {
+                break;
+            }
+            value += (ten as i32 + 2) * 10;
+        } else if word == "hundred" && found && value < 10 {
+            value = value.max(1) * 100;
+        } else if word == "and" && found && value % 100 == 0 && value > 0 {
+            // "hundred and twenty", but not "ninety and ..."
+            let next = words.get(i + 1).map(String::as_str).unwrap_or_default();
+            if !UNITS.contains(&next) && !TENS.contains(&next) {
+                break;
+            }
+        } else {
+            break;
+        }
+        found = true;
+        i += 1;
+    }
+
+    if !found {
+        return None;
+    }
+    Some((sign * value, i))
+}
+
+/// Every word as a token, or `None` if one isn't understood
+fn tokenize(words: &[String]) -> Option<Vec<Token>> {
+    let mut tokens = Vec::new();
+    for word in words {
+        let w = word.as_str();
+        if VERBS.contains(&w) {
+            continue;
+        }
+        let token = if let Ok(n) = w.parse::<f32>() {
+            Token::Number(n)
+        } else if let Some((servo, _)) = JOINTS.iter().find(|(_, names)| names.contains(&w)) {
+            Token::Joint(*servo)
+        } else if let Some((_, servo, sign)) = DIRECTIONS.iter().find(|(name, _, _)| *name == w) {
+            Token::Direction(*servo, *sign)
+        } else if matches!(w, "x" | "y" | "z") {
+            Token::Axis(w.chars().next()?)
+        } else if HOME.contains(&w) {
+            Token::Home
+        } else if OPEN.contains(&w) {
+            Token::Open
+        } else if CLOSE.contains(&w) {
+            Token::Close
+        } else {
+            return None;
+        };
+        tokens.push(token);
+    }
+    Some(tokens)
+}
+
+fn result(action: &str, fill: impl FnOnce(&mut CommandResult)) -> CommandResult {
+    let mut result = CommandResult {
+        action: action.to_string(),
+        ..Default::default()
+    };
+    fill(&mut result);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(text: &str) -> Vec<String> {
+        text.split_whitespace().map(String::from).collect()
+    }
+
+    fn grammar() -> Grammar {
+        let profile = CalibrationProfile::default();
+        Grammar {
+            limits: ServoId::ALL.map(|servo| *profile.servo(servo)),
+            gripper: profile.gripper,
+            sequences: vec![
+                ("PICK".to_string(), words("pick")),
+                ("PICK_PLACE".to_string(), words("pick place")),
+            ],
+        }
+    }
+
+    #[test]
+    fn normalize_turns_spoken_numbers_into_digits() {
+        assert_eq!(normalize("Turn the base to forty-five°"), words("turn the base to 45"));
+        assert_eq!(normalize("a hundred and twenty five"), words("125"));
+        assert_eq!(normalize("one hundred eighty"), words("180"));
+        assert_eq!(normalize("minus forty"), words("-40"));
+        assert_eq!(normalize("negative 40"), words("-40"));
+        assert_eq!(normalize("x -40."), words("x -40"));
+        assert_eq!(normalize("Let go"), words("release"));
+    }
+
+    #[test]
+    fn normalize_stops_numbers_at_words_that_cannot_follow() {
+        assert_eq!(normalize("twenty thirty"), words("20 30"));
+        assert_eq!(normalize("ninety and then open"), words("90 and then open"));
+        assert_eq!(normalize("a bit"), words("a bit"));
+    }
+
+    #[test]
+    fn spoken_number_adds_units_after_round_values() {
+        // Units add on to any multiple of ten, so these run together rather than split
+        assert_eq!(spoken_number(&words("ten five")), Some((15, 2)));
+        assert_eq!(spoken_number(&words("zero five")), Some((5, 2)));
+        assert_eq!(spoken_number(&words("twenty five degrees")), Some((25, 2)));
+        assert_eq!(spoken_number(&words("five five")), Some((5, 1)));
+        assert_eq!(spoken_number(&words("fifteen five")), Some((15, 1)));
+    }
+
+    #[test]
+    fn spoken_number_handles_hundreds_and_signs() {
+        assert_eq!(spoken_number(&words("a hundred")), Some((100, 2)));
+        assert_eq!(spoken_number(&words("two hundred and ten")), Some((210, 4)));
+        assert_eq!(spoken_number(&words("hundred")), None);
+        assert_eq!(spoken_number(&words("minus twenty")), Some((-20, 2)));
+        assert_eq!(spoken_number(&words("minus")), None);
+        assert_eq!(spoken_number(&words("base")), None);
+    }
+
+    #[test]
+    fn parse_stop_wins_over_everything() {
+        let grammar = grammar();
+        for command in ["stop", "stop the pick and place", "halt the base at ninety", "emergency"] {
+            assert_eq!(grammar.parse(command, [90; 4]).map(|r| r.action), Some("stop".to_string()), "{}", command);
+        }
+    }
+
+    #[test]
+    fn parse_prefers_the_longest_sequence_name() {
+        let result = grammar().parse("do the pick and place", [90; 4]).unwrap();
+        assert_eq!(result.action, "sequence");
+        assert_eq!(result.sequence_name.as_deref(), Some("PICK_PLACE"));
+        assert!(grammar().parse("pick and place slowly", [90; 4]).is_none());
+    }
+
+    #[test]
+    fn parse_moves() {
+        let grammar = grammar();
+        let moved = |command: &str, positions| {
+            grammar.parse(command, positions).map(|r| (r.action, r.servo, r.angle))
+        };
+        let servo = |servo: ServoId, angle| Some(("move".to_string(), Some(servo as u8), Some(angle)));
+
+        assert_eq!(moved("turn the waist to a hundred and twenty", [90; 4]), servo(ServoId::Base, 120));
+        assert_eq!(moved("rotate left thirty", [90; 4]), servo(ServoId::Base, 120));
+        assert_eq!(moved("raise the shoulder by ten degrees", [90; 4]), servo(ServoId::Shoulder, 100));
+        // Clamped to the calibrated limits
+        assert_eq!(moved("lower the shoulder ninety", [90; 4]), servo(ServoId::Shoulder, 9));
+        assert_eq!(moved("open the claw", [90; 4]), servo(ServoId::Gripper, 120));
+        assert_eq!(moved("grab", [90; 4]), servo(ServoId::Gripper, 60));
+        // Left to the LLM: an angle over 180, an unknown word, two numbers
+        assert_eq!(moved("base to two hundred", [90; 4]), None);
+        assert_eq!(moved("wave hello", [90; 4]), None);
+        assert_eq!(moved("base to 10 then 20", [90; 4]), None);
+    }
+
+    #[test]
+    fn parse_cartesian_targets() {
+        let result = grammar().parse("move to x 120 y minus 40 z twenty", [90; 4]).unwrap();
+        assert_eq!(result.action, "move_to");
+        assert_eq!((result.x, result.y, result.z), (Some(120.0), Some(-40.0), Some(20.0)));
+    }
+}
@@ -0,0 +1,896 @@
+use anyhow::{Context, Result};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+
+use crate::driver::ServoId;
+use crate::grammar::Grammar;
+use crate::llm::{self, LlmBackend, Prompt, Task};
+use crate::kinematics::{self, Point};
+use crate::plan::{ActionPlan, GripperState, JointTarget, Step};
+use crate::profile::{CalibrationProfile, Pose};
+use crate::sequence::Library;
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct CommandResult {
+    pub action: String,
+    pub servo: Option<u8>,
+    pub angle: Option<u8>,
+    #[serde(default)]
+    pub x: Option<f32>,
+    #[serde(default)]
+    pub y: Option<f32>,
+    #[serde(default)]
+    pub z: Option<f32>,
+    pub sequence_name: Option<String>,
+    pub message: Option<String>,
+    /// How long a "wait" lasts
+    #[serde(default)]
+    pub ms: Option<u64>,
+    /// The commands of a "multi", in order, or of a "parallel", started together
+    #[serde(default)]
+    pub commands: Option<Vec<CommandResult>>,
+}
+
+const ACTIONS: &[&str] = &["move", "move_to", "sequence", "home", "wait", "multi", "parallel", "stop", "unknown"];
+
+/// What a "multi" may contain
+const MULTI_ACTIONS: &[&str] = &["move", "move_to", "sequence", "home", "wait", "parallel"];
+
+/// What a "parallel" group may contain: moves that end up as one coordinated move
+const PARALLEL_ACTIONS: &[&str] = &["move", "move_to"];
+
+/// Most commands in one "multi" or "parallel"
+const MAX_COMMANDS: usize = 20;
+
+/// Longest "wait", as for plan wait steps
+const MAX_WAIT_MS: u64 = 10_000;
+
+impl CommandResult {
+    /// JSON schema (Gemini's OpenAPI subset) for a `CommandResult` naming one of `sequences`
+    ///
+    /// The schema can't refer to itself, so it spells out the two levels of nesting allowed.
+    pub fn response_schema(sequences: &Library) -> serde_json::Value {
+        let parallel = command_schema(PARALLEL_ACTIONS, sequences, None);
+        let multi = command_schema(MULTI_ACTIONS, sequences, Some(parallel));
+        command_schema(ACTIONS, sequences, Some(multi))
+    }
+
+    /// Checks that the fields the action needs are there and make sense for this arm
+    ///
+    /// Reachability and the safety envelope are left to whoever carries the command out.
+    pub fn validate(&self, profile: &CalibrationProfile, sequences: &Library) -> Result<()> {
+        match self.action.as_str() {
+            "move" => {
+                let servo = self.servo.context("\"move\" needs a servo")?;
+                let servo = ServoId::try_from(servo).map_err(|_| anyhow::anyhow!("servo must be 0-3, not {}", servo))?;
+                let angle = self.angle.context("\"move\" needs an angle")?;
+                let cal = profile.servo(servo);
+                if !(cal.min..=cal.max).contains(&angle) {
+                    anyhow::bail!(
+                        "{} angle {} is outside its calibrated limits {}-{}",
+                        servo.name(),
+                        angle,
+                        cal.min,
+                        cal.max
+                    );
+                }
+            }
+            "move_to" => {
+                if self.x.is_none() || self.y.is_none() || self.z.is_none() {
+                    anyhow::bail!("\"move_to\" needs x, y and z");
+                }
+            }
+            "sequence" => {
+                let name = self.sequence_name.as_deref().context("\"sequence\" needs a sequence_name")?;
+                sequences.get(name)?;
+            }
+            "wait" => {
+                let ms = self.ms.context("\"wait\" needs ms")?;
+                if ms > MAX_WAIT_MS {
+                    anyhow::bail!("waits are at most {}ms, not {}", MAX_WAIT_MS, ms);
+                }
+            }
+            "multi" | "parallel" => {
+                let allowed = if self.action == "multi" { MULTI_ACTIONS } else { PARALLEL_ACTIONS };
+                let commands = self.commands.as_deref().unwrap_or_default();
+                if commands.is_empty() || commands.len() > MAX_COMMANDS {
+                    anyhow::bail!("\"{}\" needs 1-{} commands", self.action, MAX_COMMANDS);
+                }
+
+                let mut moved = Vec::new();
+                for (i, command) in commands.iter().enumerate() {
+                    if !allowed.contains(&command.action.as_str()) {
+                        anyhow::bail!(
+                            "command {} of \"{}\" is \"{}\" (use {})",
+                            i + 1,
+                            self.action,
+                            command.action,
+                            allowed.join(", ")
+                        );
+                    }
+                    command
+                        .validate(profile, sequences)
+                        .with_context(|| format!("command {} of \"{}\"", i + 1, self.action))?;
+
+                    if self.action == "parallel" {
+                        for servo in command.servos() {
+                            if moved.contains(&servo) {
+                                anyhow::bail!("\"parallel\" moves the {} twice", servo.name());
+                            }
+                            moved.push(servo);
+                        }
+                    }
+                }
+            }
+            "home" | "stop" | "unknown" => {}
+            other => anyhow::bail!("Unknown action \"{}\" (use {})", other, ACTIONS.join(", ")),
+        }
+        Ok(())
+    }
+
+    /// Servos a "move" or "move_to" drives
+    fn servos(&self) -> Vec<ServoId> {
+        match self.action.as_str() {
+            "move" => self.servo.and_then(|servo| ServoId::try_from(servo).ok()).into_iter().collect(),
+            "move_to" => vec![ServoId::Base, ServoId::Shoulder, ServoId::Elbow],
+            _ => Vec::new(),
+        }
+    }
+
+    /// The plan doing the same thing; stop and unknown commands have none
+    pub fn to_plan(&self, profile: &CalibrationProfile) -> Result<ActionPlan> {
+        let steps = self.steps(profile)?;
+        let summary: Vec<String> = steps.iter().map(|step| step.to_string()).collect();
+
+        Ok(ActionPlan {
+            summary: Some(summary.join(", ")),
+            steps,
+        })
+    }
+
+    fn steps(&self, profile: &CalibrationProfile) -> Result<Vec<Step>> {
+        let step = match self.action.as_str() {
+            "sequence" => Step::Sequence {
+                name: self.sequence_name.clone().unwrap_or_default(),
+            },
+            "home" => Step::Home { joints: None },
+            "wait" => Step::Wait {
+                ms: self.ms.context("Wait needs ms")?,
+            },
+            "move" => {
+                let [target] = self.targets(profile)?[..] else {
+                    unreachable!("a move has one target");
+                };
+                let gripper = profile.gripper;
+                match target.servo {
+                    ServoId::Gripper if target.angle == gripper.open => Step::Gripper {
+                        state: GripperState::Open,
+                    },
+                    ServoId::Gripper if target.angle == gripper.closed => Step::Gripper {
+                        state: GripperState::Closed,
+                    },
+                    servo => Step::Move {
+                        servo,
+                        angle: target.angle,
+                        duration_ms: None,
+                    },
+                }
+            }
+            "move_to" => Step::CoordinatedMove {
+                moves: self.targets(profile)?,
+                duration_ms: None,
+            },
+            "parallel" => {
+                let mut moves = Vec::new();
+                for command in self.commands.as_deref().unwrap_or_default() {
+                    moves.extend(command.targets(profile)?);
+                }
+                Step::CoordinatedMove {
+                    moves,
+                    duration_ms: None,
+                }
+            }
+            "multi" => {
+                let mut steps = Vec::new();
+                for command in self.commands.as_deref().unwrap_or_default() {
+                    steps.extend(command.steps(profile)?);
+                }
+                return Ok(steps);
+            }
+            "stop" => anyhow::bail!("Stop the arm with the stop command, not a plan"),
+            _ => anyhow::bail!("{}", self.message.clone().unwrap_or_else(|| "Command not understood".to_string())),
+        };
+
+        Ok(vec![step])
+    }
+
+    /// Joint angles of a "move" or "move_to", solving the latter's position
+    fn targets(&self, profile: &CalibrationProfile) -> Result<Vec<JointTarget>> {
+        let moves = match self.action.as_str() {
+            "move" => {
+                let servo = ServoId::try_from(self.servo.context("Move needs a servo")?)?;
+                vec![(servo, self.angle.context("Move needs an angle")?)]
+            }
+            "move_to" => {
+                let (Some(x), Some(y), Some(z)) = (self.x, self.y, self.z) else {
+                    anyhow::bail!("Move to needs x, y and z");
+                };
+                kinematics::inverse(&profile.geometry, Point { x, y, z })
+                    .with_context(|| format!("Can't reach x {} y {} z {}", x, y, z))?
+                    .to_moves()
+            }
+            other => anyhow::bail!("\"{}\" can't run in parallel with other moves", other),
+        };
+        Ok(moves.into_iter().map(|(servo, angle)| JointTarget { servo, angle }).collect())
+    }
+}
+
+/// Schema of one command with `actions`; `commands` is the schema of its sub-commands, if it can have any
+fn command_schema(actions: &[&str], sequences: &Library, commands: Option<serde_json::Value>) -> serde_json::Value {
+    let number = serde_json::json!({ "type": "NUMBER" });
+    let mut sequence_name = serde_json::json!({ "type": "STRING" });
+    let names = sequences.names();
+    if !names.is_empty() {
+        sequence_name["enum"] = serde_json::json!(names);
+    }
+
+    let mut schema = serde_json::json!({
+        "type": "OBJECT",
+        "properties": {
+            "action": { "type": "STRING", "enum": actions },
+            "servo": { "type": "INTEGER" },
+            "angle": { "type": "INTEGER" },
+            "x": number,
+            "y": number,
+            "z": number,
+            "sequence_name": sequence_name,
+            "ms": { "type": "INTEGER" },
+            "message": { "type": "STRING" }
+        },
+        "required": ["action"]
+    });
+    if let Some(commands) = commands {
+        schema["properties"]["commands"] = serde_json::json!({ "type": "ARRAY", "items": commands });
+    }
+    schema
+}
+
+/// Turns transcripts into commands, plans and scripts with the configured LLM backends
+///
+/// Common commands are parsed by the offline grammar first; only the rest reach an LLM.
+pub struct Interpreter {
+    grammar: Grammar,
+    backend: Box<dyn LlmBackend>,
+    profile: CalibrationProfile,
+    sequences: Library,
+}
+
+impl Interpreter {
+    pub fn new() -> Result<Self> {
+        let profile = CalibrationProfile::load()?;
+        let sequences = Library::load()?;
+
+        Ok(Self {
+            grammar: Grammar::new(&profile, &sequences),
+            backend: llm::from_env(&profile, &sequences)?,
+            profile,
+            sequences,
+        })
+    }
+
+    /// The backends in the order they are tried, e.g. "Gemini gemini-2.0-flash-exp → rules"
+    pub fn backend_name(&self) -> String {
+        self.backend.name()
+    }
+
+    /// Fills the `{{...}}` placeholders in a prompt with calibrated limits and poses
+    fn render_prompt(&self, template: &str) -> String {
+        let defaults = CalibrationProfile::default();
+        let pose = |name: &str| -> Pose {
+            self.profile
+                .poses
+                .get(name)
+                .or_else(|| defaults.poses.get(name))
+                .copied()
+                .expect("default profile defines pick and place")
+        };
+        let pick = pose("pick");
+        let place = pose("place");
+        let gripper = self.profile.gripper;
+
+        let mut values = vec![
+            ("GRIPPER_OPEN".to_string(), gripper.open),
+            ("GRIPPER_TOUCH".to_string(), gripper.touch),
+            ("GRIPPER_CLOSED".to_string(), gripper.closed),
+            ("PICK_BASE".to_string(), pick.base),
+            ("PICK_SHOULDER".to_string(), pick.shoulder),
+            ("PICK_ELBOW".to_string(), pick.elbow),
+            ("PLACE_BASE".to_string(), place.base),
+            ("PLACE_SHOULDER".to_string(), place.shoulder),
+            ("PLACE_ELBOW".to_string(), place.elbow),
+        ];
+        for servo in ServoId::ALL {
+            let cal = self.profile.servo(servo);
+            let name = servo.name().to_uppercase();
+            values.push((format!("{}_MIN", name), cal.min));
+            values.push((format!("{}_MAX", name), cal.max));
+            values.push((format!("{}_HOME", name), cal.home));
+        }
+
+        values
+            .into_iter()
+            .fold(template.to_string(), |prompt, (key, value)| {
+                prompt.replace(&format!("{{{{{}}}}}", key), &value.to_string())
+            })
+    }
+
+    /// Forbidden zones as prompt lines, e.g. "- Shoulder 0-20 with Elbow 0-60 (name)"
+    fn zone_rules(&self) -> String {
+        let zones: Vec<String> = self
+            .profile
+            .safety
+            .zones
+            .iter()
+            .map(|zone| {
+                let ranges: Vec<String> = ServoId::ALL
+                    .into_iter()
+                    .filter_map(|servo| {
+                        zone.range(servo)
+                            .map(|[min, max]| format!("{} {}-{}", servo.name(), min, max))
+                    })
+                    .collect();
+                format!("   - {} ({})", ranges.join(" with "), zone.name)
+            })
+            .collect();
+
+        if zones.is_empty() {
+            "   - none".to_string()
+        } else {
+            zones.join("\n")
+        }
+    }
+
+    /// Sequence names as a JSON alternative, e.g. `"WAVE" | "NOD_YES"`
+    fn sequence_names(&self) -> String {
+        let names: Vec<String> = self.sequences.names().iter().map(|name| format!("\"{}\"", name)).collect();
+        names.join(" | ")
+    }
+
+    /// Library sequences as prompt lines, e.g. "   - WAVE: Wave hello"
+    fn sequence_rules(&self) -> String {
+        self.sequences
+            .iter()
+            .map(|sequence| match &sequence.description {
+                Some(description) => format!("   - {}: {}", sequence.name, description),
+                None => format!("   - {}", sequence.name),
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    pub async fn generate_robot_script(
+        &self,
+        command: &str,
+        serial_port: &str,
+        baud: u32,
+    ) -> Result<String> {
+        let system_prompt = r##"You are a robot control code generator. Generate Python scripts to control a robot arm based on natural language commands.
+
+The robot arm has 4 servos (ESP32-C3 FNK0100):
+- Servo 0: Base (rotation) - {{BASE_MIN}}-{{BASE_MAX}} degrees
+- Servo 1: Shoulder - {{SHOULDER_MIN}}-{{SHOULDER_MAX}} degrees
+- Servo 2: Elbow - {{ELBOW_MIN}}-{{ELBOW_MAX}} degrees
+- Servo 3: Gripper - {{GRIPPER_OPEN}}=open, {{GRIPPER_CLOSED}}=closed
+
+Use this Python API with smooth motion planning:
+
+```python
+import serial
+import time
+import math
+
+ser = serial.Serial('{{SERIAL_PORT}}', {{BAUD}}, timeout=1)
+time.sleep(2)
+
+def read_positions():
+    """Ask the firmware where each servo is heading; home angles if it doesn't answer"""
+    positions = [{{BASE_HOME}}, {{SHOULDER_HOME}}, {{ELBOW_HOME}}, {{GRIPPER_HOME}}]
+    ser.reset_input_buffer()
+    ser.write(b"?\n")
+    deadline = time.time() + 1.0
+    reported = 0
+    while reported < 4 and time.time() < deadline:
+        words = ser.readline().decode(errors="ignore").split()
+        if len(words) == 6 and words[0] == "STATUS":
+            positions[int(words[1])] = round(float(words[3]))
+            reported += 1
+    return positions
+
+current_positions = read_positions()
+
+def calculate_duration(start_angle, end_angle, speed_factor=1.2):
+    """Calculate smooth movement duration based on angular distance"""
+    distance = abs(end_angle - start_angle)
+    base_duration = int(distance * speed_factor * 10)
+    return max(400, min(base_duration, 3000))
+
+def move_servo_smooth(servo_id, target_angle, duration_ms=None):
+    """Move servo with calculated smooth motion"""
+    if duration_ms is None:
+        duration_ms = calculate_duration(current_positions[servo_id], target_angle)
+
+    command = f"#{servo_id}M{target_angle}T{duration_ms}\n"
+    ser.write(command.encode())
+    current_positions[servo_id] = target_angle
+    time.sleep(duration_ms / 1000.0 + 0.15)
+
+def move_coordinated(movements, settle_time=0.2):
+    """Execute multiple servo movements with coordination"""
+    if not movements:
+        return
+
+    max_duration = 0
+    for servo_id, target_angle in movements:
+        duration = calculate_duration(current_positions[servo_id], target_angle)
+        max_duration = max(max_duration, duration)
+
+    # One write, so the safety check sees the moves as one coordinated command
+    commands = "".join(f"#{servo_id}M{target_angle}T{max_duration}\n" for servo_id, target_angle in movements)
+    ser.write(commands.encode())
+    for servo_id, target_angle in movements:
+        current_positions[servo_id] = target_angle
+
+    time.sleep(max_duration / 1000.0 + settle_time)
+
+V_MAX, A_MAX = 120.0, 200.0  # firmware limits, deg/s and deg/s^2
+SEGMENT_QUEUE = 8  # segments the firmware queues behind the running one
+
+def segment_peaks(h, v0, vf, t):
+    """Peak speed and acceleration of the firmware's quintic over h degrees in t seconds"""
+    a3 = (20 * h - (8 * vf + 12 * v0) * t) / (2 * t ** 3)
+    a4 = (-30 * h + (14 * vf + 16 * v0) * t) / (2 * t ** 4)
+    a5 = (12 * h - 6 * (vf + v0) * t) / (2 * t ** 5)
+    v_peak = a_peak = 0.0
+    for i in range(21):
+        s = t * i / 20
+        v_peak = max(v_peak, abs(v0 + 3 * a3 * s ** 2 + 4 * a4 * s ** 3 + 5 * a5 * s ** 4))
+        a_peak = max(a_peak, abs(6 * a3 * s + 12 * a4 * s ** 2 + 20 * a5 * s ** 3))
+    return v_peak, a_peak
+
+def move_trajectory(servo_ids, waypoints, min_duration_ms=None):
+    """Move through waypoints without stopping at the ones in between
+
+    servo_ids is one servo id with angle waypoints, or a tuple of ids with tuple waypoints.
+    Streams queued segments (#<servo>Q<angle>T<ms>V<v0>,<vf>) that pass through each
+    waypoint at a planned velocity, stretching any segment that would exceed the limits.
+    """
+    ids = (servo_ids,) if isinstance(servo_ids, int) else tuple(servo_ids)
+    points = [tuple(current_positions[s] for s in ids)]
+    points += [(p,) if isinstance(p, int) else tuple(p) for p in waypoints]
+    count = len(points) - 1
+    if count < 1:
+        return
+
+    durations = [
+        max((min_duration_ms or 0) / 1000.0, 0.1, max(abs(b - a) for a, b in zip(p, q)) / (0.45 * V_MAX))
+        for p, q in zip(points, points[1:])
+    ]
+    for _ in range(100):
+        velocities = []
+        for k in range(len(ids)):
+            angles = [p[k] for p in points]
+            v = [0.0]
+            for i in range(1, count):
+                before = (angles[i] - angles[i - 1]) / durations[i - 1]
+                after = (angles[i + 1] - angles[i]) / durations[i]
+                # Stop at reversals, otherwise pass through at half the mean slope
+                via = 0.0 if before * after <= 0 else (before + after) / 4
+                v.append(round(max(-0.9 * V_MAX, min(0.9 * V_MAX, via)), 1))
+            velocities.append(v + [0.0])
+
+        stretched = False
+        for i in range(count):
+            for k in range(len(ids)):
+                h = points[i + 1][k] - points[i][k]
+                v_peak, a_peak = segment_peaks(h, velocities[k][i], velocities[k][i + 1], durations[i])
+                if v_peak > 0.9 * V_MAX or a_peak > 0.9 * A_MAX:
+                    durations[i] *= 1.1
+                    stretched = True
+                    break
+        if not stretched:
+            break
+
+    # Keep no more than the firmware's queue ahead of the arm
+    ends = [sum(durations[: i + 1]) for i in range(count)]
+    elapsed = 0.0
+    for i in range(count):
+        if i > SEGMENT_QUEUE:
+            wait = ends[i - SEGMENT_QUEUE - 1] - elapsed
+            if wait > 0:
+                time.sleep(wait)
+                elapsed += wait
+        ms = round(durations[i] * 1000)
+        segments = "".join(
+            f"#{servo_id}Q{points[i + 1][k]}T{ms}V{velocities[k][i]:.1f},{velocities[k][i + 1]:.1f}\n"
+            for k, servo_id in enumerate(ids)
+        )
+        ser.write(segments.encode())
+
+    for k, servo_id in enumerate(ids):
+        current_positions[servo_id] = points[-1][k]
+    time.sleep(ends[-1] - elapsed + 0.15)
+
+def go_home():
+    """Return to home position with smooth coordinated motion"""
+    move_coordinated([(0, {{BASE_HOME}}), (1, {{SHOULDER_HOME}}), (2, {{ELBOW_HOME}}), (3, {{GRIPPER_HOME}})])
+
+def pick_and_place():
+    """
+    Hardware-calibrated pick and place for ESP32-C3 FNK0100 arm
+    Tested positions: Base={{PICK_BASE}}deg, Shoulder={{PICK_SHOULDER}}deg, Elbow={{PICK_ELBOW}}deg
+    Gripper: {{GRIPPER_OPEN}}=open, {{GRIPPER_CLOSED}}=closed
+    """
+    # APPROACH PHASE - Open gripper and rotate to pick position
+    move_servo_smooth(3, {{GRIPPER_OPEN}}, 800)
+    time.sleep(0.3)
+    move_servo_smooth(0, {{PICK_BASE}}, None)
+    time.sleep(0.2)
+
+    # Staged descent in one continuous motion to prevent slamming
+    move_trajectory((1, 2), [(50, current_positions[2]), (20, current_positions[2]), ({{PICK_SHOULDER}}, {{PICK_ELBOW}})])
+    time.sleep(0.4)
+
+    # GRIP PHASE - Two-stage grip: gentle touch then firm close
+    move_servo_smooth(3, {{GRIPPER_TOUCH}}, 700)
+    time.sleep(0.3)
+    move_servo_smooth(3, {{GRIPPER_CLOSED}}, 800)
+    time.sleep(0.3)
+
+    # LIFT PHASE - Staged lift without stopping in between
+    move_trajectory((1, 2), [(50, {{PICK_ELBOW}}), (90, {{PICK_ELBOW}}), (90, 90)])
+    time.sleep(0.3)
+
+    # TRANSPORT PHASE - Rotate to place position
+    move_servo_smooth(0, {{PLACE_BASE}}, None)
+    time.sleep(0.4)
+
+    # PLACE PHASE - Staged descent
+    move_trajectory((1, 2), [(50, 90), (20, 90), ({{PLACE_SHOULDER}}, {{PLACE_ELBOW}})])
+    time.sleep(0.4)
+
+    # Release gripper
+    move_servo_smooth(3, {{GRIPPER_OPEN}}, 800)
+    time.sleep(0.3)
+
+    # RETURN HOME PHASE - Lift and return to neutral
+    move_trajectory((1, 2), [(50, {{PLACE_ELBOW}}), (90, {{PLACE_ELBOW}}), (90, {{ELBOW_HOME}})])
+    time.sleep(0.2)
+    move_servo_smooth(0, {{BASE_HOME}}, None)
+    time.sleep(0.3)
+    move_servo_smooth(3, {{GRIPPER_HOME}}, 600)
+    time.sleep(0.2)
+```
+
+CRITICAL MOTION PLANNING RULES:
+1. Always use move_servo_smooth() for single servo movements - it calculates proper timing
+2. Use move_coordinated() when multiple servos need to move together smoothly
+   and move_trajectory() to pass through several waypoints without stopping (staged descents, waves)
+3. Add settle_time delays (0.2-0.3s) after reaching positions before gripper operations
+4. Never use fixed durations - let calculate_duration() compute based on angular distance
+5. Gripper operations should have explicit durations: 600-800ms
+6. Always include time.sleep() after movements for mechanical settling
+7. current_positions is read from the arm at startup; never overwrite it with assumed angles
+
+Generate complete, executable Python scripts. Include imports, serial setup, and clean code structure.
+Always close the serial connection at the end with: ser.close()
+
+Examples:
+- "wave" -> move_trajectory(0, [60, 120, 60, 120, {{BASE_HOME}}])
+- "pick and place" -> use the pick_and_place function with calibrated positions
+- "open gripper" -> move_servo_smooth(3, {{GRIPPER_OPEN}}, 600)
+- "close gripper" -> move_servo_smooth(3, {{GRIPPER_CLOSED}}, 800)
+- "go home" -> go_home()
+
+Now generate a script for this command:
+"##;
+
+        let system_prompt = self
+            .render_prompt(system_prompt)
+            .replace("{{SERIAL_PORT}}", serial_port)
+            .replace("{{BAUD}}", &baud.to_string());
+
+        let script = self
+            .backend
+            .generate(&Prompt {
+                task: Task::Script,
+                command,
+                text: format!("{}\n\nCommand: {}", system_prompt, command),
+                schema: None,
+            })
+            .await?;
+
+        // Extract Python code from markdown if present
+        let script = self.extract_python_code(&script);
+
+        Ok(script)
+    }
+
+    /// Relative moves ("turn left 20 degrees") are resolved from `positions`
+    pub async fn interpret_command(&self, command: &str, positions: [u8; 4]) -> Result<CommandResult> {
+        // The grammar only knows angles are 0-180; anything the limits refuse goes to the LLM
+        let parsed = self.grammar.parse(command, positions);
+        if let Some(result) = parsed.filter(|r| r.validate(&self.profile, &self.sequences).is_ok()) {
+            return Ok(result);
+        }
+
+        let system_prompt = r##"You are a robot command interpreter. Parse natural language commands and return JSON.
+
+Supported actions:
+- "move": Move a specific servo to an angle
+- "move_to": Move the gripper tip to a Cartesian position in millimetres
+- "sequence": Execute a predefined sequence:
+{{SEQUENCES}}
+- "home": Return to home position
+- "wait": Pause for a number of milliseconds (at most 10000)
+- "multi": Several commands run one after another, listed in "commands"
+- "parallel": Moves started together, listed in "commands" (only inside "multi")
+- "stop": Emergency stop
+- "unknown": Command not recognized
+
+Servo IDs (ESP32-C3 FNK0100) and their calibrated limits:
+- 0: Base (rotation) - {{BASE_MIN}}-{{BASE_MAX}} degrees
+- 1: Shoulder - {{SHOULDER_MIN}}-{{SHOULDER_MAX}} degrees
+- 2: Elbow - {{ELBOW_MIN}}-{{ELBOW_MAX}} degrees
+- 3: Gripper - {{GRIPPER_OPEN}}=open, {{GRIPPER_CLOSED}}=closed
+
+Return JSON in this format:
+{
+  "action": "move" | "move_to" | "sequence" | "home" | "wait" | "multi" | "parallel" | "stop" | "unknown",
+  "servo": 0-3 (only for "move" action),
+  "angle": degrees within the servo's limits (only for "move" action),
+  "x", "y", "z": millimetres (only for "move_to" action; x forward, y left, z up from the table),
+  "sequence_name": {{SEQUENCE_NAMES}} (only for "sequence" action),
+  "ms": milliseconds (only for "wait" action),
+  "commands": [...] (only for "multi" and "parallel"),
+  "message": "explanation text" (only for "unknown" action)
+}
+
+Use "multi" only when the command asks for more than one thing. Its commands may be
+"move", "move_to", "sequence", "home", "wait" and "parallel"; a "parallel" group holds
+only "move" and "move_to" commands, each servo at most once.
+
+Examples:
+- "wave" -> {"action": "sequence", "sequence_name": "WAVE"}
+- "pick and place" -> {"action": "sequence", "sequence_name": "PICK_PLACE"}
+- "move base to 45 degrees" -> {"action": "move", "servo": 0, "angle": 45}
+- "move to x 120 y 40 z 20" -> {"action": "move_to", "x": 120, "y": 40, "z": 20}
+- "open gripper" -> {"action": "move", "servo": 3, "angle": {{GRIPPER_OPEN}}}
+- "close gripper" -> {"action": "move", "servo": 3, "angle": {{GRIPPER_CLOSED}}}
+- "go home" -> {"action": "home"}
+- "stop" -> {"action": "stop"}
+- "open the gripper, rotate the base to 45 and then wave" -> {"action": "multi", "commands": [{"action": "move", "servo": 3, "angle": {{GRIPPER_OPEN}}}, {"action": "move", "servo": 0, "angle": 45}, {"action": "sequence", "sequence_name": "WAVE"}]}
+- "turn the base to 120 while closing the gripper, wait a second, then go home" -> {"action": "multi", "commands": [{"action": "parallel", "commands": [{"action": "move", "servo": 0, "angle": 120}, {"action": "move", "servo": 3, "angle": {{GRIPPER_CLOSED}}}]}, {"action": "wait", "ms": 1000}, {"action": "home"}]}
+
+Now parse this command and respond with ONLY valid JSON:
+"##;
+
+        let system_prompt = self
+            .render_prompt(system_prompt)
+            .replace("{{SEQUENCE_NAMES}}", &self.sequence_names())
+            .replace("{{SEQUENCES}}", &self.sequence_rules());
+        let mut prompt = Prompt {
+            task: Task::Interpret,
+            command,
+            text: format!("{}\n\nCommand: {}", system_prompt, command),
+            schema: Some(CommandResult::response_schema(&self.sequences)),
+        };
+
+        let response_text = self.backend.generate(&prompt).await?;
+        let error = match self.parse_command_result(&response_text) {
+            Ok(result) => return Ok(result),
+            Err(e) => e,
+        };
+
+        // One repair attempt: show the model its answer and what was wrong with it
+        println!("{} Interpreter answer rejected ({:#}), asking again", "⚠".yellow(), error);
+        prompt.text = format!(
+            "{}\n\nYour previous answer was:\n{}\n\nIt was rejected: {:#}\nRespond again with ONLY corrected JSON for the same command.",
+            prompt.text,
+            response_text.trim(),
+            error
+        );
+        let response_text = self.backend.generate(&prompt).await?;
+        self.parse_command_result(&response_text)
+            .context("The interpreter's answer was still invalid after a repair attempt")
+    }
+
+    fn parse_command_result(&self, text: &str) -> Result<CommandResult> {
+        // Backends without a schema may still wrap the JSON in markdown
+        let result: CommandResult =
+            serde_json::from_str(&self.extract_json(text)).context("Failed to parse command result JSON")?;
+        result.validate(&self.profile, &self.sequences)?;
+        Ok(result)
+    }
+
+    pub async fn generate_action_plan(&self, command: &str, positions: [u8; 4]) -> Result<ActionPlan> {
+        let parsed = self.grammar.parse(command, positions);
+        if let Some(plan) = parsed.and_then(|result| result.to_plan(&self.profile).ok()) {
+            return Ok(plan);
+        }
+
+        let system_prompt = r##"You are a robot motion planner. Turn natural language commands into an action plan for a 4-servo robot arm (ESP32-C3 FNK0100).
+
+Servos and their calibrated limits:
+- 0: Base (rotation) - {{BASE_MIN}}-{{BASE_MAX}} degrees, home {{BASE_HOME}}
+- 1: Shoulder - {{SHOULDER_MIN}}-{{SHOULDER_MAX}} degrees, home {{SHOULDER_HOME}}
+- 2: Elbow - {{ELBOW_MIN}}-{{ELBOW_MAX}} degrees, home {{ELBOW_HOME}}
+- 3: Gripper - {{GRIPPER_OPEN}}=open, {{GRIPPER_TOUCH}}=touching an object, {{GRIPPER_CLOSED}}=closed
+
+The plan is a JSON object with an optional "summary" and an ordered list of "steps". Step types:
+- {"type": "move", "servo": 0-3, "angle": degrees, "duration_ms": optional}: move one servo
+- {"type": "coordinated_move", "moves": [{"servo": 0-3, "angle": degrees}, ...], "duration_ms": optional}: move several servos so they arrive together
+- {"type": "gripper", "state": "open" | "touch" | "closed"}
+- {"type": "wait", "ms": milliseconds}
+- {"type": "pose", "name": "pick" | "place"}: go to a calibrated pose (pick = base {{PICK_BASE}}, shoulder {{PICK_SHOULDER}}, elbow {{PICK_ELBOW}}; place = base {{PLACE_BASE}}, shoulder {{PLACE_SHOULDER}}, elbow {{PLACE_ELBOW}})
+- {"type": "home"}: return every servo to home
+- {"type": "trajectory", "waypoints": [{"moves": [{"servo": 0-3, "angle": degrees}, ...], "duration_ms": optional} or {"pose": "pick" | "place"}, ...]}: pass through the waypoints in one smooth motion without stopping between them; servos not named keep their previous angle
+- {"type": "sequence", "name": {{SEQUENCE_NAMES}}}: run a sequence from the library:
+{{SEQUENCES}}
+
+Rules:
+1. Stay within the limits above; plans outside them are rejected
+2. No joint may change by more than {{MAX_STEP}} degrees in one step; split large moves
+3. Never end a step inside a forbidden zone:
+{{ZONES}}
+4. Prefer sequences and poses over spelling out their moves
+5. Waits are at most 10000ms and durations between 100 and 10000ms
+6. Moves already wait for the arm to arrive; only add waits for deliberate pauses
+
+Examples:
+- "wave" -> {"summary": "Wave", "steps": [{"type": "sequence", "name": "WAVE"}]}
+- "open the gripper and look left" -> {"summary": "Open gripper, turn left", "steps": [{"type": "gripper", "state": "open"}, {"type": "move", "servo": 0, "angle": 150}]}
+- "lower the shoulder slowly in two stages" -> {"summary": "Staged descent", "steps": [{"type": "trajectory", "waypoints": [{"moves": [{"servo": 1, "angle": 50}]}, {"moves": [{"servo": 1, "angle": 20}], "duration_ms": 1500}]}]}
+- "reach down, wait a second and come back" -> {"summary": "Reach down and return", "steps": [{"type": "coordinated_move", "moves": [{"servo": 1, "angle": 50}, {"servo": 2, "angle": 120}]}, {"type": "wait", "ms": 1000}, {"type": "home"}]}
+"##;
+
+        let system_prompt = self
+            .render_prompt(system_prompt)
+            .replace("{{MAX_STEP}}", &self.profile.safety.max_step.to_string())
+            .replace("{{ZONES}}", &self.zone_rules())
+            .replace("{{SEQUENCE_NAMES}}", &self.sequence_names())
+            .replace("{{SEQUENCES}}", &self.sequence_rules());
+        let response_text = self
+            .backend
+            .generate(&Prompt {
+                task: Task::Plan,
+                command,
+                text: format!("{}\n\nCommand: {}", system_prompt, command),
+                schema: Some(ActionPlan::response_schema()),
+            })
+            .await?;
+
+        serde_json::from_str(&self.extract_json(&response_text)).context("Failed to parse action plan JSON")
+    }
+
+    fn extract_json(&self, text: &str) -> String {
+        // Check if JSON is wrapped in markdown code blocks
+        if text.contains("```json") {
+            let start = text.find("```json").unwrap() + 7;
+            let end = text[start..].find("```").unwrap_or(text.len() - start);
+            text[start..start + end].trim().to_string()
+        } else if text.contains("```") {
+            let start = text.find("```").unwrap() + 3;
+            let end = text[start..].find("```").unwrap_or(text.len() - start);
+            text[start..start + end].trim().to_string()
+        } else {
+            text.trim().to_string()
+        }
+    }
+
+    fn extract_python_code(&self, text: &str) -> String {
+        // Check if code is wrapped in markdown code blocks
+        if text.contains("```python") {
+            let start = text.find("```python").unwrap() + 9;
+            let end = text[start..].find("```").unwrap_or(text.len() - start);
+            text[start..start + end].trim().to_string()
+        } else if text.contains("```") {
+            let start = text.find("```").unwrap() + 3;
+            let end = text[start..].find("```").unwrap_or(text.len() - start);
+            text[start..start + end].trim().to_string()
+        } else {
+            text.trim().to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// Validates a command given as JSON against the default profile and the built-in sequences
+    fn validate(json: serde_json::Value) -> Result<()> {
+        let result: CommandResult = serde_json::from_value(json).unwrap();
+        result.validate(&CalibrationProfile::default(), &Library::builtin())
+    }
+
+    fn error(json: serde_json::Value) -> String {
+        format!("{:#}", validate(json).unwrap_err())
+    }
+
+    #[test]
+    fn moves_need_a_servo_and_an_angle_within_its_limits() {
+        assert!(validate(json!({"action": "move", "servo": 1, "angle": 9})).is_ok());
+        assert_eq!(
+            error(json!({"action": "move", "servo": 1, "angle": 0})),
+            "Shoulder angle 0 is outside its calibrated limits 9-180"
+        );
+        assert_eq!(error(json!({"action": "move", "servo": 4, "angle": 90})), "servo must be 0-3, not 4");
+        assert_eq!(error(json!({"action": "move", "servo": 0})), "\"move\" needs an angle");
+        assert!(validate(json!({"action": "move_to", "x": 120.0, "y": 0.0})).is_err());
+    }
+
+    #[test]
+    fn sequences_must_be_in_the_library() {
+        assert!(validate(json!({"action": "sequence", "sequence_name": "pick_place"})).is_ok());
+        assert!(error(json!({"action": "sequence", "sequence_name": "DANCE"})).starts_with("Unknown sequence"));
+        assert!(validate(json!({"action": "sequence"})).is_err());
+    }
+
+    #[test]
+    fn waits_are_bounded() {
+        assert!(validate(json!({"action": "wait", "ms": MAX_WAIT_MS})).is_ok());
+        assert!(validate(json!({"action": "wait", "ms": MAX_WAIT_MS + 1})).is_err());
+        assert!(validate(json!({"action": "wait"})).is_err());
+    }
+
+    #[test]
+    fn actions_without_fields_and_unknown_actions() {
+        for action in ["home", "stop", "unknown"] {
+            assert!(validate(json!({ "action": action })).is_ok(), "{}", action);
+        }
+        assert!(error(json!({"action": "dance"})).starts_with("Unknown action \"dance\""));
+    }
+
+    #[test]
+    fn multi_checks_every_command_and_what_it_may_contain() {
+        let multi = |commands: serde_json::Value| json!({"action": "multi", "commands": commands});
+
+        assert!(validate(multi(json!([
+            {"action": "move", "servo": 0, "angle": 45},
+            {"action": "wait", "ms": 500},
+            {"action": "parallel", "commands": [
+                {"action": "move", "servo": 1, "angle": 60},
+                {"action": "move", "servo": 2, "angle": 60},
+            ]},
+            {"action": "sequence", "sequence_name": "WAVE"},
+            {"action": "home"},
+        ])))
+        .is_ok());
+
+        assert_eq!(
+            error(multi(json!([{"action": "home"}, {"action": "move", "servo": 1, "angle": 0}]))),
+            "command 2 of \"multi\": Shoulder angle 0 is outside its calibrated limits 9-180"
+        );
+        assert!(validate(multi(json!([]))).is_err());
+        assert!(validate(multi(json!(vec![json!({"action": "home"}); MAX_COMMANDS + 1]))).is_err());
+        // Nothing nests deeper than a parallel group inside a multi
+        assert!(validate(multi(json!([{"action": "multi", "commands": [{"action": "home"}]}]))).is_err());
+        assert!(validate(multi(json!([{"action": "stop"}]))).is_err());
+    }
+
+    #[test]
+    fn parallel_groups_only_move_each_servo_once() {
+        let parallel = |commands: serde_json::Value| json!({"action": "parallel", "commands": commands});
+
+        assert_eq!(
+            error(parallel(json!([
+                {"action": "move", "servo": 0, "angle": 45},
+                {"action": "move", "servo": 0, "angle": 90},
+            ]))),
+            "\"parallel\" moves the Base twice"
+        );
+        assert!(validate(parallel(json!([{"action": "wait", "ms": 100}]))).is_err());
+        assert!(validate(parallel(json!([{"action": "sequence", "sequence_name": "WAVE"}]))).is_err());
+    }
+}
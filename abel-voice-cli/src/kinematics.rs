@@ -0,0 +1,213 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::driver::{ServoId, MAX_ANGLE};
+
+/// Link lengths and servo zero points of the 3-DOF arm
+///
+/// Lengths are in millimetres, offsets are servo angles in degrees. The frame
+/// has its origin on the table under the base axis, +x pointing forward when
+/// the base is at `base_offset`, +y to the left and +z up.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct ArmGeometry {
+    /// Height of the shoulder axis above the table
+    pub base_height: f32,
+    /// Shoulder axis to elbow axis
+    pub upper_arm: f32,
+    /// Elbow axis to the gripper tip
+    pub forearm: f32,
+    /// Base servo angle that points the arm along +x
+    pub base_offset: f32,
+    /// Shoulder servo angle at which the upper arm is horizontal
+    pub shoulder_offset: f32,
+    /// Elbow servo angle at which the forearm is perpendicular to the upper arm
+    pub elbow_offset: f32,
+}
+
+impl Default for ArmGeometry {
+    /// Nominal FNK0100 dimensions, matching the calibrated pick pose (81/9/84) touching the table
+    fn default() -> Self {
+        Self {
+            base_height: 105.0,
+            upper_arm: 80.0,
+            forearm: 120.0,
+            base_offset: 90.0,
+            shoulder_offset: 0.0,
+            elbow_offset: 90.0,
+        }
+    }
+}
+
+/// Gripper position in millimetres
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct Point {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+/// Servo angles of the three positioning joints, in degrees
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct JointAngles {
+    pub base: f32,
+    pub shoulder: f32,
+    pub elbow: f32,
+}
+
+impl JointAngles {
+    /// Rounds to servo commands for a coordinated move
+    pub fn to_moves(self) -> Vec<(ServoId, u8)> {
+        vec![
+            (ServoId::Base, self.base.round() as u8),
+            (ServoId::Shoulder, self.shoulder.round() as u8),
+            (ServoId::Elbow, self.elbow.round() as u8),
+        ]
+    }
+}
+
+/// Gripper tip position for the given servo angles
+pub fn forward(geometry: &ArmGeometry, joints: JointAngles) -> Point {
+    let yaw = (joints.base - geometry.base_offset).to_radians();
+    let upper = (joints.shoulder - geometry.shoulder_offset).to_radians();
+    let fore = upper + (joints.elbow - geometry.elbow_offset - 90.0).to_radians();
+
+    let reach = geometry.upper_arm * upper.cos() + geometry.forearm * fore.cos();
+    let height = geometry.upper_arm * upper.sin() + geometry.forearm * fore.sin();
+
+    Point {
+        x: reach * yaw.cos(),
+        y: reach * yaw.sin(),
+        z: geometry.base_height + height,
+    }
+}
+
+/// Servo angles that put the gripper tip at `target`, using the elbow-up solution
+pub fn inverse(geometry: &ArmGeometry, target: Point) -> Result<JointAngles> {
+    if target.z < 0.0 {
+        anyhow::bail!("Target z={:.0}mm is below the table", target.z);
+    }
+
+    let l1 = geometry.upper_arm;
+    let l2 = geometry.forearm;
+
+    let reach = target.x.hypot(target.y);
+    let height = target.z - geometry.base_height;
+    let distance = reach.hypot(height);
+
+    if distance > l1 + l2 {
+        anyhow::bail!(
+            "Target is {:.0}mm from the shoulder, beyond the arm's {:.0}mm reach",
+            distance,
+            l1 + l2
+        );
+    }
+    if distance < (l1 - l2).abs() {
+        anyhow::bail!(
+            "Target is {:.0}mm from the shoulder, too close for the arm to fold to",
+            distance
+        );
+    }
+
+    // Interior bend between the upper arm and forearm directions
+    let cos_bend = ((distance * distance - l1 * l1 - l2 * l2) / (2.0 * l1 * l2)).clamp(-1.0, 1.0);
+    let bend = cos_bend.acos();
+
+    let upper = height.atan2(reach) + (l2 * bend.sin()).atan2(l1 + l2 * bend.cos());
+    let yaw = if reach > f32::EPSILON {
+        target.y.atan2(target.x)
+    } else {
+        0.0
+    };
+
+    let joints = JointAngles {
+        base: geometry.base_offset + yaw.to_degrees(),
+        shoulder: geometry.shoulder_offset + upper.to_degrees(),
+        elbow: geometry.elbow_offset + 90.0 - bend.to_degrees(),
+    };
+
+    let checks = [
+        (ServoId::Base, joints.base),
+        (ServoId::Shoulder, joints.shoulder),
+        (ServoId::Elbow, joints.elbow),
+    ];
+    for (servo, angle) in checks {
+        if angle < -0.5 || angle > MAX_ANGLE as f32 + 0.5 {
+            anyhow::bail!(
+                "Target needs {} at {:.0}°, outside the servo's 0-{}° range",
+                servo.name(),
+                angle,
+                MAX_ANGLE
+            );
+        }
+    }
+
+    Ok(JointAngles {
+        base: joints.base.clamp(0.0, MAX_ANGLE as f32),
+        shoulder: joints.shoulder.clamp(0.0, MAX_ANGLE as f32),
+        elbow: joints.elbow.clamp(0.0, MAX_ANGLE as f32),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: f32, b: f32, tolerance: f32) {
+        assert!((a - b).abs() <= tolerance, "{} != {}", a, b);
+    }
+
+    #[test]
+    fn inverse_undoes_forward() {
+        let geometry = ArmGeometry::default();
+        for base in [30.0, 90.0, 150.0] {
+            // Below vertical the tip stays in front of the base axis, where the base angle is unique
+            for shoulder in [20.0, 60.0, 85.0] {
+                for elbow in [45.0, 100.0, 170.0] {
+                    let joints = JointAngles { base, shoulder, elbow };
+                    let point = forward(&geometry, joints);
+                    if point.z < 0.0 {
+                        continue;
+                    }
+
+                    let solved = inverse(&geometry, point).unwrap_or_else(|e| panic!("{:?}: {}", joints, e));
+                    assert_close(solved.base, base, 0.05);
+                    assert_close(solved.shoulder, shoulder, 0.05);
+                    assert_close(solved.elbow, elbow, 0.05);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn forward_reaches_the_point_inverse_solved_for() {
+        let geometry = ArmGeometry::default();
+        let targets = [
+            Point { x: 150.0, y: 0.0, z: 20.0 },
+            Point { x: 80.0, y: -60.0, z: 150.0 },
+            Point { x: 0.0, y: 120.0, z: 60.0 },
+        ];
+        for target in targets {
+            let point = forward(&geometry, inverse(&geometry, target).unwrap());
+            assert_close(point.x, target.x, 0.05);
+            assert_close(point.y, target.y, 0.05);
+            assert_close(point.z, target.z, 0.05);
+        }
+    }
+
+    #[test]
+    fn the_calibrated_pick_pose_touches_the_table() {
+        let point = forward(&ArmGeometry::default(), JointAngles { base: 81.0, shoulder: 9.0, elbow: 84.0 });
+        assert_close(point.z, 0.0, 5.0);
+    }
+
+    #[test]
+    fn unreachable_targets_are_rejected() {
+        let geometry = ArmGeometry::default();
+        assert!(inverse(&geometry, Point { x: 100.0, y: 0.0, z: -1.0 }).is_err());
+        assert!(inverse(&geometry, Point { x: 250.0, y: 0.0, z: 105.0 }).is_err());
+        assert!(inverse(&geometry, Point { x: 0.0, y: 0.0, z: 105.0 }).is_err());
+        // Behind the base needs the base servo past 180°
+        assert!(inverse(&geometry, Point { x: -20.0, y: -150.0, z: 105.0 }).is_err());
+    }
+}
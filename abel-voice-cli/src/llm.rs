@@ -0,0 +1,114 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use colored::Colorize;
+
+use crate::gemini::GeminiBackend;
+use crate::openai_chat::OpenAiChat;
+use crate::profile::CalibrationProfile;
+use crate::rules::RuleBased;
+use crate::sequence::Library;
+
+/// What a prompt asks for, so backends that don't read prompts know what to answer
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Task {
+    /// A Python script controlling the arm
+    Script,
+    /// A `CommandResult` as JSON
+    Interpret,
+    /// An `ActionPlan` as JSON
+    Plan,
+}
+
+pub struct Prompt<'a> {
+    pub task: Task,
+    /// The user's words on their own
+    pub command: &'a str,
+    /// Full prompt with instructions and the command
+    pub text: String,
+    /// JSON schema (Gemini's OpenAPI subset) the answer must match
+    pub schema: Option<serde_json::Value>,
+}
+
+/// Something that answers prompts: a hosted model, a local one, or fixed rules
+#[async_trait]
+pub trait LlmBackend: Send + Sync {
+    /// Short description for logs, e.g. "Gemini gemini-2.0-flash-exp"
+    fn name(&self) -> String;
+
+    async fn generate(&self, prompt: &Prompt<'_>) -> Result<String>;
+}
+
+/// Tries each backend in turn until one answers
+pub struct Fallback {
+    backends: Vec<Box<dyn LlmBackend>>,
+}
+
+#[async_trait]
+impl LlmBackend for Fallback {
+    fn name(&self) -> String {
+        let names: Vec<String> = self.backends.iter().map(|b| b.name()).collect();
+        names.join(" → ")
+    }
+
+    async fn generate(&self, prompt: &Prompt<'_>) -> Result<String> {
+        let mut failures = Vec::new();
+        for (i, backend) in self.backends.iter().enumerate() {
+            match backend.generate(prompt).await {
+                Ok(text) => return Ok(text),
+                Err(e) => {
+                    if let Some(next) = self.backends.get(i + 1) {
+                        println!(
+                            "{} {} failed ({}), trying {}",
+                            "⚠".yellow(),
+                            backend.name(),
+                            e,
+                            next.name()
+                        );
+                    }
+                    failures.push(format!("{}: {:#}", backend.name(), e));
+                }
+            }
+        }
+        anyhow::bail!("Every LLM backend failed:\n  {}", failures.join("\n  "))
+    }
+}
+
+/// The backends configured in the environment (or `.env`), in the order they are tried
+///
+/// - `ABEL_LLM`: comma-separated list of `gemini`, `openai` and `rules`, default `gemini,rules`
+/// - `GEMINI_API_KEY`, `ABEL_GEMINI_MODEL`: key and model for Gemini
+/// - `ABEL_LLM_URL`, `ABEL_LLM_MODEL`, `ABEL_LLM_API_KEY`: an OpenAI-compatible chat server
+pub fn from_env(profile: &CalibrationProfile, sequences: &Library) -> Result<Box<dyn LlmBackend>> {
+    let list = std::env::var("ABEL_LLM")
+        .ok()
+        .filter(|list| !list.trim().is_empty())
+        .unwrap_or_else(|| "gemini,rules".to_string());
+    let setting = |name: &str| std::env::var(name).ok().filter(|value| !value.trim().is_empty());
+
+    let mut backends: Vec<Box<dyn LlmBackend>> = Vec::new();
+    for name in list.split(',').map(|name| name.trim().to_lowercase()) {
+        match name.as_str() {
+            "gemini" => {
+                let api_key = setting("GEMINI_API_KEY").context(
+                    "GEMINI_API_KEY environment variable not set (or set ABEL_LLM to use another backend)",
+                )?;
+                let model = setting("ABEL_GEMINI_MODEL").unwrap_or_else(|| "gemini-2.0-flash-exp".to_string());
+                backends.push(Box::new(GeminiBackend::new(api_key, model)));
+            }
+            "openai" | "ollama" | "llama.cpp" => {
+                let url = setting("ABEL_LLM_URL").context(
+                    "ABEL_LLM_URL must be set to the chat server's base URL (e.g. http://localhost:11434/v1)",
+                )?;
+                let model = setting("ABEL_LLM_MODEL").context("ABEL_LLM_MODEL must name the model to use")?;
+                backends.push(Box::new(OpenAiChat::new(&url, model, setting("ABEL_LLM_API_KEY"))?));
+            }
+            "rules" => backends.push(Box::new(RuleBased::new(profile, sequences))),
+            other => anyhow::bail!("Unknown LLM backend '{}' in ABEL_LLM (use gemini, openai or rules)", other),
+        }
+    }
+
+    if backends.len() == 1 {
+        return Ok(backends.remove(0));
+    }
+    Ok(Box::new(Fallback { backends }))
+}
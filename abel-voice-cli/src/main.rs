@@ -1,19 +1,54 @@
 mod audio;
 mod whisper;
 mod gemini;
+mod grammar;
+mod interpreter;
+mod llm;
+mod openai_chat;
+mod rules;
 mod deepgram;
+mod driver;
+mod dryrun;
+mod estop;
 mod executor;
+mod firmware;
+mod kinematics;
+mod plan;
+mod preflight;
+mod profile;
+mod safety;
+mod sandbox;
+mod sequence;
+mod serial;
 mod server;
-
-use anyhow::Result;
+mod sim;
+mod stt;
+mod teach;
+mod trajectory;
+mod venv;
+mod wizard;
+mod whisper_cpp;
+
+use anyhow::{Context, Result};
 use clap::{Parser, Subcommand};
 use colored::Colorize;
 use std::path::PathBuf;
 
+use driver::{ArmDriver, ServoId};
+use serial::SerialConfig;
+
 #[derive(Parser)]
 #[command(name = "abel-voice")]
-#[command(about = "Voice-controlled robot arm CLI with AI-generated Python scripts", long_about = None)]
+#[command(about = "Voice-controlled robot arm CLI with AI-generated action plans", long_about = None)]
 struct Cli {
+    /// Serial port of the arm (auto-detected when omitted or set to "auto")
+    #[arg(long, global = true, env = "ABEL_SERIAL_PORT")]
+    port: Option<String>,
+
+    /// Serial baud rate
+    #[arg(long, global = true, default_value_t = serial::DEFAULT_BAUD)]
+    baud: u32,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -22,37 +57,66 @@ struct Cli {
 enum Commands {
     /// Start HTTP service for GUI integration
     Serve {
-        /// Port to bind the service to
-        #[arg(short, long, default_value = "8080")]
-        port: u16,
+        /// HTTP port to bind the service to
+        #[arg(short = 'p', long = "http-port", default_value = "8080")]
+        http_port: u16,
     },
 
     /// Start an interactive voice control session
     Session {
-        /// Save generated scripts to directory
+        /// Save generated plans to directory
         #[arg(short, long, default_value = "./scripts")]
         output_dir: PathBuf,
 
         /// Enable text-to-speech responses
         #[arg(short, long)]
         tts: bool,
+
+        /// Generate and run Python scripts instead of action plans
+        #[arg(long)]
+        python: bool,
+
+        /// Print what each command would do instead of moving the arm
+        #[arg(long)]
+        dry_run: bool,
+
+        #[command(flatten)]
+        sandbox: SandboxArgs,
     },
 
     /// Record and execute a single voice command
     Once {
-        /// Save generated script to file
+        /// Save generated plan to file
         #[arg(short, long)]
         save: Option<PathBuf>,
 
         /// Enable text-to-speech response
         #[arg(short, long)]
         tts: bool,
+
+        /// Generate and run a Python script instead of an action plan
+        #[arg(long)]
+        python: bool,
+
+        /// Print what the command would do instead of moving the arm
+        #[arg(long)]
+        dry_run: bool,
+
+        #[command(flatten)]
+        sandbox: SandboxArgs,
     },
 
-    /// Execute a saved Python script
+    /// Execute a saved action plan (.json) or Python script
     Run {
-        /// Path to Python script
+        /// Path to the plan or script
         script: PathBuf,
+
+        /// Record the servo commands and print a timeline instead of moving the arm
+        #[arg(long)]
+        dry_run: bool,
+
+        #[command(flatten)]
+        sandbox: SandboxArgs,
     },
 
     /// Calibrate servo ranges interactively
@@ -60,6 +124,10 @@ enum Commands {
         /// Servo ID to calibrate (0-3)
         #[arg(short, long)]
         servo: Option<u8>,
+
+        /// Guided wizard: jog each joint with the arrow keys, record poses and test them
+        #[arg(short, long, conflicts_with = "servo")]
+        wizard: bool,
     },
 
     /// Test smooth motion with different durations
@@ -80,6 +148,188 @@ enum Commands {
         #[arg(short, long, default_value = "1000")]
         duration: u16,
     },
+
+    /// Predict the firmware's quintic profile for a move without sending it
+    Trajectory {
+        /// Start angle
+        #[arg(long, default_value = "90")]
+        from: f32,
+
+        /// End angle
+        #[arg(long)]
+        to: f32,
+
+        /// Requested duration in milliseconds
+        #[arg(short, long, default_value = "1000")]
+        duration: u64,
+
+        /// Export the sampled profile as CSV
+        #[arg(long)]
+        csv: Option<PathBuf>,
+
+        /// Sample rate in Hz (the firmware updates at 200Hz)
+        #[arg(long, default_value = "200")]
+        rate: f32,
+    },
+
+    /// Move to a pose saved in the calibration profile (lists poses when no name is given)
+    Pose {
+        name: Option<String>,
+    },
+
+    /// Return every servo to its calibrated home angle
+    Home,
+
+    /// Show where every servo is, as reported by the firmware
+    Status {
+        /// Keep refreshing until Ctrl+C
+        #[arg(short, long)]
+        watch: bool,
+    },
+
+    /// Move the gripper tip to a Cartesian position (millimetres)
+    MoveTo {
+        #[arg(long, allow_hyphen_values = true)]
+        x: f32,
+
+        #[arg(long, allow_hyphen_values = true)]
+        y: f32,
+
+        #[arg(long, allow_hyphen_values = true)]
+        z: f32,
+
+        /// Only print the joint solution
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Show where the gripper tip is for the given joint angles
+    Fk {
+        #[arg(long)]
+        base: f32,
+
+        #[arg(long)]
+        shoulder: f32,
+
+        #[arg(long)]
+        elbow: f32,
+    },
+
+    /// List serial ports that look like the arm's USB bridge
+    Ports,
+
+    /// Run a virtual arm on a pseudo-terminal that speaks the firmware protocol
+    Sim {
+        /// Also expose the simulator at this path (symlink to the pseudo-terminal)
+        #[arg(short, long)]
+        link: Option<PathBuf>,
+    },
+
+    /// Record a new sequence by jogging the arm and capturing timed waypoints
+    Teach {
+        /// Name to save the sequence under
+        name: String,
+    },
+
+    /// List, inspect, run and edit sequences from the library
+    Seq {
+        #[command(subcommand)]
+        action: SeqAction,
+    },
+
+    /// Manage the Python virtual environment scripts run in
+    Env {
+        #[command(subcommand)]
+        action: EnvAction,
+    },
+}
+
+#[derive(Subcommand)]
+enum SeqAction {
+    /// Show every built-in and user sequence
+    List,
+
+    /// Show the steps of a sequence and how long it takes
+    Show { name: String },
+
+    /// Run a sequence on the arm
+    Run {
+        name: String,
+
+        /// Print the timeline instead of moving the arm
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Open a sequence in $EDITOR (built-ins are copied to the sequence directory first)
+    Edit { name: String },
+
+    /// Multiply every duration and wait of a sequence, e.g. 2 for half speed
+    Scale {
+        name: String,
+
+        factor: f32,
+
+        /// Save the result as a new sequence instead of overwriting this one
+        #[arg(long)]
+        save_as: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum EnvAction {
+    /// Check the interpreter and the pinned packages
+    Status,
+
+    /// Recreate a broken environment and install missing or mismatched packages
+    Repair {
+        #[command(flatten)]
+        python: PythonArgs,
+    },
+
+    /// Delete the environment and build it again
+    Reset {
+        #[command(flatten)]
+        python: PythonArgs,
+    },
+}
+
+/// How the Python environment is built
+#[derive(clap::Args)]
+struct PythonArgs {
+    /// Python interpreter to create the environment with
+    #[arg(long, env = "ABEL_PYTHON")]
+    interpreter: Option<String>,
+
+    /// Install packages from the wheels in this directory instead of the network
+    #[arg(long, env = "ABEL_WHEEL_DIR")]
+    wheel_dir: Option<PathBuf>,
+}
+
+/// Port generated scripts are written for when no arm is connected for a dry run
+const DRY_RUN_PORT: &str = "/dev/ttyUSB0";
+
+/// Limits for running Python scripts
+#[derive(clap::Args)]
+struct SandboxArgs {
+    /// Stop scripts that run longer than this many seconds
+    #[arg(long, default_value_t = 120)]
+    timeout: u64,
+
+    /// Run scripts without namespaces, Landlock or resource limits (the timeout still applies)
+    #[arg(long)]
+    no_sandbox: bool,
+}
+
+impl SandboxArgs {
+    fn config(&self, serial_port: &str, baud: u32) -> sandbox::SandboxConfig {
+        sandbox::SandboxConfig {
+            timeout: std::time::Duration::from_secs(self.timeout),
+            serial_port: serial_port.to_string(),
+            baud,
+            isolate: cfg!(target_os = "linux") && !self.no_sandbox,
+        }
+    }
 }
 
 #[tokio::main]
@@ -87,45 +337,356 @@ async fn main() -> Result<()> {
     dotenv::dotenv().ok();
 
     let cli = Cli::parse();
+    let serial = SerialConfig::new(cli.port, cli.baud);
 
     match cli.command {
-        Commands::Serve { port } => {
-            server::run_server(port).await?;
+        Commands::Serve { http_port } => {
+            server::run_server(http_port, serial).await?;
+        }
+        Commands::Session { output_dir, tts, python, dry_run, sandbox } => {
+            run_session(output_dir, tts, python, dry_run, &sandbox, &serial).await?;
+        }
+        Commands::Once { save, tts, python, dry_run, sandbox } => {
+            run_once(save, tts, python, dry_run, &sandbox, &serial).await?;
+        }
+        Commands::Run { script, dry_run: true, .. } if script.extension().is_some_and(|ext| ext == "json") => {
+            let plan = plan::ActionPlan::load(&script)?;
+            let profile = profile::CalibrationProfile::load()?;
+            let report = dry_run_plan(&plan, &profile)?;
+            if !report.violations.is_empty() {
+                anyhow::bail!("Dry run found {} safety violations", report.violations.len());
+            }
         }
-        Commands::Session { output_dir, tts } => {
-            run_session(output_dir, tts).await?;
+        Commands::Run { script, .. } if script.extension().is_some_and(|ext| ext == "json") => {
+            let plan = plan::ActionPlan::load(&script)?;
+            let mut driver = ArmDriver::open(&serial)?;
+            let stop = estop::StopSignal::default();
+            estop::stop_on_ctrl_c(&stop);
+            run_plan(&plan, &mut driver, &stop, false)?;
+            println!("{}", "✓ Execution complete".green());
+        }
+        Commands::Run { script, dry_run: true, sandbox } => {
+            let profile = profile::CalibrationProfile::load()?;
+            let serial_port = script_port(&serial, true)?;
+            preflight::analyze(&std::fs::read_to_string(&script)?, &profile, &serial_port).print();
+            println!();
+            let envelope = safety::SafetyEnvelope::new(&profile);
+            let sandbox = sandbox.config(&serial_port, serial.baud);
+            let stop = estop::StopSignal::default();
+            estop::stop_on_ctrl_c(&stop);
+            let report = dry_run_script(&script, &envelope, &stop, &sandbox).await?;
+            if !report.violations.is_empty() {
+                anyhow::bail!("Dry run found {} safety violations", report.violations.len());
+            }
         }
-        Commands::Once { save, tts } => {
-            run_once(save, tts).await?;
+        Commands::Run { script, dry_run: false, sandbox } => {
+            let profile = profile::CalibrationProfile::load()?;
+            let serial_port = serial.resolve_port()?;
+            if !confirm_script(&std::fs::read_to_string(&script)?, &profile, &serial_port, false)? {
+                return Ok(());
+            }
+            let envelope = safety::SafetyEnvelope::new(&profile);
+            let sandbox = sandbox.config(&serial_port, serial.baud);
+            let stop = estop::StopSignal::default();
+            estop::stop_on_ctrl_c(&stop);
+            let report = execute_script(&script, &envelope, &stop, &sandbox).await?;
+            println!("{}", format!("✓ Execution complete in {:.1}s", report.duration.as_secs_f32()).green());
         }
-        Commands::Run { script } => {
-            executor::run_script(&script).await?;
+        // clap rejects --servo together with --wizard
+        Commands::Calibrate { wizard: true, .. } => {
+            wizard::run_wizard(&serial)?;
         }
-        Commands::Calibrate { servo } => {
-            run_calibration(servo).await?;
+        Commands::Calibrate { servo, wizard: false } => {
+            run_calibration(servo, &serial).await?;
         }
         Commands::Smooth { servo, from, to, duration } => {
-            run_smooth_test(servo, from, to, duration).await?;
+            run_smooth_test(servo, from, to, duration, &serial).await?;
+        }
+        Commands::Trajectory { from, to, duration, csv, rate } => {
+            show_trajectory(from, to, duration, csv, rate)?;
+        }
+        Commands::Pose { name } => {
+            run_pose(name.as_deref(), &serial)?;
+        }
+        Commands::Home => {
+            let mut driver = ArmDriver::open(&serial)?;
+            let duration = driver.home()?;
+            println!("{} Returning home over {}ms", "➜".cyan(), duration.as_millis());
+            std::thread::sleep(duration);
+        }
+        Commands::Status { watch } => {
+            show_status(watch, &serial)?;
+        }
+        Commands::MoveTo { x, y, z, dry_run } => {
+            run_move_to(kinematics::Point { x, y, z }, dry_run, &serial)?;
+        }
+        Commands::Fk { base, shoulder, elbow } => {
+            let geometry = profile::CalibrationProfile::load()?.geometry;
+            let point = kinematics::forward(&geometry, kinematics::JointAngles { base, shoulder, elbow });
+            println!(
+                "{}: x={:.1}mm y={:.1}mm z={:.1}mm",
+                "Gripper tip".bright_white(),
+                point.x,
+                point.y,
+                point.z
+            );
+        }
+        Commands::Ports => {
+            list_ports()?;
+        }
+        Commands::Sim { link } => {
+            sim::run_simulator(link.as_deref())?;
+        }
+        Commands::Teach { name } => {
+            teach::run_teach(&name, &serial)?;
+        }
+        Commands::Seq { action } => {
+            run_seq(action, &serial)?;
+        }
+        Commands::Env { action } => {
+            manage_env(action)?;
         }
     }
 
     Ok(())
 }
 
-async fn run_session(output_dir: PathBuf, tts_enabled: bool) -> Result<()> {
+fn run_seq(action: SeqAction, serial: &SerialConfig) -> Result<()> {
+    let library = sequence::Library::load()?;
+
+    match action {
+        SeqAction::List => {
+            println!("{}", "Sequences:".bright_white().underline());
+            for sequence in library.iter() {
+                println!(
+                    "  {:<12} {:>3} steps  {}",
+                    sequence.name.bright_white(),
+                    sequence.steps.len(),
+                    sequence.description.as_deref().unwrap_or_default()
+                );
+                if let sequence::Source::File(path) = &sequence.source {
+                    println!("  {:<12} {}", "", path.display().to_string().dimmed());
+                }
+            }
+            println!();
+            println!(
+                "{}",
+                format!("Add your own as .yaml or .toml files in {}", sequence::Library::dir()?.display()).dimmed()
+            );
+        }
+        SeqAction::Show { name } => {
+            let sequence = library.get(&name)?;
+            println!("{} ({})", sequence.name.bright_white().bold(), sequence.source.to_string().dimmed());
+            if let Some(description) = &sequence.description {
+                println!("{}", description);
+            }
+            println!();
+            for (i, step) in sequence.steps.iter().enumerate() {
+                println!("  {:>2}. {}", i + 1, step);
+            }
+            println!();
+
+            // How long it runs from the reset pose, once nested sequences are expanded
+            let profile = profile::CalibrationProfile::load()?;
+            let start = [driver::HOME_ANGLE; 4];
+            let steps = library.expand(&sequence.name)?;
+            match plan::record(&steps, &profile, start) {
+                Ok(recording) => println!(
+                    "{}: about {:.1}s, {} moves",
+                    "Duration".bright_white(),
+                    recording.duration_ms / 1000.0,
+                    recording.commands.len()
+                ),
+                Err(e) => println!("{} Can't resolve against the calibration profile: {:#}", "⚠".yellow(), e),
+            }
+        }
+        SeqAction::Run { name, dry_run } => {
+            let sequence = library.get(&name)?;
+            let plan = plan::ActionPlan {
+                summary: sequence.description.clone(),
+                steps: vec![plan::Step::Sequence {
+                    name: sequence.name.clone(),
+                }],
+            };
+
+            if dry_run {
+                let report = dry_run_plan(&plan, &profile::CalibrationProfile::load()?)?;
+                if !report.violations.is_empty() {
+                    anyhow::bail!("Dry run found {} safety violations", report.violations.len());
+                }
+                return Ok(());
+            }
+
+            let mut driver = ArmDriver::open(serial)?;
+            let stop = estop::StopSignal::default();
+            estop::stop_on_ctrl_c(&stop);
+            run_plan(&plan, &mut driver, &stop, false)?;
+            println!("{}", "✓ Sequence complete".green());
+        }
+        SeqAction::Edit { name } => {
+            use dialoguer::Confirm;
+
+            // Files the library skipped because they don't parse can still be opened to fix them
+            let (sequence, path) = match library.get(&name) {
+                Ok(sequence) => (Some(sequence), sequence::Library::path_for(sequence)?),
+                Err(e) => match sequence::Library::find_file(&name)? {
+                    Some(path) => (None, path),
+                    None => return Err(e),
+                },
+            };
+            let file_name = path.file_name().context("Sequence path has no file name")?;
+
+            // Edit a draft with the same file name, so a broken edit never reaches the library
+            let drafts = std::env::temp_dir().join(format!("abel-voice-edit-{}", std::process::id()));
+            std::fs::create_dir_all(&drafts)?;
+            let draft = drafts.join(file_name);
+            match sequence {
+                Some(sequence) if matches!(sequence.source, sequence::Source::Builtin) => sequence.save(&draft)?,
+                _ => {
+                    std::fs::copy(&path, &draft).with_context(|| format!("Failed to copy {}", path.display()))?;
+                }
+            }
+
+            let editor = std::env::var("VISUAL")
+                .or_else(|_| std::env::var("EDITOR"))
+                .unwrap_or_else(|_| "vi".to_string());
+            let edited = loop {
+                let status = std::process::Command::new(&editor)
+                    .arg(&draft)
+                    .status()
+                    .with_context(|| format!("Failed to run {}; set EDITOR to your editor", editor))?;
+                if !status.success() {
+                    std::fs::remove_dir_all(&drafts).ok();
+                    anyhow::bail!("{} exited with {}", editor, status);
+                }
+
+                match sequence::Sequence::load(&draft).and_then(|edited| check_sequence(&edited).map(|_| edited)) {
+                    Ok(edited) => break edited,
+                    Err(e) => {
+                        println!("{} {:#}", "✗".red(), e);
+                        // Without a terminal to ask on, give up and keep the original
+                        let again = Confirm::new().with_prompt("Edit again?").default(true).interact().unwrap_or(false);
+                        if !again {
+                            std::fs::remove_dir_all(&drafts).ok();
+                            anyhow::bail!("{} left unchanged", path.display());
+                        }
+                    }
+                }
+            };
+
+            if let Some(dir) = path.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
+            std::fs::copy(&draft, &path).with_context(|| format!("Failed to write {}", path.display()))?;
+            std::fs::remove_dir_all(&drafts).ok();
+            println!("{} {} saved to {}", "✓".green(), edited.name, path.display());
+        }
+        SeqAction::Scale { name, factor, save_as } => {
+            if !(factor.is_finite() && factor > 0.0) {
+                anyhow::bail!("The factor must be a positive number");
+            }
+
+            let mut scaled = library.get(&name)?.clone();
+            if let Some(new_name) = save_as {
+                scaled.name = sequence::normalize_name(&new_name)?;
+                scaled.source = sequence::Source::Builtin;
+            }
+            let unscaled = scaled.scale(factor);
+            check_sequence(&scaled)?;
+
+            let path = sequence::Library::path_for(library.get(&scaled.name).unwrap_or(&scaled))?;
+            scaled.save(&path)?;
+            println!("{} {} scaled by {} and saved to {}", "✓".green(), scaled.name, factor, path.display());
+            if unscaled > 0 {
+                println!(
+                    "{} {} steps have no explicit duration and keep their default speed",
+                    "⚠".yellow(),
+                    unscaled
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks an edited sequence against the safety envelope, starting from the reset pose
+fn check_sequence(sequence: &sequence::Sequence) -> Result<()> {
+    let plan = plan::ActionPlan {
+        summary: None,
+        steps: sequence.steps.clone(),
+    };
+    let profile = profile::CalibrationProfile::load()?;
+    plan.validate(&profile, &sequence::Library::load()?, [driver::HOME_ANGLE; 4])
+        .with_context(|| format!("{} does not pass validation", sequence.name))?;
+    Ok(())
+}
+
+fn manage_env(action: EnvAction) -> Result<()> {
+    let (env, status) = match action {
+        EnvAction::Status => {
+            let env = venv::PythonEnv::from_env()?;
+            let status = env.status();
+            (env, status)
+        }
+        EnvAction::Repair { python } => {
+            let env = venv::PythonEnv::new(python.interpreter, python.wheel_dir)?;
+            let status = env.repair()?;
+            println!();
+            (env, status)
+        }
+        EnvAction::Reset { python } => {
+            let env = venv::PythonEnv::new(python.interpreter, python.wheel_dir)?;
+            let status = env.reset()?;
+            println!();
+            (env, status)
+        }
+    };
+
+    status.print(&env);
+    if let Some(problem) = status.problem() {
+        anyhow::bail!("Python environment {}; run `abel-voice env repair`", problem);
+    }
+    Ok(())
+}
+
+async fn run_session(
+    output_dir: PathBuf,
+    tts_enabled: bool,
+    python: bool,
+    dry_run: bool,
+    sandbox: &SandboxArgs,
+    serial: &SerialConfig,
+) -> Result<()> {
     println!("{}", "🎤 Starting Abel Voice Control Session".bright_cyan().bold());
-    println!("{}", "Press Ctrl+C to exit".dimmed());
+    println!("{}", "Press Ctrl+C to stop the arm while a command runs, or to exit".dimmed());
     println!();
 
     std::fs::create_dir_all(&output_dir)?;
 
-    let whisper_client = whisper::WhisperClient::new()?;
-    let gemini_client = gemini::GeminiClient::new()?;
+    let serial_port = script_port(serial, dry_run)?;
+    println!("{}: {}", "Serial port".bright_white(), serial_port.dimmed());
+    let sandbox = sandbox.config(&serial_port, serial.baud);
+
+    let speech = stt::from_env()?;
+    println!("{}: {}", "Speech-to-text".bright_white(), speech.name().dimmed());
+    let interpreter = interpreter::Interpreter::new()?;
+    println!("{}: {}", "Interpreter".bright_white(), interpreter.backend_name().dimmed());
+    let profile = profile::CalibrationProfile::load()?;
+    let envelope = safety::SafetyEnvelope::new(&profile);
+    let stop = estop::StopSignal::default();
+    estop::stop_on_ctrl_c(&stop);
     let deepgram_client = if tts_enabled {
         Some(deepgram::DeepgramClient::new()?)
     } else {
         None
     };
+    // Scripts open the serial port themselves, so only hold it for plans
+    let mut driver = if python || dry_run {
+        None
+    } else {
+        Some(ArmDriver::open(serial)?)
+    };
 
     let mut session_count = 0;
 
@@ -139,9 +700,8 @@ async fn run_session(output_dir: PathBuf, tts_enabled: bool) -> Result<()> {
         let audio_data = audio::record_audio(5).await?;
         println!("{}", "✓ Recording complete".green());
 
-        // Transcribe with Whisper
         print!("{}", "🔤 Transcribing... ".cyan());
-        let transcript = whisper_client.transcribe(&audio_data).await?;
+        let transcript = speech.transcribe(&audio_data).await?;
         println!("{}", "✓".green());
         println!("{}: \"{}\"", "You said".bright_white(), transcript.bright_yellow());
 
@@ -150,9 +710,26 @@ async fn run_session(output_dir: PathBuf, tts_enabled: bool) -> Result<()> {
             continue;
         }
 
-        // Generate Python script with Gemini
+        if !python {
+            let plan_path = output_dir.join(format!("cmd_{:03}.json", session_count));
+            let result = plan_command(
+                &interpreter,
+                &transcript,
+                Some(&plan_path),
+                driver.as_mut(),
+                &profile,
+                &stop,
+            )
+            .await;
+            report_plan_result(result, deepgram_client.as_ref()).await?;
+            continue;
+        }
+
+        // Generate the Python script
         print!("{}", "🤖 Generating robot control script... ".cyan());
-        let script = gemini_client.generate_robot_script(&transcript).await?;
+        let script = interpreter
+            .generate_robot_script(&transcript, &serial_port, serial.baud)
+            .await?;
         println!("{}", "✓".green());
 
         // Save script
@@ -170,21 +747,23 @@ async fn run_session(output_dir: PathBuf, tts_enabled: bool) -> Result<()> {
         }
         println!();
 
+        if dry_run {
+            preflight::analyze(&script, &profile, &serial_port).print();
+            println!();
+            if let Err(e) = dry_run_script(&script_path, &envelope, &stop, &sandbox).await {
+                println!("{}: {:#}", "✗ Dry run failed".red(), e);
+            }
+            continue;
+        }
+
         // Confirm execution
-        use dialoguer::Confirm;
-        if Confirm::new()
-            .with_prompt("Execute this script?")
-            .default(true)
-            .interact()?
-        {
-            println!("{}", "🚀 Executing...".cyan());
-
-            match executor::run_script(&script_path).await {
-                Ok(output) => {
-                    println!("{}", "✓ Execution complete".green());
-                    if !output.is_empty() {
-                        println!("{}", output.dimmed());
-                    }
+        if confirm_script(&script, &profile, &serial_port, true)? {
+            match execute_script(&script_path, &envelope, &stop, &sandbox).await {
+                Ok(report) => {
+                    println!(
+                        "{}",
+                        format!("✓ Execution complete in {:.1}s", report.duration.as_secs_f32()).green()
+                    );
 
                     // Text-to-speech response
                     if let Some(ref tts) = deepgram_client {
@@ -206,12 +785,27 @@ async fn run_session(output_dir: PathBuf, tts_enabled: bool) -> Result<()> {
     }
 }
 
-async fn run_once(save_path: Option<PathBuf>, tts_enabled: bool) -> Result<()> {
+async fn run_once(
+    save_path: Option<PathBuf>,
+    tts_enabled: bool,
+    python: bool,
+    dry_run: bool,
+    sandbox: &SandboxArgs,
+    serial: &SerialConfig,
+) -> Result<()> {
     println!("{}", "🎤 Voice Command".bright_cyan().bold());
+
+    let serial_port = script_port(serial, dry_run)?;
+    let sandbox = sandbox.config(&serial_port, serial.baud);
+
     println!("{}", "🎙️  Listening... (speak now)".yellow());
 
-    let whisper_client = whisper::WhisperClient::new()?;
-    let gemini_client = gemini::GeminiClient::new()?;
+    let speech = stt::from_env()?;
+    let interpreter = interpreter::Interpreter::new()?;
+    let profile = profile::CalibrationProfile::load()?;
+    let envelope = safety::SafetyEnvelope::new(&profile);
+    let stop = estop::StopSignal::default();
+    estop::stop_on_ctrl_c(&stop);
     let deepgram_client = if tts_enabled {
         Some(deepgram::DeepgramClient::new()?)
     } else {
@@ -224,13 +818,29 @@ async fn run_once(save_path: Option<PathBuf>, tts_enabled: bool) -> Result<()> {
 
     // Transcribe
     print!("{}", "🔤 Transcribing... ".cyan());
-    let transcript = whisper_client.transcribe(&audio_data).await?;
+    let transcript = speech.transcribe(&audio_data).await?;
     println!("{}", "✓".green());
     println!("{}: \"{}\"", "You said".bright_white(), transcript.bright_yellow());
 
+    if !python {
+        let mut driver = if dry_run { None } else { Some(ArmDriver::open(serial)?) };
+        let result = plan_command(
+            &interpreter,
+            &transcript,
+            save_path.as_deref(),
+            driver.as_mut(),
+            &profile,
+            &stop,
+        )
+        .await;
+        return report_plan_result(result, deepgram_client.as_ref()).await;
+    }
+
     // Generate script
     print!("{}", "🤖 Generating robot control script... ".cyan());
-    let script = gemini_client.generate_robot_script(&transcript).await?;
+    let script = interpreter
+        .generate_robot_script(&transcript, &serial_port, serial.baud)
+        .await?;
     println!("{}", "✓".green());
 
     // Save if requested
@@ -248,13 +858,24 @@ async fn run_once(save_path: Option<PathBuf>, tts_enabled: bool) -> Result<()> {
     let temp_script = std::env::temp_dir().join("abel_temp.py");
     std::fs::write(&temp_script, &script)?;
 
-    println!("{}", "🚀 Executing...".cyan());
-    match executor::run_script(&temp_script).await {
-        Ok(output) => {
-            println!("{}", "✓ Execution complete".green());
-            if !output.is_empty() {
-                println!("{}", output);
-            }
+    if dry_run {
+        preflight::analyze(&script, &profile, &serial_port).print();
+        println!();
+        dry_run_script(&temp_script, &envelope, &stop, &sandbox).await?;
+        return Ok(());
+    }
+
+    if !confirm_script(&script, &profile, &serial_port, false)? {
+        println!("{}", "⊗ Skipped execution".yellow());
+        return Ok(());
+    }
+
+    match execute_script(&temp_script, &envelope, &stop, &sandbox).await {
+        Ok(report) => {
+            println!(
+                "{}",
+                format!("✓ Execution complete in {:.1}s", report.duration.as_secs_f32()).green()
+            );
 
             if let Some(ref tts) = deepgram_client {
                 tts.speak("Command executed successfully").await?;
@@ -272,123 +893,610 @@ async fn run_once(save_path: Option<PathBuf>, tts_enabled: bool) -> Result<()> {
     Ok(())
 }
 
-async fn run_calibration(servo_id: Option<u8>) -> Result<()> {
-    use dialoguer::{Input, Select};
-    use std::io::Write;
+/// Asks the interpreter for an action plan, saves it if requested and runs it after confirmation
+///
+/// Without a driver the plan is only dry-run. Returns whether the plan was executed.
+async fn plan_command(
+    interpreter: &interpreter::Interpreter,
+    transcript: &str,
+    save_path: Option<&std::path::Path>,
+    driver: Option<&mut ArmDriver>,
+    profile: &profile::CalibrationProfile,
+    stop: &estop::StopSignal,
+) -> Result<bool> {
+    print!("{}", "🤖 Planning robot actions... ".cyan());
+    let positions = match &driver {
+        Some(driver) => driver.positions(),
+        None => ServoId::ALL.map(|servo| profile.servo(servo).home),
+    };
+    let plan = interpreter.generate_action_plan(transcript, positions).await?;
+    println!("{}", "✓".green());
+
+    if let Some(path) = save_path {
+        std::fs::write(path, serde_json::to_string_pretty(&plan)?)?;
+        println!("{}: {}", "💾 Saved".green(), path.display().to_string().dimmed());
+    }
+
+    match driver {
+        Some(driver) => run_plan(&plan, driver, stop, true),
+        None => dry_run_plan(&plan, profile).map(|_| false),
+    }
+}
+
+fn print_plan(plan: &plan::ActionPlan) {
+    println!("\n{}", "Action Plan:".bright_white().underline());
+    if let Some(summary) = &plan.summary {
+        println!("  {}", summary.bright_white());
+    }
+    for (i, step) in plan.steps.iter().enumerate() {
+        println!("  {:>2}. {}", i + 1, step);
+    }
+    println!();
+}
+
+/// Shows a plan, checks it from the arm's current pose and executes it through the driver
+///
+/// Returns false when the user declines to run it.
+fn run_plan(plan: &plan::ActionPlan, driver: &mut ArmDriver, stop: &estop::StopSignal, confirm: bool) -> Result<bool> {
+    print_plan(plan);
+
+    let sequences = sequence::Library::load()?;
+    let steps = plan.validate(driver.profile(), &sequences, driver.positions())?;
+    println!("{} {} steps within the safety envelope", "✓".green(), steps.len());
+
+    use dialoguer::Confirm;
+    if confirm
+        && !Confirm::new()
+            .with_prompt("Execute this plan?")
+            .default(true)
+            .interact()?
+    {
+        return Ok(false);
+    }
+
+    println!("{}", "🚀 Executing...".cyan());
+    let listener = stop.listen();
+    let elapsed = tokio::task::block_in_place(|| {
+        plan::execute(&steps, driver, &listener, |i, step| {
+            println!("  {} [{}/{}] {}", "➜".cyan(), i + 1, steps.len(), step);
+        })
+    })?;
+    println!("{}", format!("Finished in {:.1}s", elapsed.as_secs_f32()).dimmed());
+
+    Ok(true)
+}
+
+/// Shows a plan and the timeline it would run with, starting from the arm's reset pose
+fn dry_run_plan(plan: &plan::ActionPlan, profile: &profile::CalibrationProfile) -> Result<dryrun::DryRunReport> {
+    print_plan(plan);
+
+    let start = [driver::HOME_ANGLE; 4];
+    let steps = plan.validate(profile, &sequence::Library::load()?, start)?;
+    let recording = plan::record(&steps, profile, start)?;
+    let report = dryrun::analyze(&recording, &safety::SafetyEnvelope::new(profile), start);
+    report.print();
+    Ok(report)
+}
+
+/// The port generated scripts should open; a dry run doesn't need the arm connected
+fn script_port(serial: &SerialConfig, dry_run: bool) -> Result<String> {
+    match serial.resolve_port() {
+        Err(_) if dry_run => Ok(DRY_RUN_PORT.to_string()),
+        result => result,
+    }
+}
+
+/// A spinner that script output is printed above as it arrives
+fn output_spinner(message: &'static str) -> Result<indicatif::ProgressBar> {
+    let spinner = indicatif::ProgressBar::new_spinner();
+    spinner.set_style(indicatif::ProgressStyle::with_template("{spinner:.cyan} {msg} {elapsed:.dim}")?);
+    spinner.set_message(message);
+    spinner.enable_steady_tick(std::time::Duration::from_millis(100));
+    Ok(spinner)
+}
+
+fn print_output(spinner: &indicatif::ProgressBar, line: &executor::OutputLine) {
+    // A hidden spinner (output isn't a terminal) drops what is printed through it
+    if spinner.is_hidden() {
+        match line {
+            executor::OutputLine::Stdout(text) => println!("  {}", text),
+            executor::OutputLine::Stderr(text) => eprintln!("  {}", text),
+        }
+        return;
+    }
+    match line {
+        executor::OutputLine::Stdout(text) => spinner.println(format!("  {}", text)),
+        executor::OutputLine::Stderr(text) => spinner.println(format!("  {}", text.red())),
+    }
+}
+
+/// Runs a script with its output streamed above a spinner
+///
+/// A non-zero exit is an error; its stderr has already been shown.
+async fn execute_script(
+    path: &std::path::Path,
+    envelope: &safety::SafetyEnvelope,
+    stop: &estop::StopSignal,
+    sandbox: &sandbox::SandboxConfig,
+) -> Result<executor::ExecutionReport> {
+    let spinner = output_spinner("🚀 Executing...")?;
+    let result = executor::run_script(path, envelope, stop, sandbox, |line| print_output(&spinner, line)).await;
+    spinner.finish_and_clear();
+
+    let report = result?;
+    match report.exit_code {
+        Some(0) => Ok(report),
+        Some(code) => anyhow::bail!("Script exited with code {}", code),
+        None => anyhow::bail!("Script was killed"),
+    }
+}
+
+/// Runs a script against the recorder and prints the timeline of what it sent
+///
+/// The timeline is shown even when the script fails, up to the point it got to.
+async fn dry_run_script(
+    path: &std::path::Path,
+    envelope: &safety::SafetyEnvelope,
+    stop: &estop::StopSignal,
+    sandbox: &sandbox::SandboxConfig,
+) -> Result<dryrun::DryRunReport> {
+    let spinner = output_spinner("🧪 Dry run...")?;
+    let result =
+        executor::dry_run_script(path, envelope, stop, sandbox, |line| print_output(&spinner, line)).await;
+    spinner.finish_and_clear();
+
+    let (execution, recording) = result?;
+    match execution.exit_code {
+        Some(0) => {}
+        Some(code) => println!("{} Script exited with code {}", "⚠".yellow(), code),
+        None => println!("{} Script was killed", "⚠".yellow()),
+    }
+    println!();
+
+    let report = dryrun::analyze(&recording, envelope, [driver::HOME_ANGLE; 4]);
+    report.print();
+    Ok(report)
+}
+
+/// Shows the pre-flight analysis of a script and asks whether to run it
+///
+/// Without `always_ask` the question is only asked when the analysis found problems.
+fn confirm_script(
+    script: &str,
+    profile: &profile::CalibrationProfile,
+    serial_port: &str,
+    always_ask: bool,
+) -> Result<bool> {
+    let report = preflight::analyze(script, profile, serial_port);
+    report.print();
+    println!();
+
+    let prompt = match report.errors() {
+        0 if !always_ask => return Ok(true),
+        0 => "Execute this script?".to_string(),
+        n => format!("Execute this script despite {} problems?", n),
+    };
+
+    use dialoguer::Confirm;
+    Ok(Confirm::new()
+        .with_prompt(prompt)
+        .default(report.errors() == 0)
+        .interact()?)
+}
+
+async fn report_plan_result(result: Result<bool>, tts: Option<&deepgram::DeepgramClient>) -> Result<()> {
+    match result {
+        Ok(true) => {
+            println!("{}", "✓ Execution complete".green());
+            if let Some(tts) = tts {
+                tts.speak("Command executed successfully").await?;
+            }
+        }
+        Ok(false) => println!("{}", "⊗ Skipped execution".yellow()),
+        Err(e) => {
+            println!("{}: {:#}", "✗ Execution failed".red(), e);
+            if let Some(tts) = tts {
+                tts.speak("Execution failed").await?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn run_calibration(servo_id: Option<u8>, serial: &SerialConfig) -> Result<()> {
+    use dialoguer::{Confirm, Input, Select};
+    use profile::{CalibrationProfile, Pose};
     use std::time::Duration;
 
     println!("{}", "🎯 Servo Calibration Tool".bright_cyan().bold());
     println!();
 
-    let servo = match servo_id {
-        Some(id) if id <= 3 => id,
-        Some(_) => anyhow::bail!("Servo ID must be 0-3"),
+    let mut servo = match servo_id {
+        Some(id) => ServoId::try_from(id)?,
         None => {
-            let servos = vec!["Base (0)", "Shoulder (1)", "Elbow (2)", "Gripper (3)"];
+            let servos: Vec<String> = ServoId::ALL.iter().map(|s| s.to_string()).collect();
             let selection = Select::new()
                 .with_prompt("Select servo to calibrate")
                 .items(&servos)
                 .interact()?;
-            selection as u8
+            ServoId::ALL[selection]
         }
     };
 
-    let servo_names = ["Base", "Shoulder", "Elbow", "Gripper"];
-    println!("{}: {}", "Calibrating".green(), servo_names[servo as usize].bright_white());
-    println!();
-
-    let port = serialport::new("/dev/cu.usbserial-140", 115200)
-        .timeout(Duration::from_secs(2))
-        .open()?;
+    let mut profile = CalibrationProfile::load()?;
+    let mut changed = false;
 
-    let mut port = port;
-    std::thread::sleep(Duration::from_millis(2000));
+    // Limits are what we're here to find, so don't enforce the old ones
+    let mut driver = ArmDriver::open_with_profile(serial, profile.without_limits())?;
 
+    println!("{}: {}", "Calibrating".green(), servo.name().bright_white());
     println!("{}", "Testing range 0-180 degrees...".dimmed());
     println!();
+    println!("{}", "Commands:".bright_white());
+    println!("  {}  move the servo", "<angle>".cyan());
+    println!("  {}  record the current angle as this servo's limit/home", "min | max | home".cyan());
+    println!("  {}  set a trim offset (-20..20)", "trim <n>".cyan());
+    println!("  {}  record gripper points", "open | touch | closed".cyan());
+    println!("  {}  save base/shoulder/elbow as a named pose (e.g. pick, place)", "pose <name>".cyan());
+    println!("  {}  switch servo", "servo <0-3>".cyan());
+    println!("  {}  show the profile, or finish", "show | q".cyan());
+    println!();
 
     loop {
-        let angle: String = Input::new()
-            .with_prompt("Enter angle (0-180) or 'q' to quit")
+        let input: String = Input::new()
+            .with_prompt(format!("{} (0-180, command or 'q')", servo.name()))
             .interact_text()?;
+        let input = input.trim().to_lowercase();
+        let mut words = input.split_whitespace();
+        let current = driver.position(servo);
+
+        match (words.next(), words.next()) {
+            (Some("q"), _) => break,
+            (Some("min"), None) => {
+                profile.servo_mut(servo).min = current;
+                println!("{} {} min = {}°", "✓".green(), servo.name(), current);
+            }
+            (Some("max"), None) => {
+                profile.servo_mut(servo).max = current;
+                println!("{} {} max = {}°", "✓".green(), servo.name(), current);
+            }
+            (Some("home"), None) => {
+                profile.servo_mut(servo).home = current;
+                println!("{} {} home = {}°", "✓".green(), servo.name(), current);
+            }
+            (Some("trim"), Some(value)) => match value.parse::<i8>() {
+                Ok(trim) if (-20..=20).contains(&trim) => {
+                    profile.servo_mut(servo).trim = trim;
+                    let mut working = driver.profile().clone();
+                    working.servo_mut(servo).trim = trim;
+                    driver.set_profile(working);
+                    println!("{} {} trim = {:+}°", "✓".green(), servo.name(), trim);
+                }
+                _ => {
+                    println!("{}", "Trim must be between -20 and 20".red());
+                    continue;
+                }
+            },
+            (Some(point @ ("open" | "touch" | "closed")), None) => {
+                if servo != ServoId::Gripper {
+                    println!("{}", "Switch to the gripper first: servo 3".red());
+                    continue;
+                }
+                match point {
+                    "open" => profile.gripper.open = current,
+                    "touch" => profile.gripper.touch = current,
+                    _ => profile.gripper.closed = current,
+                }
+                println!("{} Gripper {} = {}°", "✓".green(), point, current);
+            }
+            (Some("pose"), Some(name)) => {
+                let pose = Pose {
+                    base: driver.position(ServoId::Base),
+                    shoulder: driver.position(ServoId::Shoulder),
+                    elbow: driver.position(ServoId::Elbow),
+                    gripper: None,
+                };
+                println!(
+                    "{} Pose '{}' = Base {}° Shoulder {}° Elbow {}°",
+                    "✓".green(),
+                    name,
+                    pose.base,
+                    pose.shoulder,
+                    pose.elbow
+                );
+                profile.poses.insert(name.to_string(), pose);
+            }
+            (Some("servo"), Some(id)) => {
+                match id.parse::<u8>().map_err(anyhow::Error::from).and_then(ServoId::try_from) {
+                    Ok(s) => {
+                        servo = s;
+                        println!("{}: {}", "Calibrating".green(), servo.name().bright_white());
+                    }
+                    Err(_) => println!("{}", "Servo must be 0-3".red()),
+                }
+                continue;
+            }
+            (Some("show"), None) => {
+                println!("{}", toml::to_string_pretty(&profile)?.dimmed());
+                continue;
+            }
+            (Some(angle), None) => {
+                let angle: u8 = match angle.parse() {
+                    Ok(a) if a <= driver::MAX_ANGLE => a,
+                    _ => {
+                        println!("{}", "Invalid angle. Must be 0-180.".red());
+                        continue;
+                    }
+                };
 
-        if angle.trim().to_lowercase() == "q" {
-            break;
-        }
+                let duration = match driver.move_servo(servo, angle, Duration::from_millis(800)) {
+                    Ok(d) => d,
+                    Err(e) => {
+                        println!("{}", e.to_string().red());
+                        continue;
+                    }
+                };
 
-        let angle: u8 = match angle.trim().parse() {
-            Ok(a) if a <= 180 => a,
+                println!("{} Servo {} → {}°", "➜".cyan(), servo as u8, angle);
+                std::thread::sleep(duration);
+                continue;
+            }
             _ => {
-                println!("{}", "Invalid angle. Must be 0-180.".red());
+                println!("{}", "Unknown command".red());
                 continue;
             }
-        };
-
-        let command = format!("#{servo}M{angle}T800\n");
-        port.write_all(command.as_bytes())?;
-        port.flush()?;
+        }
 
-        println!("{} Servo {} → {}°", "➜".cyan(), servo, angle);
-        std::thread::sleep(Duration::from_millis(900));
+        changed = true;
     }
 
     println!();
+    if changed
+        && Confirm::new()
+            .with_prompt("Save calibration profile?")
+            .default(true)
+            .interact()?
+    {
+        let path = profile.save()?;
+        println!("{}: {}", "💾 Saved".green(), path.display());
+    }
     println!("{}", "✓ Calibration complete".green());
 
     Ok(())
 }
 
-async fn run_smooth_test(servo: u8, from: u8, to: u8, duration: u16) -> Result<()> {
-    use std::io::Write;
+async fn run_smooth_test(
+    servo: u8,
+    from: u8,
+    to: u8,
+    duration: u16,
+    serial: &SerialConfig,
+) -> Result<()> {
     use std::time::Duration;
 
-    if servo > 3 {
-        anyhow::bail!("Servo ID must be 0-3");
-    }
+    let servo = ServoId::try_from(servo)?;
+    let duration = Duration::from_millis(duration as u64);
 
     println!("{}", "⚡ Smooth Motion Test".bright_cyan().bold());
     println!();
 
-    let servo_names = ["Base", "Shoulder", "Elbow", "Gripper"];
-    println!("{}: {}", "Servo".bright_white(), servo_names[servo as usize]);
+    println!("{}: {}", "Servo".bright_white(), servo.name());
     println!("{}: {}° → {}°", "Range".bright_white(), from, to);
-    println!("{}: {}ms", "Duration".bright_white(), duration);
+    println!("{}: {}ms", "Duration".bright_white(), duration.as_millis());
     println!();
 
-    let port = serialport::new("/dev/cu.usbserial-140", 115200)
-        .timeout(Duration::from_secs(2))
-        .open()?;
-
-    let mut port = port;
-    std::thread::sleep(Duration::from_millis(2000));
+    let mut driver = ArmDriver::open(serial)?;
 
     println!("{}", "Running test...".cyan());
 
-    let command = format!("#{servo}M{from}T{duration}\n");
-    port.write_all(command.as_bytes())?;
-    port.flush()?;
-    println!("{} Moving to {}°", "➜".cyan(), from);
-    std::thread::sleep(Duration::from_millis((duration + 200) as u64));
+    let mid = ((from as u16 + to as u16) / 2) as u8;
+    let steps = [
+        (from, "Moving to"),
+        (to, "Moving to"),
+        (mid, "Returning to center"),
+    ];
+
+    for (i, (angle, label)) in steps.into_iter().enumerate() {
+        if i > 0 {
+            std::thread::sleep(Duration::from_millis(500));
+        }
+
+        let actual = driver.move_servo(servo, angle, duration)?;
+        println!("{} {} {}°", "➜".cyan(), label, angle);
+        if actual != duration {
+            println!(
+                "  {}",
+                format!("firmware adjusted duration to {}ms", actual.as_millis()).yellow()
+            );
+        }
+        std::thread::sleep(actual);
+    }
+
+    println!();
+    println!("{}", "✓ Smooth motion test complete".green());
 
-    std::thread::sleep(Duration::from_millis(500));
+    Ok(())
+}
 
-    let command = format!("#{servo}M{to}T{duration}\n");
-    port.write_all(command.as_bytes())?;
-    port.flush()?;
-    println!("{} Moving to {}°", "➜".cyan(), to);
-    std::thread::sleep(Duration::from_millis((duration + 200) as u64));
+fn show_trajectory(from: f32, to: f32, duration: u64, csv: Option<PathBuf>, rate: f32) -> Result<()> {
+    use std::time::Duration;
 
-    std::thread::sleep(Duration::from_millis(500));
+    if !(0.0..=180.0).contains(&from) || !(0.0..=180.0).contains(&to) {
+        anyhow::bail!("Angles must be 0-180");
+    }
+    if rate <= 0.0 {
+        anyhow::bail!("Sample rate must be positive");
+    }
 
-    let mid = (from + to) / 2;
-    let command = format!("#{servo}M{mid}T{duration}\n");
-    port.write_all(command.as_bytes())?;
-    port.flush()?;
-    println!("{} Returning to center {}°", "➜".cyan(), mid);
-    std::thread::sleep(Duration::from_millis((duration + 200) as u64));
+    let profile = trajectory::Profile::plan(from, to, Duration::from_millis(duration));
+    let limits = trajectory::FIRMWARE_CONSTRAINTS;
 
+    println!("{}", "📈 Quintic Trajectory".bright_cyan().bold());
     println!();
-    println!("{}", "✓ Smooth motion test complete".green());
+    println!("{}: {}° → {}°", "Move".bright_white(), from, to);
+    println!("{}: {}ms", "Requested".bright_white(), duration);
+    if profile.is_adjusted() {
+        println!(
+            "{}: {}",
+            "Firmware".bright_white(),
+            format!("{}ms (adjusted to respect v_max/a_max)", profile.duration.as_millis()).yellow()
+        );
+    } else {
+        println!("{}: {}ms", "Firmware".bright_white(), profile.duration.as_millis());
+    }
+    println!();
+
+    let peaks = [
+        ("Peak velocity", profile.peak_velocity(), limits.max_velocity, "deg/s"),
+        ("Peak acceleration", profile.peak_acceleration(), limits.max_acceleration, "deg/s²"),
+        ("Peak jerk", profile.peak_jerk(), limits.max_jerk, "deg/s³"),
+    ];
+    for (label, peak, limit, unit) in peaks {
+        let value = format!("{:.1} {} (limit {:.0})", peak, unit, limit);
+        let value = if peak > limit { value.red() } else { value.green() };
+        println!("  {:<18} {}", label, value);
+    }
+    println!();
+
+    let samples = profile.samples(rate);
+
+    println!("{}", "Position (deg)".bright_white().underline());
+    for line in trajectory::plot(&samples, |s| s.position, 60, 12) {
+        println!("{}", line);
+    }
+    println!();
+    println!("{}", "Velocity (deg/s)".bright_white().underline());
+    for line in trajectory::plot(&samples, |s| s.velocity, 60, 8) {
+        println!("{}", line);
+    }
+
+    for violation in profile.violations(&limits) {
+        println!("{} {}", "⚠️ ".yellow(), violation.yellow());
+    }
+
+    if let Some(path) = csv {
+        trajectory::export_csv(&samples, &path)?;
+        println!();
+        println!("{}: {} ({} samples)", "💾 Exported".green(), path.display(), samples.len());
+    }
+
+    Ok(())
+}
+
+fn run_pose(name: Option<&str>, serial: &SerialConfig) -> Result<()> {
+    let Some(name) = name else {
+        let profile = profile::CalibrationProfile::load()?;
+        println!("{}", "📍 Saved Poses".bright_cyan().bold());
+        for (name, pose) in &profile.poses {
+            println!(
+                "  {:<12} Base {}° Shoulder {}° Elbow {}°",
+                name.bright_white(),
+                pose.base,
+                pose.shoulder,
+                pose.elbow
+            );
+        }
+        return Ok(());
+    };
+
+    let mut driver = ArmDriver::open(serial)?;
+    let duration = driver.move_pose(name)?;
+    println!("{} Moving to '{}' over {}ms", "➜".cyan(), name, duration.as_millis());
+    std::thread::sleep(duration);
+
+    Ok(())
+}
+
+fn show_status(watch: bool, serial: &SerialConfig) -> Result<()> {
+    let term = console::Term::stdout();
+    let mut driver = ArmDriver::open(serial)?;
+
+    println!("{}", "📡 Arm Status".bright_cyan().bold());
+    println!();
+
+    let mut first = true;
+    loop {
+        let state = driver.poll_state()?;
+
+        if !first {
+            term.clear_last_lines(ServoId::ALL.len() + 1)?;
+        }
+        first = false;
+
+        println!(
+            "  {:<10} {:>8} {:>8} {:>10}",
+            "Servo".bright_white(),
+            "Current".bright_white(),
+            "Target".bright_white(),
+            "Velocity".bright_white()
+        );
+        for status in state.servos {
+            let line = format!(
+                "  {:<10} {:>7.1}° {:>7.1}° {:>6.1}°/s",
+                status.servo.name(),
+                status.current,
+                status.target,
+                status.velocity
+            );
+            if status.moving {
+                println!("{} {}", line.bright_yellow(), "moving".yellow());
+            } else {
+                println!("{}", line);
+            }
+        }
+
+        if !watch {
+            return Ok(());
+        }
+        std::thread::sleep(std::time::Duration::from_millis(200));
+    }
+}
+
+fn run_move_to(target: kinematics::Point, dry_run: bool, serial: &SerialConfig) -> Result<()> {
+    let geometry = profile::CalibrationProfile::load()?.geometry;
+    let joints = kinematics::inverse(&geometry, target)?;
+
+    println!("{}", "📐 Inverse Kinematics".bright_cyan().bold());
+    println!(
+        "{}: x={}mm y={}mm z={}mm",
+        "Target".bright_white(),
+        target.x,
+        target.y,
+        target.z
+    );
+    println!(
+        "{}: Base={:.1}° Shoulder={:.1}° Elbow={:.1}°",
+        "Joints".bright_white(),
+        joints.base,
+        joints.shoulder,
+        joints.elbow
+    );
+
+    if dry_run {
+        return Ok(());
+    }
+
+    let mut driver = ArmDriver::open(serial)?;
+    let duration = driver.move_coordinated(&joints.to_moves())?;
+    println!("{} Moving over {}ms", "➜".cyan(), duration.as_millis());
+    std::thread::sleep(duration);
+
+    println!("{}", "✓ Arrived".green());
+    Ok(())
+}
+
+fn list_ports() -> Result<()> {
+    println!("{}", "🔌 Detected Arm Serial Ports".bright_cyan().bold());
+    println!();
+
+    let candidates = serial::find_candidates()?;
+    if candidates.is_empty() {
+        println!("{}", "No ESP32-C3 serial bridge detected".yellow());
+    }
+    for candidate in candidates {
+        println!("  {}  {}", candidate.path.bright_white(), candidate.description.dimmed());
+    }
 
     Ok(())
 }
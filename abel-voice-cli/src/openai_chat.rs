@@ -0,0 +1,112 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::llm::{LlmBackend, Prompt};
+
+#[derive(Serialize)]
+struct ChatRequest<'a> {
+    model: &'a str,
+    messages: Vec<Message<'a>>,
+    temperature: f32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_format: Option<serde_json::Value>,
+}
+
+#[derive(Serialize)]
+struct Message<'a> {
+    role: &'static str,
+    content: &'a str,
+}
+
+#[derive(Deserialize)]
+struct ChatResponse {
+    choices: Vec<Choice>,
+}
+
+#[derive(Deserialize)]
+struct Choice {
+    message: ChoiceMessage,
+}
+
+#[derive(Deserialize)]
+struct ChoiceMessage {
+    content: Option<String>,
+}
+
+/// Any server with OpenAI's chat completions API: OpenAI itself, Ollama, llama.cpp, vLLM...
+pub struct OpenAiChat {
+    /// Up to and including the version, e.g. `http://localhost:11434/v1`
+    base_url: String,
+    model: String,
+    api_key: Option<String>,
+    client: reqwest::Client,
+}
+
+impl OpenAiChat {
+    pub fn new(base_url: &str, model: String, api_key: Option<String>) -> Result<Self> {
+        let base_url = base_url.trim().trim_end_matches('/');
+        if !base_url.starts_with("http://") && !base_url.starts_with("https://") {
+            anyhow::bail!("LLM URL must start with http:// or https:// (got '{}')", base_url);
+        }
+
+        Ok(Self {
+            base_url: base_url.to_string(),
+            model,
+            api_key,
+            client: reqwest::Client::new(),
+        })
+    }
+}
+
+#[async_trait]
+impl LlmBackend for OpenAiChat {
+    fn name(&self) -> String {
+        format!("{} at {}", self.model, self.base_url)
+    }
+
+    async fn generate(&self, prompt: &Prompt<'_>) -> Result<String> {
+        // Plain JSON mode: local servers rarely support full schemas, and the prompt spells out the format
+        let request = ChatRequest {
+            model: &self.model,
+            messages: vec![Message {
+                role: "user",
+                content: &prompt.text,
+            }],
+            temperature: 0.2,
+            response_format: prompt
+                .schema
+                .as_ref()
+                .map(|_| serde_json::json!({ "type": "json_object" })),
+        };
+
+        let mut http = self
+            .client
+            .post(format!("{}/chat/completions", self.base_url))
+            .json(&request);
+        if let Some(api_key) = &self.api_key {
+            http = http.header("Authorization", format!("Bearer {}", api_key));
+        }
+
+        let response = http
+            .send()
+            .await
+            .with_context(|| format!("Failed to send chat request to {}", self.base_url))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            anyhow::bail!("Chat API error ({}): {}", status, error_text);
+        }
+
+        let chat_response: ChatResponse = response.json().await.context("Failed to parse chat response")?;
+
+        chat_response
+            .choices
+            .into_iter()
+            .next()
+            .and_then(|choice| choice.message.content)
+            .filter(|content| !content.trim().is_empty())
+            .context("Empty response from the chat server")
+    }
+}
@@ -0,0 +1,519 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+use crate::driver::{self, ArmDriver, ServoId, MAX_DURATION, MIN_DURATION};
+use crate::dryrun::{RecordedCommand, Recording};
+use crate::estop::StopListener;
+use crate::profile::CalibrationProfile;
+use crate::safety::SafetyEnvelope;
+use crate::sequence::Library;
+
+/// Longest plan accepted after sequences are expanded
+const MAX_STEPS: usize = 200;
+
+/// Longest single wait step
+const MAX_WAIT: Duration = Duration::from_secs(10);
+
+/// Pause after each move so the arm settles before the next step
+const SETTLE_TIME: Duration = Duration::from_millis(150);
+
+/// Most waypoints in one trajectory step
+const MAX_WAYPOINTS: usize = 32;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct JointTarget {
+    pub servo: ServoId,
+    pub angle: u8,
+}
+
+/// One via point of a trajectory: some joints to angles, or to a calibrated pose
+///
+/// Joints that aren't mentioned stay where the previous waypoint left them.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct TrajectoryPoint {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub moves: Vec<JointTarget>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pose: Option<String>,
+    /// Limits `pose` to some servos
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub joints: Option<Vec<ServoId>>,
+    /// Least time to get here from the previous waypoint; longer if the limits need it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum GripperState {
+    Open,
+    Touch,
+    Closed,
+}
+
+/// One instruction of an action plan
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Step {
+    /// Single servo; the duration defaults to one scaled by the distance
+    Move {
+        servo: ServoId,
+        angle: u8,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        duration_ms: Option<u64>,
+    },
+    /// Several servos arriving together; the duration defaults to one scaled by the longest distance
+    CoordinatedMove {
+        moves: Vec<JointTarget>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        duration_ms: Option<u64>,
+    },
+    /// Gripper to a calibrated point
+    Gripper { state: GripperState },
+    Wait { ms: u64 },
+    /// Pose saved in the calibration profile, optionally only some of its joints
+    Pose {
+        name: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        joints: Option<Vec<ServoId>>,
+    },
+    /// Every servo, or only `joints`, to its calibrated home
+    Home {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        joints: Option<Vec<ServoId>>,
+    },
+    /// Smooth path through several waypoints, without stopping at the ones in between
+    Trajectory { waypoints: Vec<TrajectoryPoint> },
+    /// Sequence from the library, expanded into the steps above before running
+    Sequence { name: String },
+}
+
+impl std::fmt::Display for Step {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Step::Move { servo, angle, .. } => write!(f, "Move {} to {}°", servo.name(), angle),
+            Step::CoordinatedMove { moves, .. } => {
+                let moves: Vec<String> = moves
+                    .iter()
+                    .map(|m| format!("{} {}°", m.servo.name(), m.angle))
+                    .collect();
+                write!(f, "Move {}", moves.join(", "))
+            }
+            Step::Gripper { state } => match state {
+                GripperState::Open => write!(f, "Open gripper"),
+                GripperState::Touch => write!(f, "Close gripper to touch"),
+                GripperState::Closed => write!(f, "Close gripper"),
+            },
+            Step::Wait { ms } => write!(f, "Wait {}ms", ms),
+            Step::Pose { name, joints: None } => write!(f, "Go to pose '{}'", name),
+            Step::Pose {
+                name,
+                joints: Some(joints),
+            } => write!(f, "Move {} to pose '{}'", joint_names(joints), name),
+            Step::Home { joints: None } => write!(f, "Go home"),
+            Step::Home { joints: Some(joints) } => write!(f, "Move {} home", joint_names(joints)),
+            Step::Trajectory { waypoints } => {
+                let points: Vec<String> = waypoints
+                    .iter()
+                    .map(|point| match &point.pose {
+                        Some(pose) => format!("pose '{}'", pose),
+                        None => {
+                            let moves: Vec<String> =
+                                point.moves.iter().map(|m| format!("{} {}°", m.servo.name(), m.angle)).collect();
+                            moves.join(" ")
+                        }
+                    })
+                    .collect();
+                write!(f, "Move through {}", points.join(" → "))
+            }
+            Step::Sequence { name } => write!(f, "Run sequence {}", name),
+        }
+    }
+}
+
+fn joint_names(joints: &[ServoId]) -> String {
+    let names: Vec<&str> = joints.iter().map(|servo| servo.name()).collect();
+    names.join(", ")
+}
+
+/// A command turned into a checked, ordered list of steps
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct ActionPlan {
+    /// What the plan does, in a few words
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+    pub steps: Vec<Step>,
+}
+
+impl ActionPlan {
+    /// JSON schema handed to the model so it can only emit well-formed plans
+    pub fn response_schema() -> serde_json::Value {
+        let integer = serde_json::json!({ "type": "INTEGER" });
+        let moves = serde_json::json!({
+            "type": "ARRAY",
+            "items": {
+                "type": "OBJECT",
+                "properties": { "servo": integer, "angle": integer },
+                "required": ["servo", "angle"]
+            }
+        });
+
+        serde_json::json!({
+            "type": "OBJECT",
+            "properties": {
+                "summary": { "type": "STRING" },
+                "steps": {
+                    "type": "ARRAY",
+                    "items": {
+                        "type": "OBJECT",
+                        "properties": {
+                            "type": {
+                                "type": "STRING",
+                                "enum": ["move", "coordinated_move", "gripper", "wait", "pose", "home", "trajectory", "sequence"]
+                            },
+                            "servo": integer,
+                            "angle": integer,
+                            "duration_ms": integer,
+                            "moves": moves,
+                            "waypoints": {
+                                "type": "ARRAY",
+                                "items": {
+                                    "type": "OBJECT",
+                                    "properties": {
+                                        "moves": moves.clone(),
+                                        "pose": { "type": "STRING" },
+                                        "duration_ms": integer
+                                    }
+                                }
+                            },
+                            "state": { "type": "STRING", "enum": ["open", "touch", "closed"] },
+                            "ms": integer,
+                            "name": { "type": "STRING" }
+                        },
+                        "required": ["type"]
+                    }
+                }
+            },
+            "required": ["steps"]
+        })
+    }
+
+    pub fn load(path: &std::path::Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_str(&text).with_context(|| format!("Invalid action plan {}", path.display()))
+    }
+
+    /// Replaces sequence steps with the steps they stand for
+    pub fn expand(&self, sequences: &Library) -> Result<Vec<Step>> {
+        let mut steps = Vec::new();
+        for step in &self.steps {
+            match step {
+                Step::Sequence { name } => steps.extend(sequences.expand(name)?),
+                step => steps.push(step.clone()),
+            }
+        }
+        Ok(steps)
+    }
+
+    /// Checks every step against the profile and safety envelope, starting from `positions`
+    ///
+    /// Returns the expanded steps ready for `execute`.
+    pub fn validate(&self, profile: &CalibrationProfile, sequences: &Library, positions: [u8; 4]) -> Result<Vec<Step>> {
+        let steps = self.expand(sequences)?;
+        if steps.is_empty() {
+            anyhow::bail!("The plan has no steps");
+        }
+        if steps.len() > MAX_STEPS {
+            anyhow::bail!("The plan has {} steps, more than the {} allowed", steps.len(), MAX_STEPS);
+        }
+
+        let envelope = SafetyEnvelope::new(profile);
+        let mut pose = positions;
+
+        for (i, step) in steps.iter().enumerate() {
+            let context = || format!("Step {} ({})", i + 1, step);
+
+            match step {
+                Step::Move {
+                    duration_ms: Some(ms),
+                    ..
+                } => check_duration(*ms).with_context(context)?,
+                Step::CoordinatedMove { moves, duration_ms } => {
+                    if let Some(ms) = duration_ms {
+                        check_duration(*ms).with_context(context)?;
+                    }
+                    if moves.is_empty() {
+                        anyhow::bail!("{}: no servos to move", context());
+                    }
+                    for (j, m) in moves.iter().enumerate() {
+                        if moves[..j].iter().any(|other| other.servo == m.servo) {
+                            anyhow::bail!("{}: {} is moved twice", context(), m.servo.name());
+                        }
+                    }
+                }
+                Step::Pose {
+                    joints: Some(joints),
+                    ..
+                }
+                | Step::Home {
+                    joints: Some(joints),
+                } if joints.is_empty() => {
+                    anyhow::bail!("{}: no joints to move", context());
+                }
+                Step::Wait { ms } if Duration::from_millis(*ms) > MAX_WAIT => {
+                    anyhow::bail!("{}: waits longer than {}s", context(), MAX_WAIT.as_secs());
+                }
+                Step::Trajectory { waypoints } => {
+                    if waypoints.is_empty() || waypoints.len() > MAX_WAYPOINTS {
+                        anyhow::bail!("{}: needs 1-{} waypoints", context(), MAX_WAYPOINTS);
+                    }
+                    for (j, point) in waypoints.iter().enumerate() {
+                        check_point(point).with_context(|| format!("{}, waypoint {}", context(), j + 1))?;
+                    }
+
+                    // Every waypoint has to be safe, not just where the path ends
+                    for (j, target) in trajectory_poses(waypoints, profile, pose)?.into_iter().enumerate() {
+                        pose = envelope
+                            .check(pose, &driver::pose_changes(pose, target))
+                            .with_context(|| format!("{}, waypoint {}", context(), j + 1))?;
+                    }
+                    continue;
+                }
+                _ => {}
+            }
+
+            let moves = step_moves(step, profile).with_context(context)?;
+            pose = envelope.check(pose, &moves).with_context(context)?;
+        }
+
+        Ok(steps)
+    }
+}
+
+fn check_point(point: &TrajectoryPoint) -> Result<()> {
+    if let Some(ms) = point.duration_ms {
+        check_duration(ms)?;
+    }
+    match (&point.pose, point.moves.is_empty()) {
+        (Some(_), false) => anyhow::bail!("give either moves or a pose, not both"),
+        (None, true) => anyhow::bail!("no servos to move"),
+        (Some(_), true) if point.joints.as_ref().is_some_and(|joints| joints.is_empty()) => {
+            anyhow::bail!("no joints to move")
+        }
+        _ => {}
+    }
+    for (j, m) in point.moves.iter().enumerate() {
+        if point.moves[..j].iter().any(|other| other.servo == m.servo) {
+            anyhow::bail!("{} is moved twice", m.servo.name());
+        }
+    }
+    Ok(())
+}
+
+/// The whole arm's pose at each waypoint, starting from `start`
+fn trajectory_poses(waypoints: &[TrajectoryPoint], profile: &CalibrationProfile, start: [u8; 4]) -> Result<Vec<[u8; 4]>> {
+    let mut pose = start;
+    waypoints
+        .iter()
+        .map(|point| {
+            let moves = match &point.pose {
+                Some(name) => only(profile.pose(name)?.moves(), &point.joints),
+                None => point.moves.iter().map(|m| (m.servo, m.angle)).collect(),
+            };
+            for (servo, angle) in moves {
+                pose[servo.index()] = angle;
+            }
+            Ok(pose)
+        })
+        .collect()
+}
+
+fn trajectory_durations(waypoints: &[TrajectoryPoint]) -> Vec<Option<Duration>> {
+    waypoints.iter().map(|point| point.duration_ms.map(Duration::from_millis)).collect()
+}
+
+fn check_duration(ms: u64) -> Result<()> {
+    let duration = Duration::from_millis(ms);
+    if duration < MIN_DURATION || duration > MAX_DURATION {
+        anyhow::bail!(
+            "duration {}ms is outside {}-{}ms",
+            ms,
+            MIN_DURATION.as_millis(),
+            MAX_DURATION.as_millis()
+        );
+    }
+    Ok(())
+}
+
+/// Servo targets a step drives the arm to
+fn step_moves(step: &Step, profile: &CalibrationProfile) -> Result<Vec<(ServoId, u8)>> {
+    Ok(match step {
+        Step::Move { servo, angle, .. } => vec![(*servo, *angle)],
+        Step::CoordinatedMove { moves, .. } => moves.iter().map(|m| (m.servo, m.angle)).collect(),
+        Step::Gripper { state } => vec![(ServoId::Gripper, gripper_angle(profile, *state))],
+        Step::Pose { name, joints } => only(profile.pose(name)?.moves(), joints),
+        Step::Home { joints } => only(profile.home_moves(), joints),
+        Step::Wait { .. } => Vec::new(),
+        Step::Trajectory { .. } => anyhow::bail!("Trajectories move through several poses"),
+        Step::Sequence { name } => anyhow::bail!("Sequence {} was not expanded", name),
+    })
+}
+
+fn only(moves: Vec<(ServoId, u8)>, joints: &Option<Vec<ServoId>>) -> Vec<(ServoId, u8)> {
+    match joints {
+        Some(joints) => moves.into_iter().filter(|(servo, _)| joints.contains(servo)).collect(),
+        None => moves,
+    }
+}
+
+fn gripper_angle(profile: &CalibrationProfile, state: GripperState) -> u8 {
+    match state {
+        GripperState::Open => profile.gripper.open,
+        GripperState::Touch => profile.gripper.touch,
+        GripperState::Closed => profile.gripper.closed,
+    }
+}
+
+/// Runs validated steps on the arm, calling `report` before each one
+///
+/// Each move waits for the firmware to finish it. An emergency stop halts the
+/// arm where it is and ends the plan with an error.
+pub fn execute(
+    steps: &[Step],
+    driver: &mut ArmDriver,
+    stop: &StopListener,
+    mut report: impl FnMut(usize, &Step),
+) -> Result<Duration> {
+    let started = Instant::now();
+
+    for (i, step) in steps.iter().enumerate() {
+        report(i, step);
+
+        let wait = match step {
+            Step::Wait { ms } => Duration::from_millis(*ms),
+            Step::Move {
+                servo,
+                angle,
+                duration_ms,
+            } => {
+                let duration = duration_ms
+                    .map(Duration::from_millis)
+                    .unwrap_or_else(|| driver::calculate_duration(driver.position(*servo), *angle));
+                driver.move_servo(*servo, *angle, duration)? + SETTLE_TIME
+            }
+            Step::CoordinatedMove {
+                moves,
+                duration_ms: Some(ms),
+            } => {
+                let moves: Vec<(ServoId, u8)> = moves.iter().map(|m| (m.servo, m.angle)).collect();
+                driver.move_coordinated_over(&moves, Duration::from_millis(*ms))? + SETTLE_TIME
+            }
+            Step::Trajectory { waypoints } => {
+                let poses = trajectory_poses(waypoints, driver.profile(), driver.positions())?;
+                driver.move_trajectory(&poses, &trajectory_durations(waypoints), stop)? + SETTLE_TIME
+            }
+            step => {
+                let moves = step_moves(step, driver.profile())?;
+                driver.move_coordinated(&moves)? + SETTLE_TIME
+            }
+        };
+
+        sleep_unless_stopped(wait, driver, stop)?;
+    }
+
+    Ok(started.elapsed())
+}
+
+/// The commands `execute` would send, with when it would send them, without an arm
+pub fn record(steps: &[Step], profile: &CalibrationProfile, start: [u8; 4]) -> Result<Recording> {
+    let mut positions = start;
+    let mut elapsed = Duration::ZERO;
+    let mut commands = Vec::new();
+    // The driver sends each move step, and each trajectory waypoint, as one command
+    let mut write = 0;
+
+    for step in steps {
+        let moves = match step {
+            Step::Wait { ms } => {
+                elapsed += Duration::from_millis(*ms);
+                continue;
+            }
+            Step::Move {
+                servo,
+                angle,
+                duration_ms,
+            } => {
+                let duration = duration_ms
+                    .map(Duration::from_millis)
+                    .unwrap_or_else(|| driver::calculate_duration(positions[servo.index()], *angle));
+                vec![(*servo, *angle, duration)]
+            }
+            Step::Trajectory { waypoints } => {
+                let poses = trajectory_poses(waypoints, profile, positions)?;
+                let (joints, spline) = driver::plan_trajectory(positions, &poses, &trajectory_durations(waypoints));
+
+                // Streamed as the driver does: no more than the firmware queue holds ahead
+                let ends = spline.ends();
+                for i in 0..spline.durations.len() {
+                    let sent = match i.checked_sub(crate::trajectory::SEGMENT_QUEUE + 1) {
+                        Some(done) => elapsed + ends[done],
+                        None => elapsed,
+                    };
+                    for (servo, segments) in joints.iter().zip(&spline.segments) {
+                        commands.push(RecordedCommand {
+                            t_ms: sent.as_secs_f64() * 1000.0,
+                            write,
+                            command: driver::format_segment(*servo, &segments[i])?.trim().to_string(),
+                        });
+                    }
+                    write += 1;
+                }
+
+                if let Some(last) = poses.last() {
+                    positions = *last;
+                }
+                elapsed += spline.duration() + SETTLE_TIME;
+                continue;
+            }
+            step => {
+                let moves = step_moves(step, profile)?;
+                let duration = match step {
+                    Step::CoordinatedMove {
+                        duration_ms: Some(ms), ..
+                    } => Duration::from_millis(*ms),
+                    _ => driver::coordinated_duration(positions, &moves, 1.0),
+                };
+                moves.into_iter().map(|(servo, angle)| (servo, angle, duration)).collect()
+            }
+        };
+
+        let mut longest = Duration::ZERO;
+        for (servo, angle, duration) in moves {
+            commands.push(RecordedCommand {
+                t_ms: elapsed.as_secs_f64() * 1000.0,
+                write,
+                command: driver::format_move(servo, angle, duration)?.trim().to_string(),
+            });
+            let from = positions[servo.index()];
+            longest = longest.max(crate::trajectory::firmware_duration(angle as f32 - from as f32, duration));
+            positions[servo.index()] = angle;
+        }
+        elapsed += longest + SETTLE_TIME;
+        write += 1;
+    }
+
+    Ok(Recording {
+        duration_ms: elapsed.as_secs_f64() * 1000.0,
+        commands,
+    })
+}
+
+fn sleep_unless_stopped(duration: Duration, driver: &mut ArmDriver, stop: &StopListener) -> Result<()> {
+    if stop.sleep(duration) {
+        driver.stop()?;
+        anyhow::bail!("Plan stopped by emergency stop");
+    }
+    Ok(())
+}
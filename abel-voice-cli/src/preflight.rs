@@ -0,0 +1,770 @@
+use colored::Colorize;
+
+use crate::driver::ServoId;
+use crate::profile::CalibrationProfile;
+
+/// Modules a generated script may import
+const ALLOWED_IMPORTS: [&str; 3] = ["serial", "time", "math"];
+
+/// Calls that reach outside the arm: shells, processes, files and dynamic code
+const BLOCKED_CALLS: [&str; 9] = [
+    "open",
+    "eval",
+    "exec",
+    "compile",
+    "__import__",
+    "os.system",
+    "os.popen",
+    "os.remove",
+    "shutil.rmtree",
+];
+
+/// Call prefixes that are blocked whatever follows them
+const BLOCKED_MODULES: [&str; 2] = ["subprocess.", "socket."];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    /// Suspicious but possibly fine
+    Warning,
+    /// The script should not run as is
+    Error,
+}
+
+#[derive(Debug)]
+pub struct Finding {
+    pub line: usize,
+    pub severity: Severity,
+    /// Short name of the check, shown in the table's second column
+    pub check: &'static str,
+    pub message: String,
+}
+
+/// Everything the analyzer found in one script
+#[derive(Debug, Default)]
+pub struct Report {
+    pub findings: Vec<Finding>,
+    /// Move calls whose literal angles were checked against the limits
+    pub moves_checked: usize,
+    /// Move calls with computed arguments the analyzer can't evaluate
+    pub moves_unchecked: usize,
+}
+
+impl Report {
+    pub fn errors(&self) -> usize {
+        self.findings
+            .iter()
+            .filter(|f| f.severity == Severity::Error)
+            .count()
+    }
+
+    /// Prints the findings as a table, followed by a one-line summary of the move checks
+    pub fn print(&self) {
+        println!("{}", "Pre-flight check:".bright_white().underline());
+
+        if !self.findings.is_empty() {
+            println!("  {:>4}  {:<8}  {}", "Line".dimmed(), "Check".dimmed(), "Finding".dimmed());
+            for finding in &self.findings {
+                let check = format!("{:<8}", finding.check);
+                let check = match finding.severity {
+                    Severity::Error => check.red(),
+                    Severity::Warning => check.yellow(),
+                };
+                println!("  {:>4}  {}  {}", finding.line, check, finding.message);
+            }
+        }
+
+        let mut summary = format!("{} literal moves checked against calibrated limits", self.moves_checked);
+        if self.moves_unchecked > 0 {
+            summary += &format!(", {} with computed angles not checked", self.moves_unchecked);
+        }
+        match self.errors() {
+            0 => println!("  {} {}", "✓".green(), summary),
+            n => println!("  {} {} problems; {}", "✗".red(), n, summary),
+        }
+    }
+}
+
+/// Inspects a generated script without running it
+///
+/// Flags imports outside the allow-list, blocked calls, serial ports other than
+/// `serial_port`, and move calls or raw commands whose literal angles are outside
+/// the calibrated limits.
+pub fn analyze(script: &str, profile: &CalibrationProfile, serial_port: &str) -> Report {
+    let tokens = tokenize(script);
+    let mut report = Report::default();
+
+    let mut i = 0;
+    while i < tokens.len() {
+        let token = &tokens[i];
+        match &token.kind {
+            Kind::Name(name) if name == "import" && token.starts_line => {
+                i = check_import(&tokens, i + 1, false, &mut report);
+                continue;
+            }
+            Kind::Name(name) if name == "from" && token.starts_line => {
+                i = check_import(&tokens, i + 1, true, &mut report);
+                continue;
+            }
+            Kind::Name(_) => {
+                let (name, next) = dotted_name(&tokens, i);
+                let defined = i > 0 && matches!(&tokens[i - 1].kind, Kind::Name(n) if n == "def");
+                if is_op(&tokens, next, '(') && !defined {
+                    check_call(&name, &tokens, next + 1, token.line, profile, serial_port, &mut report);
+                }
+                i = next;
+                continue;
+            }
+            Kind::Str(text) => {
+                if let Some((servo, angle)) = move_command(text) {
+                    check_angle(servo, angle, token.line, profile, &mut report);
+                } else if looks_like_port(text) && text != serial_port {
+                    report.findings.push(Finding {
+                        line: token.line,
+                        severity: Severity::Warning,
+                        check: "serial",
+                        message: format!("'{}' looks like a serial port but the arm is on {}", text, serial_port),
+                    });
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+
+    // A port opened by serial.Serial is reported as an error; don't repeat it as a warning
+    let opened: Vec<usize> = report
+        .findings
+        .iter()
+        .filter(|f| f.check == "serial" && f.severity == Severity::Error)
+        .map(|f| f.line)
+        .collect();
+    report
+        .findings
+        .retain(|f| !(f.check == "serial" && f.severity == Severity::Warning && opened.contains(&f.line)));
+    report.findings.sort_by_key(|f| f.line);
+
+    report
+}
+
+/// Checks the modules of an `import`/`from` statement, returning the token after it
+fn check_import(tokens: &[Token], mut i: usize, from: bool, report: &mut Report) -> usize {
+    let line = tokens.get(i).map_or(0, |t| t.line);
+
+    loop {
+        match tokens.get(i).map(|t| &t.kind) {
+            Some(Kind::Name(_)) => {
+                let (module, next) = dotted_name(tokens, i);
+                let root = module.split('.').next().unwrap_or_default();
+                if !ALLOWED_IMPORTS.contains(&root) {
+                    report.findings.push(Finding {
+                        line,
+                        severity: Severity::Error,
+                        check: "import",
+                        message: format!(
+                            "'{}' is not in the allow-list ({})",
+                            module,
+                            ALLOWED_IMPORTS.join(", ")
+                        ),
+                    });
+                }
+                i = next;
+                if from {
+                    // The imported names belong to the module just checked
+                    break;
+                }
+                if matches!(tokens.get(i).map(|t| &t.kind), Some(Kind::Name(n)) if n == "as") {
+                    i += 2;
+                }
+                if !is_op(tokens, i, ',') {
+                    break;
+                }
+                i += 1;
+            }
+            Some(Kind::Op('.')) => {
+                // Relative import of a local module
+                report.findings.push(Finding {
+                    line,
+                    severity: Severity::Error,
+                    check: "import",
+                    message: "relative imports are not allowed".to_string(),
+                });
+                break;
+            }
+            _ => break,
+        }
+    }
+
+    while i < tokens.len() && tokens[i].kind != Kind::Newline {
+        i += 1;
+    }
+    i
+}
+
+fn check_call(
+    name: &str,
+    tokens: &[Token],
+    args: usize,
+    line: usize,
+    profile: &CalibrationProfile,
+    serial_port: &str,
+    report: &mut Report,
+) {
+    let blocked = BLOCKED_CALLS.contains(&name)
+        || BLOCKED_MODULES.iter().any(|prefix| name.starts_with(prefix))
+        || name.starts_with("os.exec")
+        || name.starts_with("os.spawn");
+    if blocked {
+        report.findings.push(Finding {
+            line,
+            severity: Severity::Error,
+            check: "call",
+            message: format!("{}() is not allowed in robot scripts", name),
+        });
+        return;
+    }
+
+    match name {
+        "serial.Serial" => {
+            if let Some(Kind::Str(port)) = tokens.get(args).map(|t| &t.kind) {
+                if port != serial_port {
+                    report.findings.push(Finding {
+                        line,
+                        severity: Severity::Error,
+                        check: "serial",
+                        message: format!("opens '{}' instead of the arm on {}", port, serial_port),
+                    });
+                }
+            }
+        }
+        "move_servo_smooth" => match (int_at(tokens, args), int_after_comma(tokens, args)) {
+            (Some(servo), Some(angle)) => check_angle(servo, angle, line, profile, report),
+            _ => report.moves_unchecked += 1,
+        },
+        "move_coordinated" => match coordinated_literal(tokens, args) {
+            Some(moves) => {
+                for (servo, angle) in moves {
+                    check_angle(servo, angle, line, profile, report);
+                }
+            }
+            None => report.moves_unchecked += 1,
+        },
+        "move_trajectory" => check_trajectory(tokens, args, line, profile, report),
+        _ => {}
+    }
+}
+
+/// Checks the literal waypoints of `move_trajectory(servo_ids, [...])`
+///
+/// Waypoints are an angle or a tuple of angles, one per servo. The call counts as
+/// unchecked when the servos or any waypoint are computed.
+fn check_trajectory(tokens: &[Token], args: usize, line: usize, profile: &CalibrationProfile, report: &mut Report) {
+    let servos = match int_tuple(tokens, args) {
+        Some((servos, next)) if is_op(tokens, next, ',') && is_op(tokens, next + 1, '[') => Some((servos, next + 2)),
+        _ => None,
+    };
+    let Some((servos, mut i)) = servos else {
+        report.moves_unchecked += 1;
+        return;
+    };
+
+    let mut computed = false;
+    while i < tokens.len() && !is_op(tokens, i, ']') {
+        match int_tuple(tokens, i) {
+            Some((angles, end))
+                if angles.len() == servos.len() && (is_op(tokens, end, ',') || is_op(tokens, end, ']')) =>
+            {
+                for (&servo, &angle) in servos.iter().zip(&angles) {
+                    check_angle(servo, angle, line, profile, report);
+                }
+                i = end;
+            }
+            _ => {
+                computed = true;
+                i = skip_expression(tokens, i);
+            }
+        }
+        if is_op(tokens, i, ',') {
+            i += 1;
+        } else if !is_op(tokens, i, ']') {
+            computed = true;
+            break;
+        }
+    }
+
+    if computed {
+        report.moves_unchecked += 1;
+    }
+}
+
+fn check_angle(servo: i64, angle: i64, line: usize, profile: &CalibrationProfile, report: &mut Report) {
+    report.moves_checked += 1;
+
+    let Some(id) = u8::try_from(servo).ok().and_then(|s| ServoId::try_from(s).ok()) else {
+        report.findings.push(Finding {
+            line,
+            severity: Severity::Error,
+            check: "move",
+            message: format!("servo {} does not exist (0-3)", servo),
+        });
+        return;
+    };
+
+    let cal = profile.servo(id);
+    if angle < i64::from(cal.min) || angle > i64::from(cal.max) {
+        report.findings.push(Finding {
+            line,
+            severity: Severity::Error,
+            check: "move",
+            message: format!(
+                "{} to {}° is outside its calibrated limits {}-{}°",
+                id.name(),
+                angle,
+                cal.min,
+                cal.max
+            ),
+        });
+    }
+}
+
+/// Parses `[(servo, angle), ...]` made only of integer literals
+fn coordinated_literal(tokens: &[Token], mut i: usize) -> Option<Vec<(i64, i64)>> {
+    if !is_op(tokens, i, '[') {
+        return None;
+    }
+    i += 1;
+
+    let mut moves = Vec::new();
+    loop {
+        if is_op(tokens, i, ']') {
+            return Some(moves);
+        }
+        if !is_op(tokens, i, '(') {
+            return None;
+        }
+        let servo = int_at(tokens, i + 1)?;
+        let (angle, next) = int_token(tokens, i + 3)?;
+        if !is_op(tokens, i + 2, ',') || !is_op(tokens, next, ')') {
+            return None;
+        }
+        moves.push((servo, angle));
+        i = next + 1;
+        if is_op(tokens, i, ',') {
+            i += 1;
+        }
+    }
+}
+
+/// The second argument when the first is a single integer literal
+fn int_after_comma(tokens: &[Token], i: usize) -> Option<i64> {
+    let (_, next) = int_token(tokens, i)?;
+    if !is_op(tokens, next, ',') {
+        return None;
+    }
+    let (value, end) = int_token(tokens, next + 1)?;
+    // `angle + 10` and the like are computed, not literal
+    (is_op(tokens, end, ',') || is_op(tokens, end, ')')).then_some(value)
+}
+
+fn int_at(tokens: &[Token], i: usize) -> Option<i64> {
+    let (value, next) = int_token(tokens, i)?;
+    (is_op(tokens, next, ',') || is_op(tokens, next, ')')).then_some(value)
+}
+
+/// An integer literal or a tuple of them, and the index after it
+fn int_tuple(tokens: &[Token], i: usize) -> Option<(Vec<i64>, usize)> {
+    if !is_op(tokens, i, '(') {
+        return int_token(tokens, i).map(|(value, next)| (vec![value], next));
+    }
+
+    let mut values = Vec::new();
+    let mut i = i + 1;
+    while !is_op(tokens, i, ')') {
+        let (value, next) = int_token(tokens, i)?;
+        values.push(value);
+        i = next;
+        if is_op(tokens, i, ',') {
+            i += 1;
+        } else if !is_op(tokens, i, ')') {
+            return None;
+        }
+    }
+    Some((values, i + 1))
+}
+
+/// The index of the `,`, `)` or `]` that ends the expression starting at `i`
+fn skip_expression(tokens: &[Token], mut i: usize) -> usize {
+    let mut depth = 0usize;
+    while let Some(token) = tokens.get(i) {
+        match token.kind {
+            Kind::Op('(' | '[' | '{') => depth += 1,
+            Kind::Op(')' | ']' | '}') if depth == 0 => break,
+            Kind::Op(')' | ']' | '}') => depth -= 1,
+            Kind::Op(',') if depth == 0 => break,
+            _ => {}
+        }
+        i += 1;
+    }
+    i
+}
+
+/// An integer literal, possibly negative, and the index after it
+fn int_token(tokens: &[Token], i: usize) -> Option<(i64, usize)> {
+    match tokens.get(i).map(|t| &t.kind) {
+        Some(Kind::Number(Some(n))) => Some((*n, i + 1)),
+        Some(Kind::Op('-')) => match tokens.get(i + 1).map(|t| &t.kind) {
+            Some(Kind::Number(Some(n))) => Some((-n, i + 2)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// `a.b.c` starting at a name token, and the index after it
+fn dotted_name(tokens: &[Token], mut i: usize) -> (String, usize) {
+    let mut name = String::new();
+    while let Some(Kind::Name(part)) = tokens.get(i).map(|t| &t.kind) {
+        name.push_str(part);
+        i += 1;
+        if is_op(tokens, i, '.') && matches!(tokens.get(i + 1).map(|t| &t.kind), Some(Kind::Name(_))) {
+            name.push('.');
+            i += 1;
+        } else {
+            break;
+        }
+    }
+    (name, i)
+}
+
+fn is_op(tokens: &[Token], i: usize, op: char) -> bool {
+    tokens.get(i).is_some_and(|t| t.kind == Kind::Op(op))
+}
+
+/// Servo and angle of a literal firmware command such as `#0M90T800` or `#0Q90T800V0.0,0.0`
+fn move_command(text: &str) -> Option<(i64, i64)> {
+    let text = text.trim().strip_prefix('#')?;
+    let (servo, rest) = text.split_once('M').or_else(|| text.split_once('Q'))?;
+    let (angle, rest) = rest.split_once('T')?;
+    let duration = rest.split_once('V').map_or(rest, |(duration, _)| duration);
+    duration.parse::<u64>().ok()?;
+    Some((servo.parse().ok()?, angle.parse().ok()?))
+}
+
+fn looks_like_port(text: &str) -> bool {
+    let com = text
+        .strip_prefix("COM")
+        .is_some_and(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()));
+    com || text.starts_with("/dev/tty") || text.starts_with("/dev/cu.")
+}
+
+#[derive(Debug, PartialEq)]
+enum Kind {
+    Name(String),
+    /// Numeric literal, with its value when it is an integer
+    Number(Option<i64>),
+    /// Contents of a plain string literal; f-strings are kept empty
+    Str(String),
+    Op(char),
+    /// End of a logical line
+    Newline,
+}
+
+#[derive(Debug)]
+struct Token {
+    kind: Kind,
+    line: usize,
+    /// First token of a logical line, where statements like `import` start
+    starts_line: bool,
+}
+
+/// Just enough of Python's tokenizer to find names, calls and literals
+fn tokenize(source: &str) -> Vec<Token> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut line = 1;
+    let mut depth = 0usize;
+    let mut starts_line = true;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '\n' => {
+                if depth == 0 && !starts_line {
+                    push(&mut tokens, Kind::Newline, line, &mut starts_line);
+                }
+                line += 1;
+                i += 1;
+            }
+            '#' => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+            }
+            '\\' if chars.get(i + 1) == Some(&'\n') => {
+                line += 1;
+                i += 2;
+            }
+            ';' if depth == 0 => {
+                push(&mut tokens, Kind::Newline, line, &mut starts_line);
+                i += 1;
+            }
+            c if c.is_whitespace() => i += 1,
+            c if c.is_ascii_digit() => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_' || chars[i] == '.') {
+                    i += 1;
+                }
+                let text: String = chars[start..i].iter().filter(|&&c| c != '_').collect();
+                push(&mut tokens, Kind::Number(text.parse().ok()), line, &mut starts_line);
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+                let word: String = chars[start..i].iter().collect();
+
+                let is_prefix = word.len() <= 2 && word.chars().all(|c| "rRbBfFuU".contains(c));
+                if is_prefix && matches!(chars.get(i), Some('"') | Some('\'')) {
+                    let raw = word.to_lowercase().contains('r');
+                    let formatted = word.to_lowercase().contains('f');
+                    let token_line = line;
+                    let (text, end) = read_string(&chars, i, raw, &mut line);
+                    let text = if formatted { String::new() } else { text };
+                    push(&mut tokens, Kind::Str(text), token_line, &mut starts_line);
+                    i = end;
+                } else {
+                    push(&mut tokens, Kind::Name(word), line, &mut starts_line);
+                }
+            }
+            '"' | '\'' => {
+                let token_line = line;
+                let (text, end) = read_string(&chars, i, false, &mut line);
+                push(&mut tokens, Kind::Str(text), token_line, &mut starts_line);
+                i = end;
+            }
+            c => {
+                match c {
+                    '(' | '[' | '{' => depth += 1,
+                    ')' | ']' | '}' => depth = depth.saturating_sub(1),
+                    _ => {}
+                }
+                push(&mut tokens, Kind::Op(c), line, &mut starts_line);
+                i += 1;
+            }
+        }
+    }
+
+    tokens
+}
+
+fn push(tokens: &mut Vec<Token>, kind: Kind, line: usize, starts_line: &mut bool) {
+    let newline = kind == Kind::Newline;
+    tokens.push(Token {
+        kind,
+        line,
+        starts_line: *starts_line,
+    });
+    *starts_line = newline;
+}
+
+/// Reads a string literal starting at its opening quote, returning its text and the index after it
+fn read_string(chars: &[char], start: usize, raw: bool, line: &mut usize) -> (String, usize) {
+    let quote = chars[start];
+    let triple = chars.get(start + 1) == Some(&quote) && chars.get(start + 2) == Some(&quote);
+    let mut i = start + if triple { 3 } else { 1 };
+    let mut text = String::new();
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '\\' && i + 1 < chars.len() {
+            if chars[i + 1] == '\n' {
+                *line += 1;
+            }
+            if raw {
+                text.push(c);
+                text.push(chars[i + 1]);
+            } else {
+                text.push(match chars[i + 1] {
+                    'n' => '\n',
+                    't' => '\t',
+                    other => other,
+                });
+            }
+            i += 2;
+            continue;
+        }
+        if c == quote {
+            if !triple {
+                return (text, i + 1);
+            }
+            if chars.get(i + 1) == Some(&quote) && chars.get(i + 2) == Some(&quote) {
+                return (text, i + 3);
+            }
+        }
+        if c == '\n' {
+            *line += 1;
+            if !triple {
+                // Unterminated; Python would reject it, so stop here
+                return (text, i);
+            }
+        }
+        text.push(c);
+        i += 1;
+    }
+
+    (text, i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PORT: &str = "/dev/ttyACM0";
+
+    fn analyze_script(script: &str) -> Report {
+        analyze(script, &CalibrationProfile::default(), PORT)
+    }
+
+    /// (line, check) of every finding
+    fn findings(script: &str) -> Vec<(usize, &'static str)> {
+        analyze_script(script).findings.iter().map(|f| (f.line, f.check)).collect()
+    }
+
+    fn strings(source: &str) -> Vec<String> {
+        tokenize(source)
+            .into_iter()
+            .filter_map(|t| match t.kind {
+                Kind::Str(text) => Some(text),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn tokenize_reads_string_literals() {
+        assert_eq!(strings(r#"a = "x # y""#), ["x # y"]);
+        assert_eq!(strings(r#"a = 'it\'s\n'"#), ["it's\n"]);
+        assert_eq!(strings(r#"a = r"\d+\n""#), [r"\d+\n"]);
+        assert_eq!(strings("a = b'#0M90T800'"), ["#0M90T800"]);
+        assert_eq!(strings("a = '''one\n'two'\nthree'''"), ["one\n'two'\nthree"]);
+    }
+
+    #[test]
+    fn tokenize_keeps_f_strings_empty() {
+        assert_eq!(strings(r##"a = f"#{servo}M{angle}T800""##), [""]);
+        assert_eq!(strings(r#"a = rf"{x}\n""#), [""]);
+    }
+
+    #[test]
+    fn tokenize_tracks_lines_through_multi_line_tokens() {
+        let tokens = tokenize("a = '''one\ntwo'''\nb = (1,\n2)\nc = 1 + \\\n 2\nd");
+        let line_of = |name: &str| {
+            tokens
+                .iter()
+                .find(|t| t.kind == Kind::Name(name.to_string()))
+                .map(|t| (t.line, t.starts_line))
+        };
+
+        assert_eq!(line_of("b"), Some((3, true)));
+        assert_eq!(line_of("c"), Some((5, true)));
+        assert_eq!(line_of("d"), Some((7, true)));
+        // Brackets and backslash continuations don't end the logical line
+        let newlines = tokens.iter().filter(|t| t.kind == Kind::Newline).count();
+        assert_eq!(newlines, 3);
+    }
+
+    #[test]
+    fn imports_outside_the_allow_list_are_errors() {
+        assert!(findings("import serial, time as t, math\nfrom serial.tools import list_ports").is_empty());
+        assert_eq!(findings("import time, os"), [(1, "import")]);
+        assert_eq!(findings("import os.path"), [(1, "import")]);
+        assert_eq!(findings("from subprocess import run"), [(1, "import")]);
+        assert_eq!(findings("from . import helpers"), [(1, "import")]);
+        assert_eq!(findings("x = 1; import socket"), [(1, "import")]);
+        // Reported on the line naming the module
+        assert_eq!(findings("import \\\n    os"), [(2, "import")]);
+    }
+
+    #[test]
+    fn import_is_only_a_statement_at_the_start_of_a_line() {
+        assert!(findings("text = 'import os'\nprint(text, 'from os import path')").is_empty());
+        // `from` inside an expression isn't an import
+        assert!(findings("def g():\n    yield from range(3)").is_empty());
+    }
+
+    #[test]
+    fn blocked_calls_are_errors() {
+        for call in [
+            "open('f')",
+            "eval('1')",
+            "os.system('ls')",
+            "os.execv('/bin/sh', [])",
+            "subprocess.run(['ls'])",
+            "socket.socket()",
+            "__import__('os')",
+        ] {
+            assert_eq!(findings(call), [(1, "call")], "{}", call);
+        }
+    }
+
+    #[test]
+    fn blocked_names_are_fine_when_not_called_as_such() {
+        // Defining a function with the name, or a method that happens to share it
+        assert!(findings("def open(port):\n    pass").is_empty());
+        assert!(findings("ser.open()\nexpr.eval()").is_empty());
+        assert!(findings("# os.system('ls')").is_empty());
+    }
+
+    #[test]
+    fn literal_moves_are_checked_against_the_limits() {
+        // The default shoulder limits are 9-180
+        let report = analyze_script("move_servo_smooth(1, 5)\nmove_servo_smooth(1, 90, 800)\nmove_servo_smooth(1, angle)");
+        assert_eq!(report.moves_checked, 2);
+        assert_eq!(report.moves_unchecked, 1);
+        assert_eq!(findings("move_servo_smooth(1, 5)"), [(1, "move")]);
+        assert_eq!(findings("move_servo_smooth(0, -10)"), [(1, "move")]);
+        assert_eq!(findings("move_coordinated([(0, 90), (4, 90)])"), [(1, "move")]);
+        assert_eq!(findings("ser.write(b'#1M5T800\\n')"), [(1, "move")]);
+        assert_eq!(findings("ser.write(b'#1Q5T800V0.0,0.0\\n')"), [(1, "move")]);
+        assert!(findings("ser.write(b'#1M90T800\\n')").is_empty());
+    }
+
+    #[test]
+    fn literal_trajectory_waypoints_are_checked() {
+        let script = "move_trajectory(0, [60, 120, 60])\nmove_trajectory((1, 2), [(50, 90), (20, 90)])";
+        let report = analyze_script(script);
+        assert_eq!((report.moves_checked, report.moves_unchecked), (7, 0));
+        assert!(report.findings.is_empty());
+
+        // The default shoulder limits are 9-180
+        assert_eq!(findings("move_trajectory((1, 2), [(50, 90), (5, 90)])"), [(1, "move")]);
+        assert_eq!(findings("move_trajectory(1, [50, -5], 800)"), [(1, "move")]);
+        assert_eq!(findings("move_trajectory((4,), [(90,)])"), [(1, "move")]);
+    }
+
+    #[test]
+    fn computed_trajectories_count_as_unchecked() {
+        let report = analyze_script("move_trajectory((1, 2), [(50, current_positions[2]), (5, 90)])");
+        // The literal waypoint is still checked
+        assert_eq!((report.moves_checked, report.moves_unchecked), (2, 1));
+        assert_eq!(report.errors(), 1);
+
+        for script in [
+            "move_trajectory(ids, [(50, 90)])",
+            "move_trajectory(0, waypoints)",
+            "move_trajectory(0, [a + 1, 90])",
+            "move_trajectory((1, 2), [50, 90])",
+        ] {
+            assert_eq!(analyze_script(script).moves_unchecked, 1, "{}", script);
+        }
+    }
+
+    #[test]
+    fn other_serial_ports_are_flagged_once() {
+        assert!(findings("serial.Serial('/dev/ttyACM0', 115200)").is_empty());
+        let report = analyze_script("serial.Serial('/dev/ttyUSB1', 115200)\nbackup = 'COM3'");
+        let severities: Vec<_> = report.findings.iter().map(|f| (f.line, f.severity)).collect();
+        assert_eq!(severities, [(1, Severity::Error), (2, Severity::Warning)]);
+    }
+}
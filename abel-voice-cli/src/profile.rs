@@ -0,0 +1,246 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use crate::driver::{ServoId, HOME_ANGLE, MAX_ANGLE};
+use crate::kinematics::ArmGeometry;
+use crate::safety::SafetySettings;
+
+/// Mechanical range, rest angle and trim of one servo
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct ServoCalibration {
+    pub min: u8,
+    pub max: u8,
+    pub home: u8,
+    /// Added to every commanded angle to correct horn misalignment
+    #[serde(default)]
+    pub trim: i8,
+}
+
+impl ServoCalibration {
+    pub const fn new(min: u8, max: u8) -> Self {
+        Self {
+            min,
+            max,
+            home: HOME_ANGLE,
+            trim: 0,
+        }
+    }
+
+    /// Applies the trim, staying inside the firmware's 0-180 range
+    pub fn trimmed(&self, angle: u8) -> u8 {
+        (angle as i16 + self.trim as i16).clamp(0, MAX_ANGLE as i16) as u8
+    }
+
+    /// Commanded angle that puts the servo at `angle` as reported by the firmware
+    pub fn untrimmed(&self, angle: f32) -> u8 {
+        (angle.round() as i16 - self.trim as i16).clamp(0, MAX_ANGLE as i16) as u8
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Servos {
+    pub base: ServoCalibration,
+    pub shoulder: ServoCalibration,
+    pub elbow: ServoCalibration,
+    pub gripper: ServoCalibration,
+}
+
+/// Gripper angles found during calibration
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct GripperCalibration {
+    pub open: u8,
+    /// Closed just far enough to touch the object
+    pub touch: u8,
+    pub closed: u8,
+}
+
+/// A named arm position
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct Pose {
+    pub base: u8,
+    pub shoulder: u8,
+    pub elbow: u8,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gripper: Option<u8>,
+}
+
+impl Pose {
+    pub fn moves(&self) -> Vec<(ServoId, u8)> {
+        let mut moves = vec![
+            (ServoId::Base, self.base),
+            (ServoId::Shoulder, self.shoulder),
+            (ServoId::Elbow, self.elbow),
+        ];
+        if let Some(gripper) = self.gripper {
+            moves.push((ServoId::Gripper, gripper));
+        }
+        moves
+    }
+}
+
+/// Everything calibration discovers about a particular arm
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CalibrationProfile {
+    pub servos: Servos,
+    pub gripper: GripperCalibration,
+    #[serde(default = "default_poses")]
+    pub poses: BTreeMap<String, Pose>,
+    #[serde(default)]
+    pub geometry: ArmGeometry,
+    #[serde(default)]
+    pub safety: SafetySettings,
+}
+
+/// Pick and place poses from CALIBRATION.md
+fn default_poses() -> BTreeMap<String, Pose> {
+    let mut poses = BTreeMap::new();
+    poses.insert(
+        "pick".to_string(),
+        Pose {
+            base: 81,
+            shoulder: 9,
+            elbow: 84,
+            gripper: None,
+        },
+    );
+    poses.insert(
+        "place".to_string(),
+        Pose {
+            base: 126,
+            shoulder: 9,
+            elbow: 84,
+            gripper: None,
+        },
+    );
+    poses
+}
+
+impl Default for CalibrationProfile {
+    /// Values from CALIBRATION.md for the ESP32-C3 FNK0100 arm
+    fn default() -> Self {
+        Self {
+            servos: Servos {
+                base: ServoCalibration::new(0, 180),
+                shoulder: ServoCalibration::new(9, 180),
+                elbow: ServoCalibration::new(0, 180),
+                gripper: ServoCalibration::new(60, 120),
+            },
+            gripper: GripperCalibration {
+                open: 120,
+                touch: 90,
+                closed: 60,
+            },
+            poses: default_poses(),
+            geometry: ArmGeometry::default(),
+            safety: SafetySettings::default(),
+        }
+    }
+}
+
+impl CalibrationProfile {
+    /// `ABEL_PROFILE` if set, otherwise `profile.toml` in the user config dir
+    pub fn path() -> Result<PathBuf> {
+        if let Ok(path) = std::env::var("ABEL_PROFILE") {
+            return Ok(PathBuf::from(path));
+        }
+
+        let dir = dirs::config_dir().context("Could not find config directory")?;
+        Ok(dir.join("abel-voice").join("profile.toml"))
+    }
+
+    /// Loads the saved profile, or the defaults if calibration was never saved
+    pub fn load() -> Result<Self> {
+        let path = Self::path()?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let text = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let profile: Self = toml::from_str(&text)
+            .with_context(|| format!("Invalid calibration profile {}", path.display()))?;
+        profile.validate()?;
+
+        Ok(profile)
+    }
+
+    pub fn save(&self) -> Result<PathBuf> {
+        self.validate()?;
+
+        let path = Self::path()?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+
+        let text = toml::to_string_pretty(self).context("Failed to serialize profile")?;
+        std::fs::write(&path, text).with_context(|| format!("Failed to write {}", path.display()))?;
+
+        Ok(path)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        for servo in ServoId::ALL {
+            let cal = self.servo(servo);
+            if cal.min > cal.max || cal.max > MAX_ANGLE {
+                anyhow::bail!("{} limits {}-{} are invalid", servo.name(), cal.min, cal.max);
+            }
+            if cal.home < cal.min || cal.home > cal.max {
+                anyhow::bail!(
+                    "{} home {}° is outside its limits {}-{}",
+                    servo.name(),
+                    cal.home,
+                    cal.min,
+                    cal.max
+                );
+            }
+        }
+        Ok(())
+    }
+
+    pub fn servo(&self, servo: ServoId) -> &ServoCalibration {
+        match servo {
+            ServoId::Base => &self.servos.base,
+            ServoId::Shoulder => &self.servos.shoulder,
+            ServoId::Elbow => &self.servos.elbow,
+            ServoId::Gripper => &self.servos.gripper,
+        }
+    }
+
+    pub fn servo_mut(&mut self, servo: ServoId) -> &mut ServoCalibration {
+        match servo {
+            ServoId::Base => &mut self.servos.base,
+            ServoId::Shoulder => &mut self.servos.shoulder,
+            ServoId::Elbow => &mut self.servos.elbow,
+            ServoId::Gripper => &mut self.servos.gripper,
+        }
+    }
+
+    pub fn pose(&self, name: &str) -> Result<&Pose> {
+        self.poses.get(name).with_context(|| {
+            let known: Vec<&str> = self.poses.keys().map(|k| k.as_str()).collect();
+            format!("Unknown pose '{}' (known: {})", name, known.join(", "))
+        })
+    }
+
+    /// Copy with the full 0-180 range on every servo and no safety envelope, for finding limits during calibration
+    pub fn without_limits(&self) -> Self {
+        let mut profile = self.clone();
+        profile.safety = SafetySettings::disabled();
+        for servo in ServoId::ALL {
+            let cal = profile.servo_mut(servo);
+            cal.min = 0;
+            cal.max = MAX_ANGLE;
+        }
+        profile
+    }
+
+    /// Home angles of every servo, for a coordinated return home
+    pub fn home_moves(&self) -> Vec<(ServoId, u8)> {
+        ServoId::ALL
+            .iter()
+            .map(|&servo| (servo, self.servo(servo).home))
+            .collect()
+    }
+}
@@ -0,0 +1,124 @@
+use anyhow::Result;
+use async_trait::async_trait;
+
+use crate::driver::ServoId;
+use crate::grammar::{self, CLOSE, HOME, JOINTS, OPEN, STOP};
+use crate::interpreter::CommandResult;
+use crate::llm::{LlmBackend, Prompt, Task};
+use crate::plan::ActionPlan;
+use crate::profile::CalibrationProfile;
+use crate::sequence::Library;
+
+/// Keyword matching that needs no network or model, for when every LLM is out of reach
+///
+/// Understands stop, home, sequence names, opening and closing the gripper, "move
+/// <joint> to <angle>" and "move to x .. y .. z ..". It can't write scripts.
+pub struct RuleBased {
+    profile: CalibrationProfile,
+    /// Each sequence name with the words it is made of, e.g. ("PICK_PLACE", ["pick", "place"])
+    sequences: Vec<(String, Vec<String>)>,
+}
+
+impl RuleBased {
+    pub fn new(profile: &CalibrationProfile, sequences: &Library) -> Self {
+        Self {
+            profile: profile.clone(),
+            sequences: grammar::sequence_words(sequences),
+        }
+    }
+
+    pub fn interpret(&self, command: &str) -> CommandResult {
+        let words = grammar::normalize(command);
+        let has = |list: &[&str]| words.iter().any(|w| list.contains(&w.as_str()));
+        let result = CommandResult {
+            action: "unknown".to_string(),
+            ..Default::default()
+        };
+
+        if has(STOP) {
+            return CommandResult {
+                action: "stop".to_string(),
+                ..result
+            };
+        }
+
+        if let Some((name, _)) = grammar::find_sequence(&self.sequences, &words) {
+            return CommandResult {
+                action: "sequence".to_string(),
+                sequence_name: Some(name.to_string()),
+                ..result
+            };
+        }
+
+        if let (Some(x), Some(y), Some(z)) = (after(&words, "x"), after(&words, "y"), after(&words, "z")) {
+            return CommandResult {
+                action: "move_to".to_string(),
+                x: Some(x),
+                y: Some(y),
+                z: Some(z),
+                ..result
+            };
+        }
+
+        let joint = JOINTS
+            .iter()
+            .find(|(_, names)| has(names))
+            .map(|(servo, _)| *servo);
+        let angle = words.iter().find_map(|w| w.parse::<u8>().ok());
+
+        let gripper = match joint {
+            Some(ServoId::Gripper) | None if has(OPEN) => Some(self.profile.gripper.open),
+            Some(ServoId::Gripper) | None if has(CLOSE) => Some(self.profile.gripper.closed),
+            _ => None,
+        };
+        let target = match (joint, angle) {
+            (Some(servo), Some(angle)) => Some((servo, angle)),
+            _ => gripper.map(|angle| (ServoId::Gripper, angle)),
+        };
+        if let Some((servo, angle)) = target {
+            return CommandResult {
+                action: "move".to_string(),
+                servo: Some(servo as u8),
+                angle: Some(angle),
+                ..result
+            };
+        }
+
+        if has(HOME) {
+            return CommandResult {
+                action: "home".to_string(),
+                ..result
+            };
+        }
+
+        CommandResult {
+            message: Some(format!("The offline rules did not understand \"{}\"", command.trim())),
+            ..result
+        }
+    }
+
+    pub fn plan(&self, command: &str) -> Result<ActionPlan> {
+        self.interpret(command).to_plan(&self.profile)
+    }
+}
+
+#[async_trait]
+impl LlmBackend for RuleBased {
+    fn name(&self) -> String {
+        "offline rules".to_string()
+    }
+
+    async fn generate(&self, prompt: &Prompt<'_>) -> Result<String> {
+        match prompt.task {
+            Task::Interpret => Ok(serde_json::to_string(&self.interpret(prompt.command))?),
+            Task::Plan => Ok(serde_json::to_string(&self.plan(prompt.command)?)?),
+            Task::Script => anyhow::bail!("The offline rules can't write scripts; use an action plan instead"),
+        }
+    }
+}
+
+/// The number following `word`, e.g. 120 in "x 120"
+fn after(words: &[String], word: &str) -> Option<f32> {
+    let i = words.iter().position(|w| w == word)?;
+    words.get(i + 1)?.parse().ok()
+}
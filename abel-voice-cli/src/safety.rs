@@ -0,0 +1,275 @@
+use serde::{Deserialize, Serialize};
+
+use crate::driver::{ServoId, MAX_ANGLE};
+use crate::profile::CalibrationProfile;
+
+/// Inclusive angle range, written `[min, max]` in the profile
+pub type AngleRange = [u8; 2];
+
+/// Joint combination the arm must never be commanded into
+///
+/// A pose is inside the zone when every joint that has a range is inside it.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ForbiddenZone {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base: Option<AngleRange>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shoulder: Option<AngleRange>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub elbow: Option<AngleRange>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gripper: Option<AngleRange>,
+}
+
+impl ForbiddenZone {
+    pub fn range(&self, servo: ServoId) -> Option<AngleRange> {
+        match servo {
+            ServoId::Base => self.base,
+            ServoId::Shoulder => self.shoulder,
+            ServoId::Elbow => self.elbow,
+            ServoId::Gripper => self.gripper,
+        }
+    }
+
+    pub fn contains(&self, pose: [u8; 4]) -> bool {
+        let mut constrained = false;
+        for servo in ServoId::ALL {
+            if let Some([min, max]) = self.range(servo) {
+                constrained = true;
+                if !(min..=max).contains(&pose[servo.index()]) {
+                    return false;
+                }
+            }
+        }
+        constrained
+    }
+}
+
+/// `[safety]` section of the calibration profile
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct SafetySettings {
+    /// Largest change of any joint in a single command, in degrees
+    pub max_step: u8,
+    pub zones: Vec<ForbiddenZone>,
+}
+
+impl Default for SafetySettings {
+    fn default() -> Self {
+        Self {
+            max_step: 120,
+            zones: vec![ForbiddenZone {
+                // Upper arm near the table with the forearm curled back drives the gripper into it
+                name: "shoulder low with elbow folded".to_string(),
+                base: None,
+                shoulder: Some([0, 20]),
+                elbow: Some([0, 60]),
+                gripper: None,
+            }],
+        }
+    }
+}
+
+impl SafetySettings {
+    /// No step limit and no zones, for calibration
+    pub fn disabled() -> Self {
+        Self {
+            max_step: MAX_ANGLE,
+            zones: Vec::new(),
+        }
+    }
+}
+
+/// Why the envelope refused a command
+#[derive(Debug, Clone, PartialEq)]
+pub enum Violation {
+    OutsideLimits {
+        servo: ServoId,
+        angle: u8,
+        min: u8,
+        max: u8,
+    },
+    StepTooLarge {
+        servo: ServoId,
+        from: u8,
+        to: u8,
+        max_step: u8,
+    },
+    ForbiddenZone {
+        name: String,
+        pose: [u8; 4],
+    },
+}
+
+impl std::fmt::Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Violation::OutsideLimits { servo, angle, min, max } => write!(
+                f,
+                "{} angle {}° is outside its soft limits {}-{}°",
+                servo.name(),
+                angle,
+                min,
+                max
+            ),
+            Violation::StepTooLarge { servo, from, to, max_step } => write!(
+                f,
+                "{} step {}° -> {}° is larger than the {}° allowed in one command",
+                servo.name(),
+                from,
+                to,
+                max_step
+            ),
+            Violation::ForbiddenZone { name, pose } => write!(
+                f,
+                "Base {}° Shoulder {}° Elbow {}° Gripper {}° is in the forbidden zone '{}'",
+                pose[0], pose[1], pose[2], pose[3], name
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Violation {}
+
+/// Everything a command must satisfy before it is sent to the arm
+///
+/// Serialized as JSON for the Python safety shim, so scripts get the same checks.
+#[derive(Serialize, Clone, Debug)]
+pub struct SafetyEnvelope {
+    /// `[min, max]` of each servo, indexed by servo number
+    pub limits: [AngleRange; 4],
+    pub max_step: u8,
+    pub zones: Vec<ForbiddenZone>,
+}
+
+impl SafetyEnvelope {
+    pub fn new(profile: &CalibrationProfile) -> Self {
+        Self {
+            limits: ServoId::ALL.map(|servo| {
+                let cal = profile.servo(servo);
+                [cal.min, cal.max]
+            }),
+            max_step: profile.safety.max_step,
+            zones: profile.safety.zones.clone(),
+        }
+    }
+
+    /// Checks moves starting from `current`, returning the pose they end in
+    ///
+    /// This is the rule every path applies, including the script shim in
+    /// `sitecustomize.py` and the dry-run replay: moves sent together (one driver
+    /// call, one trajectory waypoint, one script write) are one command. Each move
+    /// must be inside its servo's limits and within `max_step` of where that servo
+    /// started; zones are checked only against the pose the command ends in.
+    pub fn check(&self, current: [u8; 4], moves: &[(ServoId, u8)]) -> Result<[u8; 4], Violation> {
+        let mut pose = current;
+
+        for &(servo, angle) in moves {
+            let [min, max] = self.limits[servo.index()];
+            if angle < min || angle > max {
+                return Err(Violation::OutsideLimits { servo, angle, min, max });
+            }
+
+            let from = current[servo.index()];
+            if from.abs_diff(angle) > self.max_step {
+                return Err(Violation::StepTooLarge {
+                    servo,
+                    from,
+                    to: angle,
+                    max_step: self.max_step,
+                });
+            }
+
+            pose[servo.index()] = angle;
+        }
+
+        if let Some(zone) = self.zones.iter().find(|zone| zone.contains(pose)) {
+            return Err(Violation::ForbiddenZone {
+                name: zone.name.clone(),
+                pose,
+            });
+        }
+
+        Ok(pose)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Default profile: shoulder 9-180, gripper 60-120, 120° steps and the
+    /// shoulder 0-20 / elbow 0-60 zone
+    fn envelope() -> SafetyEnvelope {
+        SafetyEnvelope::new(&CalibrationProfile::default())
+    }
+
+    #[test]
+    fn moves_inside_the_envelope_return_the_new_pose() {
+        let pose = envelope().check([90; 4], &[(ServoId::Base, 45), (ServoId::Gripper, 120)]);
+        assert_eq!(pose, Ok([45, 90, 90, 120]));
+    }
+
+    #[test]
+    fn limits_are_inclusive() {
+        let envelope = envelope();
+        assert!(envelope.check([90; 4], &[(ServoId::Shoulder, 9)]).is_ok());
+        assert_eq!(
+            envelope.check([90; 4], &[(ServoId::Shoulder, 8)]),
+            Err(Violation::OutsideLimits {
+                servo: ServoId::Shoulder,
+                angle: 8,
+                min: 9,
+                max: 180
+            })
+        );
+        assert!(envelope.check([90; 4], &[(ServoId::Gripper, 121)]).is_err());
+    }
+
+    #[test]
+    fn steps_are_measured_from_where_each_servo_started() {
+        let envelope = envelope();
+        assert!(envelope.check([0, 90, 90, 90], &[(ServoId::Base, 120)]).is_ok());
+        assert_eq!(
+            envelope.check([0, 90, 90, 90], &[(ServoId::Base, 121)]),
+            Err(Violation::StepTooLarge {
+                servo: ServoId::Base,
+                from: 0,
+                to: 121,
+                max_step: 120
+            })
+        );
+        // A second move of the same servo in one command doesn't get a fresh start
+        assert!(envelope
+            .check([0, 90, 90, 90], &[(ServoId::Base, 100), (ServoId::Base, 180)])
+            .is_err());
+    }
+
+    #[test]
+    fn zones_reject_the_final_pose() {
+        let result = envelope().check([90; 4], &[(ServoId::Shoulder, 15), (ServoId::Elbow, 50)]);
+        assert!(matches!(result, Err(Violation::ForbiddenZone { pose: [90, 15, 50, 90], .. })));
+    }
+
+    #[test]
+    fn zones_ignore_poses_passed_on_the_way_in_one_command() {
+        // Folding the elbow first would be in the zone on its own, but the command ends outside it
+        let moves = [(ServoId::Elbow, 50), (ServoId::Shoulder, 90)];
+        assert_eq!(envelope().check([90, 15, 90, 90], &moves), Ok([90, 90, 50, 90]));
+        // Sent as two commands, the first one is refused
+        assert!(envelope().check([90, 15, 90, 90], &moves[..1]).is_err());
+    }
+
+    #[test]
+    fn a_zone_without_ranges_contains_nothing() {
+        let zone = ForbiddenZone {
+            name: "empty".to_string(),
+            base: None,
+            shoulder: None,
+            elbow: None,
+            gripper: None,
+        };
+        assert!(!zone.contains([90; 4]));
+    }
+}
@@ -0,0 +1,315 @@
+use anyhow::{Context, Result};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::firmware::{self, FirmwareEvent};
+
+/// Address space a sandboxed script may use
+const MEMORY_LIMIT: u64 = 512 * 1024 * 1024;
+
+/// Largest file a sandboxed script may write into its scratch directory
+const FILE_SIZE_LIMIT: u64 = 16 * 1024 * 1024;
+
+const OPEN_FILES_LIMIT: u64 = 64;
+
+/// Directories the interpreter needs to start, granted read and execute access
+const SYSTEM_PATHS: &[&str] = &["/usr", "/lib", "/lib64", "/bin", "/etc"];
+
+/// Devices any process expects to be able to open
+const DEVICE_PATHS: &[&str] = &["/dev/null", "/dev/urandom"];
+
+/// How a generated script is confined while it runs
+#[derive(Clone, Debug)]
+pub struct SandboxConfig {
+    /// Wall-clock limit for the whole script; also used as its CPU-time limit
+    pub timeout: Duration,
+    /// The only device the script may open
+    pub serial_port: String,
+    pub baud: u32,
+    /// Namespaces, Landlock and rlimits (Linux only); when off only the timeout applies
+    pub isolate: bool,
+}
+
+/// Scratch directory and prepared restrictions for one script run
+pub struct Sandbox {
+    scratch: tempfile::TempDir,
+    #[cfg(target_os = "linux")]
+    ruleset: Option<landlock::Ruleset>,
+    config: SandboxConfig,
+}
+
+impl Sandbox {
+    /// Creates the scratch directory and, when isolating, the filesystem allow-list
+    ///
+    /// `read_only` are extra paths the script needs to read, such as the script itself,
+    /// the interpreter and the safety shim.
+    pub fn prepare(config: &SandboxConfig, read_only: &[PathBuf]) -> Result<Self> {
+        let scratch = tempfile::Builder::new()
+            .prefix("abel-script-")
+            .tempdir()
+            .context("Failed to create scratch directory")?;
+
+        #[cfg(target_os = "linux")]
+        let ruleset = if config.isolate {
+            let mut readable: Vec<PathBuf> = SYSTEM_PATHS.iter().map(PathBuf::from).collect();
+            readable.extend(read_only.iter().cloned());
+            Some(landlock::Ruleset::new(
+                &readable,
+                &DEVICE_PATHS.iter().map(PathBuf::from).collect::<Vec<_>>(),
+                Path::new(&config.serial_port),
+                scratch.path(),
+            )?)
+        } else {
+            None
+        };
+
+        #[cfg(not(target_os = "linux"))]
+        let _ = read_only;
+
+        Ok(Self {
+            scratch,
+            #[cfg(target_os = "linux")]
+            ruleset,
+            config: config.clone(),
+        })
+    }
+
+    /// The script's working directory, removed when the sandbox is dropped
+    pub fn scratch_dir(&self) -> &Path {
+        self.scratch.path()
+    }
+
+    /// Runs `command` in the scratch directory and, when isolating, confines it on exec
+    ///
+    /// Isolation also clears the environment, so API keys don't leak into the script;
+    /// set the script's own variables after calling this.
+    pub fn apply(&self, command: &mut tokio::process::Command) {
+        if self.config.isolate {
+            command.env_clear().env("PATH", "/usr/local/bin:/usr/bin:/bin");
+        }
+
+        command
+            .current_dir(self.scratch.path())
+            .env("HOME", self.scratch.path())
+            .env("TMPDIR", self.scratch.path());
+
+        #[cfg(target_os = "linux")]
+        if self.config.isolate {
+            let ruleset = self.ruleset.as_ref().map(|r| r.fd());
+            let cpu_secs = self.config.timeout.as_secs().max(1);
+
+            // Only async-signal-safe syscalls from here on: this runs in the forked child
+            unsafe {
+                command.pre_exec(move || confine(ruleset, cpu_secs));
+            }
+        }
+    }
+}
+
+/// Drops network access, caps resources and enforces the Landlock allow-list
+#[cfg(target_os = "linux")]
+fn confine(ruleset: Option<std::os::fd::RawFd>, cpu_secs: u64) -> std::io::Result<()> {
+    let check = |ret: libc::c_int| {
+        if ret < 0 {
+            Err(std::io::Error::last_os_error())
+        } else {
+            Ok(())
+        }
+    };
+
+    unsafe {
+        // A fresh network namespace has only a downed loopback interface
+        check(libc::unshare(libc::CLONE_NEWUSER | libc::CLONE_NEWNET))?;
+
+        for (resource, limit) in [
+            (libc::RLIMIT_AS, MEMORY_LIMIT),
+            (libc::RLIMIT_CPU, cpu_secs),
+            (libc::RLIMIT_FSIZE, FILE_SIZE_LIMIT),
+            (libc::RLIMIT_NOFILE, OPEN_FILES_LIMIT),
+            (libc::RLIMIT_CORE, 0),
+        ] {
+            let rlimit = libc::rlimit {
+                rlim_cur: limit,
+                rlim_max: limit,
+            };
+            check(libc::setrlimit(resource, &rlimit))?;
+        }
+
+        check(libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0))?;
+        if let Some(fd) = ruleset {
+            check(libc::syscall(libc::SYS_landlock_restrict_self, fd, 0) as libc::c_int)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Minimal Landlock bindings; see `Documentation/userspace-api/landlock.rst`
+#[cfg(target_os = "linux")]
+mod landlock {
+    use anyhow::{Context, Result};
+    use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::{Path, PathBuf};
+
+    const CREATE_RULESET_VERSION: u32 = 1;
+    const RULE_PATH_BENEATH: libc::c_int = 1;
+
+    const EXECUTE: u64 = 1 << 0;
+    const WRITE_FILE: u64 = 1 << 1;
+    const READ_FILE: u64 = 1 << 2;
+    const READ_DIR: u64 = 1 << 3;
+    /// Every right of ABI v1, from EXECUTE to MAKE_SYM
+    const ALL_V1: u64 = (1 << 13) - 1;
+    const REFER: u64 = 1 << 13;
+    const TRUNCATE: u64 = 1 << 14;
+
+    #[repr(C)]
+    struct RulesetAttr {
+        handled_access_fs: u64,
+    }
+
+    #[repr(C, packed)]
+    struct PathBeneathAttr {
+        allowed_access: u64,
+        parent_fd: i32,
+    }
+
+    /// A Landlock ruleset, built in the parent and enforced by the child before exec
+    pub struct Ruleset {
+        fd: OwnedFd,
+        handled: u64,
+    }
+
+    impl Ruleset {
+        /// Read-only `readable`, read-write `devices` and `serial`, everything in `scratch`
+        pub fn new(readable: &[PathBuf], devices: &[PathBuf], serial: &Path, scratch: &Path) -> Result<Self> {
+            let abi = unsafe {
+                libc::syscall(
+                    libc::SYS_landlock_create_ruleset,
+                    std::ptr::null::<RulesetAttr>(),
+                    0,
+                    CREATE_RULESET_VERSION,
+                )
+            };
+            if abi < 1 {
+                anyhow::bail!(
+                    "Landlock is not available on this kernel ({}); use --no-sandbox to run unconfined",
+                    std::io::Error::last_os_error()
+                );
+            }
+
+            let mut handled = ALL_V1;
+            if abi >= 2 {
+                handled |= REFER;
+            }
+            if abi >= 3 {
+                handled |= TRUNCATE;
+            }
+
+            let attr = RulesetAttr {
+                handled_access_fs: handled,
+            };
+            let fd = unsafe {
+                libc::syscall(
+                    libc::SYS_landlock_create_ruleset,
+                    &attr,
+                    std::mem::size_of::<RulesetAttr>(),
+                    0,
+                )
+            };
+            if fd < 0 {
+                return Err(std::io::Error::last_os_error()).context("Failed to create Landlock ruleset");
+            }
+
+            let ruleset = Self {
+                fd: unsafe { OwnedFd::from_raw_fd(fd as RawFd) },
+                handled,
+            };
+
+            for path in readable {
+                ruleset.allow(path, EXECUTE | READ_FILE | READ_DIR, false)?;
+            }
+            for path in devices {
+                ruleset.allow(path, READ_FILE | WRITE_FILE, false)?;
+            }
+            // A dry run never opens the port, so it may not exist
+            ruleset.allow(serial, READ_FILE | WRITE_FILE, false)?;
+            ruleset.allow(scratch, handled, true)?;
+
+            Ok(ruleset)
+        }
+
+        pub fn fd(&self) -> RawFd {
+            self.fd.as_raw_fd()
+        }
+
+        /// Grants `access` beneath `path`; missing paths are skipped unless `required`
+        fn allow(&self, path: &Path, access: u64, required: bool) -> Result<()> {
+            let c_path = std::ffi::CString::new(path.as_os_str().as_bytes())?;
+            let parent = unsafe { libc::open(c_path.as_ptr(), libc::O_PATH | libc::O_CLOEXEC) };
+            if parent < 0 {
+                if required {
+                    return Err(std::io::Error::last_os_error())
+                        .with_context(|| format!("Failed to open {}", path.display()));
+                }
+                return Ok(());
+            }
+            let parent = unsafe { OwnedFd::from_raw_fd(parent) };
+
+            // Directory-only rights are rejected on files
+            let is_dir = std::fs::metadata(path).map(|m| m.is_dir()).unwrap_or(false);
+            let access = if is_dir {
+                access
+            } else {
+                access & (EXECUTE | WRITE_FILE | READ_FILE | TRUNCATE)
+            };
+
+            let rule = PathBeneathAttr {
+                allowed_access: access & self.handled,
+                parent_fd: parent.as_raw_fd(),
+            };
+            let ret = unsafe {
+                libc::syscall(libc::SYS_landlock_add_rule, self.fd.as_raw_fd(), RULE_PATH_BENEATH, &rule, 0)
+            };
+            if ret < 0 {
+                return Err(std::io::Error::last_os_error())
+                    .with_context(|| format!("Failed to add Landlock rule for {}", path.display()));
+            }
+
+            Ok(())
+        }
+    }
+}
+
+/// Sends the firmware stop command without resetting the board
+///
+/// Backstop for scripts that were killed before their own stop handler ran. Returns
+/// the angles the firmware reports holding (trim included), if it answers in time.
+pub fn hold_arm(config: &SandboxConfig) -> Result<Option<[f32; 4]>> {
+    let mut port = serialport::new(&config.serial_port, config.baud)
+        .timeout(Duration::from_millis(500))
+        .dtr_on_open(false)
+        .open()
+        .with_context(|| format!("Failed to open serial port {}", config.serial_port))?;
+
+    port.write_all(b"!\n")?;
+    port.flush()?;
+
+    let mut reader = BufReader::new(port);
+    let mut line = String::new();
+    let deadline = Instant::now() + Duration::from_millis(500);
+    while Instant::now() < deadline {
+        line.clear();
+        match reader.read_line(&mut line) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {
+                if let Some(FirmwareEvent::Stopped(angles)) = firmware::parse_line(line.trim()) {
+                    return Ok(Some(angles));
+                }
+            }
+        }
+    }
+    Ok(None)
+}
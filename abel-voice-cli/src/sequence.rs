@@ -0,0 +1,356 @@
+use anyhow::{Context, Result};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::driver::{MAX_DURATION, MIN_DURATION};
+use crate::plan::Step;
+
+/// Sequences shipped with the CLI; files in the sequence directory override them by name
+const BUILTIN: &[(&str, &str)] = &[
+    ("wave.yaml", include_str!("../sequences/wave.yaml")),
+    ("nod_yes.yaml", include_str!("../sequences/nod_yes.yaml")),
+    ("shake_no.yaml", include_str!("../sequences/shake_no.yaml")),
+    ("pick_place.yaml", include_str!("../sequences/pick_place.yaml")),
+];
+
+/// How deeply sequences may run other sequences
+const MAX_DEPTH: usize = 8;
+
+/// A named list of plan steps, loaded from a YAML or TOML file
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Sequence {
+    /// Defaults to the file name in upper case
+    #[serde(default)]
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub steps: Vec<Step>,
+    #[serde(skip)]
+    pub source: Source,
+}
+
+/// Where a sequence was defined
+#[derive(Clone, Debug, Default)]
+pub enum Source {
+    #[default]
+    Builtin,
+    File(PathBuf),
+}
+
+impl std::fmt::Display for Source {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Source::Builtin => write!(f, "built-in"),
+            Source::File(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+impl Sequence {
+    /// Parses a `.yaml`/`.yml` or `.toml` definition; `path` picks the format and default name
+    pub fn parse(text: &str, path: &Path) -> Result<Self> {
+        let mut sequence: Sequence = match path.extension().and_then(|e| e.to_str()) {
+            Some("yaml" | "yml") => serde_yaml::from_str(text)?,
+            Some("toml") => toml::from_str(text)?,
+            _ => anyhow::bail!("Sequence files must be .yaml, .yml or .toml"),
+        };
+
+        if sequence.name.trim().is_empty() {
+            sequence.name = path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .unwrap_or_default()
+                .to_string();
+        }
+        sequence.name = sequence.name.trim().to_uppercase();
+        Ok(sequence)
+    }
+
+    /// Writes the definition as YAML or TOML, depending on the extension
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let text = match path.extension().and_then(|e| e.to_str()) {
+            Some("toml") => toml::to_string_pretty(self)?,
+            _ => serde_yaml::to_string(self)?,
+        };
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, text).with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Multiplies every explicit duration and wait by `factor`, e.g. 2.0 for half speed
+    ///
+    /// Moves without a duration keep the speed the driver picks for their distance.
+    /// Returns how many of those were left unscaled.
+    pub fn scale(&mut self, factor: f32) -> usize {
+        let scaled = |ms: u64| (ms as f32 * factor).round() as u64;
+        let mut unscaled = 0;
+
+        for step in &mut self.steps {
+            match step {
+                Step::Move {
+                    duration_ms: Some(ms), ..
+                }
+                | Step::CoordinatedMove {
+                    duration_ms: Some(ms), ..
+                } => {
+                    *ms = scaled(*ms).clamp(MIN_DURATION.as_millis() as u64, MAX_DURATION.as_millis() as u64);
+                }
+                Step::Wait { ms } => *ms = scaled(*ms),
+                Step::Trajectory { waypoints } => {
+                    for point in waypoints {
+                        match &mut point.duration_ms {
+                            Some(ms) => {
+                                *ms = scaled(*ms)
+                                    .clamp(MIN_DURATION.as_millis() as u64, MAX_DURATION.as_millis() as u64)
+                            }
+                            None => unscaled += 1,
+                        }
+                    }
+                }
+                Step::Sequence { .. } => {}
+                _ => unscaled += 1,
+            }
+        }
+
+        unscaled
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let mut sequence =
+            Self::parse(&text, path).with_context(|| format!("Invalid sequence file {}", path.display()))?;
+        sequence.source = Source::File(path.to_path_buf());
+        Ok(sequence)
+    }
+}
+
+/// Upper-case sequence name made of letters, digits and underscores
+pub fn normalize_name(name: &str) -> Result<String> {
+    let name = name.trim().replace([' ', '-'], "_").to_uppercase();
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        anyhow::bail!("Sequence names may only contain letters, digits, spaces, '-' and '_'");
+    }
+    Ok(name)
+}
+
+/// Every sequence the CLI, server and interpreter know by name
+#[derive(Clone, Debug)]
+pub struct Library {
+    /// Keyed by upper-case name
+    sequences: BTreeMap<String, Sequence>,
+}
+
+impl Library {
+    /// `ABEL_SEQUENCES` if set, otherwise `sequences/` in the user config dir
+    pub fn dir() -> Result<PathBuf> {
+        if let Ok(dir) = std::env::var("ABEL_SEQUENCES") {
+            return Ok(PathBuf::from(dir));
+        }
+
+        let dir = dirs::config_dir().context("Could not find config directory")?;
+        Ok(dir.join("abel-voice").join("sequences"))
+    }
+
+    /// The built-in sequences plus every valid file in the sequence directory
+    ///
+    /// Files that don't parse are skipped with a warning.
+    pub fn load() -> Result<Self> {
+        let Self { mut sequences } = Self::builtin();
+
+        let dir = Self::dir()?;
+        if dir.is_dir() {
+            let mut paths: Vec<PathBuf> = std::fs::read_dir(&dir)
+                .with_context(|| format!("Failed to read {}", dir.display()))?
+                .filter_map(|entry| entry.ok().map(|e| e.path()))
+                .filter(|path| {
+                    matches!(
+                        path.extension().and_then(|e| e.to_str()),
+                        Some("yaml" | "yml" | "toml")
+                    )
+                })
+                .collect();
+            paths.sort();
+
+            // One broken file shouldn't take every other sequence (and voice control) down with it
+            for path in paths {
+                match Sequence::load(&path) {
+                    Ok(sequence) => {
+                        sequences.insert(sequence.name.clone(), sequence);
+                    }
+                    Err(e) => eprintln!("{} Skipping {:#}", "⚠".yellow(), e),
+                }
+            }
+        }
+
+        Ok(Self { sequences })
+    }
+
+    /// Only the sequences shipped with the CLI
+    pub fn builtin() -> Self {
+        let mut sequences = BTreeMap::new();
+        for (file, text) in BUILTIN {
+            let sequence = Sequence::parse(text, Path::new(file)).expect("built-in sequences are valid");
+            sequences.insert(sequence.name.clone(), sequence);
+        }
+        Self { sequences }
+    }
+
+    /// Where `sequence` is saved: its own file, or a new one in the sequence directory
+    pub fn path_for(sequence: &Sequence) -> Result<PathBuf> {
+        match &sequence.source {
+            Source::File(path) => Ok(path.clone()),
+            Source::Builtin => Ok(Self::dir()?.join(format!("{}.yaml", sequence.name.to_lowercase()))),
+        }
+    }
+
+    /// A file in the sequence directory named after `name`, whether or not it parses
+    pub fn find_file(name: &str) -> Result<Option<PathBuf>> {
+        let stem = name.trim().to_lowercase();
+        let dir = Self::dir()?;
+        Ok(["yaml", "yml", "toml"]
+            .iter()
+            .map(|extension| dir.join(format!("{}.{}", stem, extension)))
+            .find(|path| path.is_file()))
+    }
+
+    pub fn get(&self, name: &str) -> Result<&Sequence> {
+        self.sequences.get(&name.trim().to_uppercase()).with_context(|| {
+            format!("Unknown sequence '{}' (known: {})", name, self.names().join(", "))
+        })
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.sequences.keys().map(|k| k.as_str()).collect()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Sequence> {
+        self.sequences.values()
+    }
+
+    /// Steps of a sequence, with the sequences it runs replaced by their steps
+    pub fn expand(&self, name: &str) -> Result<Vec<Step>> {
+        let mut steps = Vec::new();
+        self.expand_into(name, &mut Vec::new(), &mut steps)?;
+        Ok(steps)
+    }
+
+    fn expand_into(&self, name: &str, running: &mut Vec<String>, steps: &mut Vec<Step>) -> Result<()> {
+        let sequence = self.get(name)?;
+        if running.contains(&sequence.name) {
+            anyhow::bail!("Sequence {} runs itself ({} → {})", sequence.name, running.join(" → "), sequence.name);
+        }
+        if running.len() >= MAX_DEPTH {
+            anyhow::bail!("Sequences are nested more than {} deep ({})", MAX_DEPTH, running.join(" → "));
+        }
+
+        running.push(sequence.name.clone());
+        for step in &sequence.steps {
+            match step {
+                Step::Sequence { name } => self.expand_into(name, running, steps)?,
+                step => steps.push(step.clone()),
+            }
+        }
+        running.pop();
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A library of YAML sequences, each given as (file name, definition)
+    fn library(files: &[(&str, &str)]) -> Library {
+        let sequences = files
+            .iter()
+            .map(|(file, text)| {
+                let sequence = Sequence::parse(text, Path::new(file)).unwrap();
+                (sequence.name.clone(), sequence)
+            })
+            .collect();
+        Library { sequences }
+    }
+
+    fn expanded(library: &Library, name: &str) -> Vec<String> {
+        library.expand(name).unwrap().iter().map(|step| step.to_string()).collect()
+    }
+
+    #[test]
+    fn parse_names_sequences_after_their_file_unless_named() {
+        let sequence = Sequence::parse("steps: [{ type: wait, ms: 100 }]", Path::new("little wave.yaml")).unwrap();
+        assert_eq!(sequence.name, "LITTLE WAVE");
+
+        let toml = "name = ' nod '\n[[steps]]\ntype = \"wait\"\nms = 1";
+        assert_eq!(Sequence::parse(toml, Path::new("x.toml")).unwrap().name, "NOD");
+
+        assert!(Sequence::parse("steps: []", Path::new("wave.json")).is_err());
+    }
+
+    #[test]
+    fn builtin_sequences_expand() {
+        let library = Library::builtin();
+        assert_eq!(library.names(), ["NOD_YES", "PICK_PLACE", "SHAKE_NO", "WAVE"]);
+        for name in library.names() {
+            assert!(!library.expand(name).unwrap().is_empty(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn expand_inlines_nested_sequences() {
+        let library = library(&[
+            (
+                "twice.yaml",
+                "steps: [{ type: sequence, name: once }, { type: wait, ms: 5 }, { type: sequence, name: ONCE }]",
+            ),
+            ("once.yaml", "steps: [{ type: wait, ms: 1 }]"),
+        ]);
+        assert_eq!(expanded(&library, "twice"), ["Wait 1ms", "Wait 5ms", "Wait 1ms"]);
+    }
+
+    #[test]
+    fn expand_rejects_cycles() {
+        let library = library(&[
+            ("a.yaml", "steps: [{ type: sequence, name: b }]"),
+            ("b.yaml", "steps: [{ type: wait, ms: 1 }, { type: sequence, name: a }]"),
+            ("me.yaml", "steps: [{ type: sequence, name: me }]"),
+        ]);
+
+        let error = library.expand("a").unwrap_err().to_string();
+        assert_eq!(error, "Sequence A runs itself (A → B → A)");
+        assert!(library.expand("me").is_err());
+    }
+
+    #[test]
+    fn expand_limits_nesting_depth() {
+        // LEVEL_1 runs LEVEL_2 and so on, with LEVEL_<depth> doing the actual work
+        let nested = |depth: usize| {
+            let files: Vec<(String, String)> = (1..=depth)
+                .map(|level| {
+                    let step = if level == depth {
+                        "{ type: wait, ms: 1 }".to_string()
+                    } else {
+                        format!("{{ type: sequence, name: level_{} }}", level + 1)
+                    };
+                    (format!("level_{}.yaml", level), format!("steps: [{}]", step))
+                })
+                .collect();
+            let files: Vec<(&str, &str)> = files.iter().map(|(f, t)| (f.as_str(), t.as_str())).collect();
+            library(&files)
+        };
+
+        assert_eq!(expanded(&nested(MAX_DEPTH), "level_1"), ["Wait 1ms"]);
+        let error = nested(MAX_DEPTH + 1).expand("level_1").unwrap_err().to_string();
+        assert!(error.starts_with("Sequences are nested more than 8 deep"), "{}", error);
+    }
+
+    #[test]
+    fn expand_reports_unknown_sequences() {
+        let library = library(&[("a.yaml", "steps: [{ type: sequence, name: missing }]")]);
+        let error = library.expand("a").unwrap_err().to_string();
+        assert!(error.starts_with("Unknown sequence 'missing'"), "{}", error);
+    }
+}
@@ -0,0 +1,137 @@
+use anyhow::{Context, Result};
+use serialport::{SerialPortInfo, SerialPortType};
+use std::time::Duration;
+
+pub const DEFAULT_BAUD: u32 = 115200;
+
+/// USB VID/PID pairs of the serial bridges found on ESP32-C3 boards
+const KNOWN_BRIDGES: &[(u16, u16, &str)] = &[
+    (0x303A, 0x1001, "ESP32-C3 USB Serial/JTAG"),
+    (0x1A86, 0x7523, "WCH CH340"),
+    (0x1A86, 0x55D3, "WCH CH343"),
+    (0x1A86, 0x55D4, "WCH CH9102"),
+    (0x10C4, 0xEA60, "Silicon Labs CP210x"),
+];
+
+/// Serial connection settings shared by every command that talks to the arm
+#[derive(Clone, Debug)]
+pub struct SerialConfig {
+    /// Explicit device path, or `None`/`"auto"` to discover it
+    pub port: Option<String>,
+    pub baud: u32,
+}
+
+impl SerialConfig {
+    pub fn new(port: Option<String>, baud: u32) -> Self {
+        Self { port, baud }
+    }
+
+    /// Returns the configured device path, discovering it if none was given
+    pub fn resolve_port(&self) -> Result<String> {
+        match self.port.as_deref() {
+            Some(port) if !port.trim().is_empty() && port != "auto" => Ok(port.to_string()),
+            _ => discover_port(),
+        }
+    }
+
+    /// Returns a copy with the device path resolved, so later opens skip discovery
+    pub fn resolved(&self) -> Result<Self> {
+        Ok(Self::new(Some(self.resolve_port()?), self.baud))
+    }
+
+    /// Opens the serial port with the configured baud rate
+    pub fn open(&self) -> Result<Box<dyn serialport::SerialPort>> {
+        let path = self.resolve_port()?;
+
+        serialport::new(&path, self.baud)
+            .timeout(Duration::from_secs(2))
+            .open()
+            .with_context(|| format!("Failed to open serial port {}", path))
+    }
+}
+
+/// A serial port that looks like an ESP32-C3 USB bridge
+pub struct Candidate {
+    pub path: String,
+    pub description: String,
+}
+
+/// Lists all serial ports whose USB VID/PID match a known ESP32-C3 bridge
+pub fn find_candidates() -> Result<Vec<Candidate>> {
+    let ports = serialport::available_ports().context("Failed to enumerate serial ports")?;
+    Ok(ports.into_iter().filter_map(candidate).collect())
+}
+
+fn candidate(info: SerialPortInfo) -> Option<Candidate> {
+    let SerialPortType::UsbPort(usb) = &info.port_type else {
+        return None;
+    };
+
+    let (_, _, bridge) = KNOWN_BRIDGES
+        .iter()
+        .find(|(vid, pid, _)| *vid == usb.vid && *pid == usb.pid)?;
+
+    // macOS lists every device twice (tty.* and cu.*); only cu.* is usable for output
+    if info.port_name.starts_with("/dev/tty.") {
+        return None;
+    }
+
+    let description = match &usb.product {
+        Some(product) => format!("{} ({})", bridge, product),
+        None => bridge.to_string(),
+    };
+
+    Some(Candidate {
+        path: info.port_name,
+        description: format!("{} [{:04x}:{:04x}]", description, usb.vid, usb.pid),
+    })
+}
+
+/// Finds the arm's serial port, asking the user to pick when several match
+///
+/// Without a terminal to ask on, several matches are an error listing them.
+pub fn discover_port() -> Result<String> {
+    let mut candidates = find_candidates()?;
+
+    match candidates.len() {
+        0 => {
+            let all: Vec<String> = serialport::available_ports()
+                .unwrap_or_default()
+                .into_iter()
+                .map(|p| p.port_name)
+                .collect();
+
+            if all.is_empty() {
+                anyhow::bail!(
+                    "No serial ports found. Is the arm plugged in? Set --port or ABEL_SERIAL_PORT."
+                );
+            }
+            anyhow::bail!(
+                "No ESP32-C3 serial bridge detected (available ports: {}). Set --port or ABEL_SERIAL_PORT.",
+                all.join(", ")
+            );
+        }
+        1 => Ok(candidates.remove(0).path),
+        _ => {
+            use dialoguer::Select;
+
+            let items: Vec<String> = candidates
+                .iter()
+                .map(|c| format!("{}  {}", c.path, c.description))
+                .collect();
+            if !console::Term::stderr().is_term() {
+                anyhow::bail!(
+                    "Several ESP32-C3 serial bridges detected ({}). Set --port or ABEL_SERIAL_PORT.",
+                    items.join(", ")
+                );
+            }
+            let selection = Select::new()
+                .with_prompt("Several arms detected, select serial port")
+                .items(&items)
+                .default(0)
+                .interact()?;
+
+            Ok(candidates.remove(selection).path)
+        }
+    }
+}
@@ -4,9 +4,119 @@ use actix_multipart::Multipart;
 use futures_util::StreamExt;
 use serde::{Deserialize, Serialize};
 use anyhow::Result;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::Duration;
 
-use crate::whisper::WhisperClient;
-use crate::gemini::GeminiClient;
+use crate::driver::{self, ArmDriver, ServoId};
+use crate::estop::StopSignal;
+use crate::executor;
+use crate::kinematics::{self, JointAngles, Point};
+use crate::plan::{ActionPlan, JointTarget, Step};
+use crate::preflight;
+use crate::profile::CalibrationProfile;
+use crate::safety::{SafetyEnvelope, Violation};
+use crate::sandbox::SandboxConfig;
+use crate::sequence::{Library, Sequence};
+use crate::serial::SerialConfig;
+use crate::teach::{self, TeachSession, Waypoint};
+use crate::wizard::STEP_SIZES;
+use crate::stt;
+use crate::interpreter::{CommandResult, Interpreter};
+
+/// Longest a script started over HTTP may run
+const SCRIPT_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// How long `/stop` waits for a running script to be halted and report where it stopped
+const SCRIPT_STOP_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Why a request that needs the serial port was refused while a script has it
+#[derive(Debug)]
+struct ScriptRunning;
+
+impl std::fmt::Display for ScriptRunning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "A script is running on the arm; wait for it to finish or POST /stop")
+    }
+}
+
+impl std::error::Error for ScriptRunning {}
+
+/// Serial settings plus the lazily opened connection to the arm
+struct ServiceState {
+    /// Resolved once at startup, or why no arm was found then
+    serial: std::result::Result<SerialConfig, String>,
+    driver: Mutex<Option<ArmDriver>>,
+    stop: StopSignal,
+    /// Sequence being recorded through the `/teach` endpoints
+    teach: Mutex<Option<TeachSession>>,
+    /// Where the last `/script/run` script left the arm, sent once an emergency stop has halted it
+    script_stopped: Mutex<Option<mpsc::Receiver<Option<[u8; 4]>>>>,
+    /// Set while a `/script/run` script owns the serial port; changed with `driver` locked
+    script_running: AtomicBool,
+}
+
+impl ServiceState {
+    /// Serial settings with the port found at startup; requests never rediscover it
+    fn serial(&self) -> Result<&SerialConfig> {
+        self.serial.as_ref().map_err(|e| {
+            anyhow::anyhow!("{} Restart the service once the arm is connected.", e)
+        })
+    }
+
+    /// Runs `f` against the driver, opening the serial port on first use
+    ///
+    /// Fails with `ScriptRunning` while a script has the port: reopening it would reset the ESP32.
+    fn with_driver<T>(&self, f: impl FnOnce(&mut ArmDriver) -> Result<T>) -> Result<T> {
+        let mut guard = self.driver.lock().unwrap();
+        if self.script_running.load(Ordering::SeqCst) {
+            return Err(ScriptRunning.into());
+        }
+        if guard.is_none() {
+            *guard = Some(ArmDriver::open(self.serial()?)?);
+        }
+
+        let result = f(guard.as_mut().unwrap());
+        if let Err(e) = &result {
+            // Reopen after I/O failures, but not after rejected commands: opening resets the ESP32
+            if e.chain().any(|c| c.is::<std::io::Error>()) {
+                *guard = None;
+            }
+        }
+        result
+    }
+
+    /// Interrupts anything running and holds the arm where it is
+    ///
+    /// Returns the held angles when they are known. With no connection open nothing
+    /// is reopened: that would reset the ESP32, and while a script runs the port is
+    /// its own. The executor halts the arm through the script's connection instead.
+    fn emergency_stop(&self) -> Result<Option<[u8; 4]>> {
+        self.stop.trigger();
+
+        if let Some(script) = self.script_stopped.lock().unwrap().take() {
+            match script.recv_timeout(SCRIPT_STOP_TIMEOUT) {
+                Ok(held) => return Ok(held),
+                Err(mpsc::RecvTimeoutError::Timeout) => return Ok(None),
+                // The script had already finished
+                Err(mpsc::RecvTimeoutError::Disconnected) => {}
+            }
+        }
+
+        match self.driver.lock().unwrap().as_mut() {
+            Some(driver) => driver.stop().map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Last commanded angles if the arm is connected, otherwise the calibrated home
+    fn positions(&self, profile: &CalibrationProfile) -> [u8; 4] {
+        match self.driver.lock().unwrap().as_ref() {
+            Some(driver) => driver.positions(),
+            None => ServoId::ALL.map(|servo| profile.servo(servo).home),
+        }
+    }
+}
 
 #[derive(Serialize)]
 struct TranscriptResponse {
@@ -16,6 +126,8 @@ struct TranscriptResponse {
 #[derive(Deserialize)]
 struct InterpretRequest {
     transcript: String,
+    /// Current servo angles, for clients that drive the arm themselves
+    positions: Option<[u8; 4]>,
 }
 
 #[derive(Serialize)]
@@ -26,9 +138,87 @@ struct InterpretResponse {
     #[serde(skip_serializing_if = "Option::is_none")]
     angle: Option<u8>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    x: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    y: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    z: Option<f32>,
+    /// IK solution for "move_to", so clients don't need their own kinematics
+    #[serde(skip_serializing_if = "Option::is_none")]
+    joints: Option<JointAngles>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     sequence_name: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     message: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ms: Option<u64>,
+    /// Steps of "multi" and moves of "parallel", in the same format
+    #[serde(skip_serializing_if = "Option::is_none")]
+    commands: Option<Vec<InterpretResponse>>,
+}
+
+#[derive(Deserialize)]
+struct MoveRequest {
+    servo: ServoId,
+    angle: u8,
+    duration_ms: Option<u64>,
+}
+
+#[derive(Deserialize)]
+struct CoordinatedMoveRequest {
+    moves: Vec<JointTarget>,
+}
+
+#[derive(Deserialize)]
+struct PlanRequest {
+    transcript: String,
+}
+
+#[derive(Serialize)]
+struct PlanResponse {
+    plan: ActionPlan,
+    /// The plan with sequences expanded, as it will run
+    steps: Vec<Step>,
+}
+
+/// A sequence definition, plus the moves it makes for clients that drive the arm themselves
+#[derive(Serialize)]
+struct SequenceResponse {
+    #[serde(flatten)]
+    sequence: Sequence,
+    /// `built-in` or the file it was loaded from
+    source: String,
+    /// Moves in the web GUI's `{servo, angle, delay}` format, starting from 90° on every
+    /// servo; missing when the sequence can't be resolved against the calibration profile
+    #[serde(skip_serializing_if = "Option::is_none")]
+    frames: Option<Vec<Frame>>,
+}
+
+#[derive(Serialize)]
+struct Frame {
+    servo: ServoId,
+    angle: u8,
+    /// Milliseconds until the next move is sent
+    delay: u64,
+}
+
+#[derive(Deserialize)]
+struct ScriptRequest {
+    /// Python source of the script
+    script: String,
+}
+
+#[derive(Serialize)]
+struct MoveResponse {
+    duration_ms: u64,
+}
+
+#[derive(Serialize)]
+struct StopResponse {
+    stopped: bool,
+    /// Angles the servos are held at, unless no connection to the arm was open
+    #[serde(skip_serializing_if = "Option::is_none")]
+    positions: Option<[u8; 4]>,
 }
 
 #[derive(Serialize)]
@@ -36,6 +226,50 @@ struct ErrorResponse {
     error: String,
 }
 
+#[derive(Deserialize)]
+struct TeachStartRequest {
+    name: String,
+    description: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct JogRequest {
+    servo: ServoId,
+    /// Degrees to move by, at most one jog step; the result stays inside the calibrated limits
+    delta: i16,
+}
+
+#[derive(Serialize)]
+struct JogResponse {
+    servo: ServoId,
+    angle: u8,
+}
+
+#[derive(Serialize)]
+struct TeachResponse {
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    waypoints: Vec<Waypoint>,
+}
+
+impl From<&TeachSession> for TeachResponse {
+    fn from(session: &TeachSession) -> Self {
+        Self {
+            name: session.name.clone(),
+            description: session.description.clone(),
+            waypoints: session.waypoints().to_vec(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct TeachSaveResponse {
+    name: String,
+    path: String,
+    steps: Vec<Step>,
+}
+
 async fn health() -> impl Responder {
     HttpResponse::Ok().json(serde_json::json!({
         "status": "ok",
@@ -77,21 +311,20 @@ async fn transcribe(mut payload: Multipart) -> impl Responder {
         });
     }
 
-    // Transcribe using Whisper
-    let whisper = match WhisperClient::new() {
-        Ok(w) => w,
+    let speech = match stt::from_env() {
+        Ok(speech) => speech,
         Err(e) => {
             return HttpResponse::InternalServerError().json(ErrorResponse {
-                error: format!("Failed to initialize Whisper client: {}", e),
+                error: format!("Failed to initialize speech-to-text: {:#}", e),
             });
         }
     };
 
-    let transcript = match whisper.transcribe(&audio_data).await {
+    let transcript = match speech.transcribe(&audio_data).await {
         Ok(t) => t,
         Err(e) => {
             return HttpResponse::InternalServerError().json(ErrorResponse {
-                error: format!("Transcription failed: {}", e),
+                error: format!("Transcription failed ({}): {:#}", speech.name(), e),
             });
         }
     };
@@ -99,42 +332,712 @@ async fn transcribe(mut payload: Multipart) -> impl Responder {
     HttpResponse::Ok().json(TranscriptResponse { transcript })
 }
 
-async fn interpret(req: web::Json<InterpretRequest>) -> impl Responder {
-    let gemini = match GeminiClient::new() {
-        Ok(g) => g,
+async fn interpret(state: web::Data<ServiceState>, req: web::Json<InterpretRequest>) -> impl Responder {
+    let interpreter = match Interpreter::new() {
+        Ok(interpreter) => interpreter,
         Err(e) => {
             return HttpResponse::InternalServerError().json(ErrorResponse {
-                error: format!("Failed to initialize Gemini client: {}", e),
+                error: format!("Failed to initialize the interpreter: {:#}", e),
             });
         }
     };
 
-    let result = match gemini.interpret_command(&req.transcript).await {
+    let profile = match CalibrationProfile::load() {
+        Ok(p) => p,
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(ErrorResponse {
+                error: format!("Failed to load calibration profile: {}", e),
+            });
+        }
+    };
+
+    let positions = req.positions.unwrap_or_else(|| state.positions(&profile));
+    let result = match interpreter.interpret_command(&req.transcript, positions).await {
         Ok(r) => r,
         Err(e) => {
             return HttpResponse::InternalServerError().json(ErrorResponse {
-                error: format!("Interpretation failed: {}", e),
+                error: format!("Interpretation failed: {:#}", e),
             });
         }
     };
 
-    // Parse the Gemini response into our response format
+    let rejected = |message: String| {
+        HttpResponse::Ok().json(InterpretResponse {
+            action: "unknown".to_string(),
+            servo: None,
+            angle: None,
+            x: result.x,
+            y: result.y,
+            z: result.z,
+            joints: None,
+            sequence_name: None,
+            message: Some(message),
+            ms: None,
+            commands: None,
+        })
+    };
+
+    let mut joints = None;
+    let mut message = result.message.clone();
+    let mut sequence_name = result.sequence_name.clone();
+    let moves = match result.action.as_str() {
+        "move" => match (result.servo.map(ServoId::try_from), result.angle) {
+            (Some(Ok(servo)), Some(angle)) => vec![(servo, angle)],
+            _ => return rejected("Move needs a servo (0-3) and an angle".to_string()),
+        },
+        "move_to" => match solve_move_to(&result, &profile) {
+            Ok(j) => {
+                joints = Some(j);
+                message = None;
+                j.to_moves()
+            }
+            Err(e) => return rejected(format!("Can't reach that position: {}", e)),
+        },
+        "home" => profile.home_moves(),
+        "sequence" => {
+            let name = sequence_name.clone().unwrap_or_default();
+            let checked = Library::load().and_then(|library| {
+                let name = library.get(&name)?.name.clone();
+                let plan = ActionPlan {
+                    summary: None,
+                    steps: vec![Step::Sequence { name: name.clone() }],
+                };
+                plan.validate(&profile, &library, positions)?;
+                Ok(name)
+            });
+            match checked {
+                Ok(name) => sequence_name = Some(name),
+                Err(e) => return rejected(format!("Can't run that sequence: {:#}", e)),
+            }
+            Vec::new()
+        }
+        "wait" | "multi" | "parallel" => {
+            // Checked step by step from the current pose, like a plan
+            let checked = Library::load().and_then(|library| {
+                result.to_plan(&profile)?.validate(&profile, &library, positions)?;
+                command_response(&result, &profile, &library)
+            });
+            return match checked {
+                Ok(response) => HttpResponse::Ok().json(response),
+                Err(e) if e.is::<Violation>() => rejected(format!("Rejected by safety envelope: {:#}", e)),
+                Err(e) => rejected(format!("Can't do that: {:#}", e)),
+            };
+        }
+        "stop" => {
+            // Don't wait for the client to act on it; halt anything this service is driving
+            let stopped = web::block(move || state.emergency_stop()).await;
+            if let Ok(Err(e)) = stopped {
+                eprintln!("Emergency stop could not reach the arm: {}", e);
+            }
+            return HttpResponse::Ok().json(InterpretResponse {
+                action: result.action.clone(),
+                servo: None,
+                angle: None,
+                x: None,
+                y: None,
+                z: None,
+                joints: None,
+                sequence_name: None,
+                message: None,
+                ms: None,
+                commands: None,
+            });
+        }
+        _ => Vec::new(),
+    };
+
+    if let Err(violation) = SafetyEnvelope::new(&profile).check(positions, &moves) {
+        return rejected(format!("Rejected by safety envelope: {}", violation));
+    }
+
+    // Parse the interpreter result into our response format
     let response = InterpretResponse {
         action: result.action.clone(),
         servo: result.servo,
         angle: result.angle,
-        sequence_name: result.sequence_name.clone(),
-        message: result.message.clone(),
+        x: result.x,
+        y: result.y,
+        z: result.z,
+        joints,
+        sequence_name,
+        message,
+        ms: result.ms,
+        commands: None,
     };
 
     HttpResponse::Ok().json(response)
 }
 
-pub async fn run_server(port: u16) -> Result<()> {
+fn solve_move_to(result: &CommandResult, profile: &CalibrationProfile) -> Result<JointAngles> {
+    let (Some(x), Some(y), Some(z)) = (result.x, result.y, result.z) else {
+        anyhow::bail!("move_to needs x, y and z");
+    };
+    kinematics::inverse(&profile.geometry, Point { x, y, z })
+}
+
+/// A multi-step command as returned to clients, with every Cartesian target solved
+fn command_response(result: &CommandResult, profile: &CalibrationProfile, library: &Library) -> Result<InterpretResponse> {
+    let joints = match result.action.as_str() {
+        "move_to" => Some(solve_move_to(result, profile)?),
+        _ => None,
+    };
+    let sequence_name = match &result.sequence_name {
+        Some(name) => Some(library.get(name)?.name.clone()),
+        None => None,
+    };
+    let commands = match &result.commands {
+        Some(commands) => Some(
+            commands
+                .iter()
+                .map(|command| command_response(command, profile, library))
+                .collect::<Result<Vec<_>>>()?,
+        ),
+        None => None,
+    };
+
+    Ok(InterpretResponse {
+        action: result.action.clone(),
+        servo: result.servo,
+        angle: result.angle,
+        x: result.x,
+        y: result.y,
+        z: result.z,
+        joints,
+        sequence_name,
+        message: None,
+        ms: result.ms,
+        commands,
+    })
+}
+
+async fn move_to(state: web::Data<ServiceState>, req: web::Json<Point>) -> impl Responder {
+    let target = req.into_inner();
+
+    let result = web::block(move || {
+        let joints = kinematics::inverse(&CalibrationProfile::load()?.geometry, target)?;
+        state.with_driver(|driver| driver.move_coordinated(&joints.to_moves()))
+    })
+    .await;
+
+    move_response(result)
+}
+
+/// Turns a transcript into an action plan checked against the arm's current pose
+async fn plan(state: web::Data<ServiceState>, req: web::Json<PlanRequest>) -> impl Responder {
+    let interpreter = match Interpreter::new() {
+        Ok(interpreter) => interpreter,
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(ErrorResponse {
+                error: format!("Failed to initialize the interpreter: {:#}", e),
+            });
+        }
+    };
+
+    let profile = match CalibrationProfile::load() {
+        Ok(p) => p,
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(ErrorResponse {
+                error: format!("Failed to load calibration profile: {}", e),
+            });
+        }
+    };
+
+    let positions = state.positions(&profile);
+    let plan = match interpreter.generate_action_plan(&req.transcript, positions).await {
+        Ok(p) => p,
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(ErrorResponse {
+                error: format!("Planning failed: {}", e),
+            });
+        }
+    };
+
+    let checked = Library::load().and_then(|library| plan.validate(&profile, &library, positions));
+    match checked {
+        Ok(steps) => HttpResponse::Ok().json(PlanResponse { plan, steps }),
+        Err(e) => HttpResponse::UnprocessableEntity().json(ErrorResponse {
+            error: format!("Invalid plan: {:#}", e),
+        }),
+    }
+}
+
+/// Validates and runs a plan on the arm; `/stop` interrupts it
+async fn run_plan(state: web::Data<ServiceState>, req: web::Json<ActionPlan>) -> impl Responder {
+    execute_plan(state, req.into_inner()).await
+}
+
+/// Runs a command as `/interpret` returned it, multi-step ones included, through the plan executor
+async fn run_command(state: web::Data<ServiceState>, req: web::Json<CommandResult>) -> impl Responder {
+    let plan = match CalibrationProfile::load().and_then(|profile| req.to_plan(&profile)) {
+        Ok(plan) => plan,
+        Err(e) => {
+            return HttpResponse::BadRequest().json(ErrorResponse {
+                error: format!("Can't run that command: {:#}", e),
+            });
+        }
+    };
+    execute_plan(state, plan).await
+}
+
+async fn execute_plan(state: web::Data<ServiceState>, plan: ActionPlan) -> HttpResponse {
+    let result = web::block(move || {
+        let listener = state.stop.listen();
+        let sequences = Library::load()?;
+        state.with_driver(|driver| {
+            let steps = plan.validate(driver.profile(), &sequences, driver.positions())?;
+            crate::plan::execute(&steps, driver, &listener, |_, _| {})
+        })
+    })
+    .await;
+
+    move_response(result)
+}
+
+/// Every sequence in the library, so the GUI runs the same definitions as the CLI
+async fn list_sequences() -> impl Responder {
+    let loaded = Library::load().and_then(|library| {
+        let profile = CalibrationProfile::load()?;
+        Ok(library
+            .iter()
+            .map(|sequence| sequence_response(&library, sequence, &profile))
+            .collect::<Vec<_>>())
+    });
+
+    match loaded {
+        Ok(sequences) => HttpResponse::Ok().json(sequences),
+        Err(e) => HttpResponse::InternalServerError().json(ErrorResponse {
+            error: format!("Failed to load sequences: {:#}", e),
+        }),
+    }
+}
+
+async fn get_sequence(name: web::Path<String>) -> impl Responder {
+    let library = match Library::load() {
+        Ok(library) => library,
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(ErrorResponse {
+                error: format!("Failed to load sequences: {:#}", e),
+            });
+        }
+    };
+    let profile = match CalibrationProfile::load() {
+        Ok(p) => p,
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(ErrorResponse {
+                error: format!("Failed to load calibration profile: {}", e),
+            });
+        }
+    };
+
+    match library.get(&name) {
+        Ok(sequence) => HttpResponse::Ok().json(sequence_response(&library, sequence, &profile)),
+        Err(e) => HttpResponse::NotFound().json(ErrorResponse { error: e.to_string() }),
+    }
+}
+
+/// Runs a sequence on the arm; `/stop` interrupts it
+async fn run_sequence(state: web::Data<ServiceState>, name: web::Path<String>) -> impl Responder {
+    let plan = ActionPlan {
+        summary: None,
+        steps: vec![Step::Sequence { name: name.into_inner() }],
+    };
+    execute_plan(state, plan).await
+}
+
+fn sequence_response(library: &Library, sequence: &Sequence, profile: &CalibrationProfile) -> SequenceResponse {
+    let frames = library.expand(&sequence.name).and_then(|steps| {
+        let start = [driver::HOME_ANGLE; 4];
+        let recording = crate::plan::record(&steps, profile, start)?;
+        let timeline = crate::dryrun::analyze(&recording, &SafetyEnvelope::new(profile), start);
+
+        Ok(timeline
+            .moves
+            .iter()
+            .enumerate()
+            .map(|(i, m)| {
+                let next = timeline.moves.get(i + 1).map_or(timeline.duration, |next| next.at);
+                Frame {
+                    servo: m.servo,
+                    angle: m.to,
+                    delay: next.saturating_sub(m.at).as_millis() as u64,
+                }
+            })
+            .collect())
+    });
+
+    SequenceResponse {
+        sequence: sequence.clone(),
+        source: sequence.source.to_string(),
+        frames: frames.ok(),
+    }
+}
+
+/// Runs a Python script, streaming its output as server-sent events
+///
+/// Every line is an `output` event (`{"stream": "stdout", "line": "..."}`); the stream
+/// ends with a `done` event carrying the execution report, or an `error` event.
+async fn run_script(state: web::Data<ServiceState>, req: web::Json<ScriptRequest>) -> HttpResponse {
+    let profile = match CalibrationProfile::load() {
+        Ok(p) => p,
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(ErrorResponse {
+                error: format!("Failed to load calibration profile: {}", e),
+            });
+        }
+    };
+    let serial = match state.serial() {
+        Ok(serial) => serial.clone(),
+        Err(e) => {
+            return HttpResponse::ServiceUnavailable().json(ErrorResponse {
+                error: format!("No serial port: {}", e),
+            });
+        }
+    };
+
+    let serial_port = serial.port.clone().unwrap_or_default();
+
+    // Nobody is there to confirm a script with problems, so refuse it
+    let analysis = preflight::analyze(&req.script, &profile, &serial_port);
+    if analysis.errors() > 0 {
+        let problems: Vec<String> = analysis
+            .findings
+            .iter()
+            .filter(|f| f.severity == preflight::Severity::Error)
+            .map(|f| format!("line {}: {}", f.line, f.message))
+            .collect();
+        return HttpResponse::UnprocessableEntity().json(ErrorResponse {
+            error: format!("Script failed the pre-flight check: {}", problems.join("; ")),
+        });
+    }
+
+    let script = match tempfile::Builder::new().suffix(".py").tempfile() {
+        Ok(file) => file,
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(ErrorResponse {
+                error: format!("Failed to write script: {}", e),
+            });
+        }
+    };
+    if let Err(e) = std::fs::write(script.path(), &req.script) {
+        return HttpResponse::InternalServerError().json(ErrorResponse {
+            error: format!("Failed to write script: {}", e),
+        });
+    }
+
+    // The script opens the serial port itself, and keeps it until it ends
+    {
+        let mut driver = state.driver.lock().unwrap();
+        if state.script_running.swap(true, Ordering::SeqCst) {
+            return script_conflict(&ScriptRunning.into());
+        }
+        driver.take();
+    }
+    let (script_stopped, stopped) = mpsc::channel();
+    *state.script_stopped.lock().unwrap() = Some(stopped);
+
+    let (events, stream) = tokio::sync::mpsc::unbounded_channel();
+    actix_web::rt::spawn(async move {
+        let sandbox = SandboxConfig {
+            timeout: SCRIPT_TIMEOUT,
+            serial_port,
+            baud: serial.baud,
+            isolate: cfg!(target_os = "linux"),
+        };
+        let envelope = SafetyEnvelope::new(&profile);
+
+        let result = executor::run_script(script.path(), &envelope, &state.stop, &sandbox, |line| {
+            let _ = events.send(sse_event("output", line));
+        })
+        .await;
+
+        if let Some(stopped) = result.as_ref().err().and_then(|e| e.downcast_ref::<executor::ScriptStopped>()) {
+            let held = stopped
+                .held
+                .map(|angles| ServoId::ALL.map(|servo| profile.servo(servo).untrimmed(angles[servo.index()])));
+            let _ = script_stopped.send(held);
+        }
+        state.script_running.store(false, Ordering::SeqCst);
+
+        let _ = events.send(match result {
+            Ok(report) => sse_event("done", &report),
+            Err(e) => sse_event("error", &ErrorResponse {
+                error: format!("{:#}", e),
+            }),
+        });
+    });
+
+    let body = futures_util::stream::unfold(stream, |mut stream| async move {
+        let event = stream.recv().await?;
+        Some((Ok::<_, actix_web::Error>(event), stream))
+    });
+
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(("Cache-Control", "no-cache"))
+        .streaming(body)
+}
+
+fn sse_event(name: &str, data: &impl Serialize) -> web::Bytes {
+    let data = serde_json::to_string(data).unwrap_or_default();
+    web::Bytes::from(format!("event: {}\ndata: {}\n\n", name, data))
+}
+
+async fn move_pose(state: web::Data<ServiceState>, name: web::Path<String>) -> impl Responder {
+    let name = name.into_inner();
+    let result = web::block(move || state.with_driver(|driver| driver.move_pose(&name))).await;
+    move_response(result)
+}
+
+async fn arm_state(state: web::Data<ServiceState>) -> impl Responder {
+    match web::block(move || state.with_driver(|driver| driver.poll_state())).await {
+        Ok(Ok(arm)) => HttpResponse::Ok().json(arm),
+        Ok(Err(e)) if e.is::<ScriptRunning>() => script_conflict(&e),
+        Ok(Err(e)) => HttpResponse::ServiceUnavailable().json(ErrorResponse {
+            error: format!("Status query failed: {}", e),
+        }),
+        Err(e) => HttpResponse::InternalServerError().json(ErrorResponse {
+            error: format!("Status query failed: {}", e),
+        }),
+    }
+}
+
+async fn stop(state: web::Data<ServiceState>) -> impl Responder {
+    match web::block(move || state.emergency_stop()).await {
+        Ok(Ok(positions)) => HttpResponse::Ok().json(StopResponse {
+            stopped: true,
+            positions,
+        }),
+        Ok(Err(e)) => HttpResponse::ServiceUnavailable().json(ErrorResponse {
+            error: format!("Stop failed: {}", e),
+        }),
+        Err(e) => HttpResponse::InternalServerError().json(ErrorResponse {
+            error: format!("Stop failed: {}", e),
+        }),
+    }
+}
+
+async fn home(state: web::Data<ServiceState>) -> impl Responder {
+    let result = web::block(move || state.with_driver(|driver| driver.home())).await;
+    move_response(result)
+}
+
+async fn move_servo(state: web::Data<ServiceState>, req: web::Json<MoveRequest>) -> impl Responder {
+    let req = req.into_inner();
+
+    let result = web::block(move || {
+        state.with_driver(|driver| {
+            let duration = match req.duration_ms {
+                Some(ms) => Duration::from_millis(ms),
+                None => driver::calculate_duration(driver.position(req.servo), req.angle),
+            };
+            driver.move_servo(req.servo, req.angle, duration)
+        })
+    })
+    .await;
+
+    move_response(result)
+}
+
+async fn move_coordinated(
+    state: web::Data<ServiceState>,
+    req: web::Json<CoordinatedMoveRequest>,
+) -> impl Responder {
+    let moves: Vec<(ServoId, u8)> = req.moves.iter().map(|m| (m.servo, m.angle)).collect();
+
+    let result = web::block(move || state.with_driver(|driver| driver.move_coordinated(&moves))).await;
+
+    move_response(result)
+}
+
+/// The sequence being taught, or 404 when teach mode isn't active
+async fn teach_status(state: web::Data<ServiceState>) -> impl Responder {
+    match state.teach.lock().unwrap().as_ref() {
+        Some(session) => HttpResponse::Ok().json(TeachResponse::from(session)),
+        None => no_teach_session(),
+    }
+}
+
+/// Starts recording a sequence; an unfinished one has to be saved or cancelled first
+async fn teach_start(state: web::Data<ServiceState>, req: web::Json<TeachStartRequest>) -> impl Responder {
+    let req = req.into_inner();
+    let mut teach = state.teach.lock().unwrap();
+    if let Some(session) = teach.as_ref() {
+        return HttpResponse::Conflict().json(ErrorResponse {
+            error: format!("Already teaching {}; save it or DELETE /teach first", session.name),
+        });
+    }
+
+    match TeachSession::new(&req.name, req.description) {
+        Ok(session) => {
+            let response = TeachResponse::from(&session);
+            *teach = Some(session);
+            HttpResponse::Ok().json(response)
+        }
+        Err(e) => HttpResponse::BadRequest().json(ErrorResponse { error: e.to_string() }),
+    }
+}
+
+/// Nudges one joint, clamped to its calibrated limits
+async fn teach_jog(state: web::Data<ServiceState>, req: web::Json<JogRequest>) -> impl Responder {
+    let JogRequest { servo, delta } = req.into_inner();
+    let max_step = *STEP_SIZES.iter().max().unwrap_or(&1);
+    if delta.unsigned_abs() > max_step as u16 {
+        return HttpResponse::BadRequest().json(ErrorResponse {
+            error: format!("Jog by at most {}° at a time", max_step),
+        });
+    }
+
+    match web::block(move || state.with_driver(|driver| teach::jog(driver, servo, delta))).await {
+        Ok(Ok(angle)) => HttpResponse::Ok().json(JogResponse { servo, angle }),
+        Ok(Err(e)) if e.is::<ScriptRunning>() => script_conflict(&e),
+        Ok(Err(e)) if e.is::<Violation>() => HttpResponse::UnprocessableEntity().json(ErrorResponse {
+            error: format!("Rejected by safety envelope: {:#}", e),
+        }),
+        Ok(Err(e)) => HttpResponse::BadRequest().json(ErrorResponse {
+            error: format!("Jog failed: {:#}", e),
+        }),
+        Err(e) => HttpResponse::InternalServerError().json(ErrorResponse {
+            error: format!("Jog failed: {}", e),
+        }),
+    }
+}
+
+/// Captures the arm's current pose as the next waypoint
+async fn teach_capture(state: web::Data<ServiceState>) -> impl Responder {
+    let result = web::block(move || -> Result<Option<TeachResponse>> {
+        let mut teach = state.teach.lock().unwrap();
+        let Some(session) = teach.as_mut() else {
+            return Ok(None);
+        };
+        let positions = state.with_driver(|driver| Ok(driver.positions()))?;
+        session.capture(positions);
+        Ok(Some(TeachResponse::from(&*session)))
+    })
+    .await;
+
+    match result {
+        Ok(Ok(Some(response))) => HttpResponse::Ok().json(response),
+        Ok(Ok(None)) => no_teach_session(),
+        Ok(Err(e)) if e.is::<ScriptRunning>() => script_conflict(&e),
+        Ok(Err(e)) => HttpResponse::ServiceUnavailable().json(ErrorResponse {
+            error: format!("Failed to read the arm's position: {:#}", e),
+        }),
+        Err(e) => HttpResponse::InternalServerError().json(ErrorResponse { error: e.to_string() }),
+    }
+}
+
+async fn teach_undo(state: web::Data<ServiceState>) -> impl Responder {
+    match state.teach.lock().unwrap().as_mut() {
+        Some(session) => {
+            session.undo();
+            HttpResponse::Ok().json(TeachResponse::from(&*session))
+        }
+        None => no_teach_session(),
+    }
+}
+
+/// Validates the waypoints and saves them to the sequence library, ending teach mode
+async fn teach_save(state: web::Data<ServiceState>) -> impl Responder {
+    let result = web::block(move || -> Result<Option<TeachSaveResponse>> {
+        let mut teach = state.teach.lock().unwrap();
+        let Some(session) = teach.as_ref() else {
+            return Ok(None);
+        };
+
+        let profile = CalibrationProfile::load()?;
+        let path = session.save(&profile)?;
+        let response = TeachSaveResponse {
+            name: session.name.clone(),
+            path: path.display().to_string(),
+            steps: session.to_sequence()?.steps,
+        };
+        *teach = None;
+        Ok(Some(response))
+    })
+    .await;
+
+    match result {
+        Ok(Ok(Some(response))) => HttpResponse::Ok().json(response),
+        Ok(Ok(None)) => no_teach_session(),
+        Ok(Err(e)) => HttpResponse::UnprocessableEntity().json(ErrorResponse {
+            error: format!("Failed to save sequence: {:#}", e),
+        }),
+        Err(e) => HttpResponse::InternalServerError().json(ErrorResponse { error: e.to_string() }),
+    }
+}
+
+/// Discards the sequence being taught
+async fn teach_cancel(state: web::Data<ServiceState>) -> impl Responder {
+    match state.teach.lock().unwrap().take() {
+        Some(session) => HttpResponse::Ok().json(TeachResponse::from(&session)),
+        None => no_teach_session(),
+    }
+}
+
+fn no_teach_session() -> HttpResponse {
+    HttpResponse::NotFound().json(ErrorResponse {
+        error: "Not in teach mode; POST /teach/start first".to_string(),
+    })
+}
+
+fn script_conflict(e: &anyhow::Error) -> HttpResponse {
+    HttpResponse::Conflict().json(ErrorResponse { error: e.to_string() })
+}
+
+fn move_response(
+    result: std::result::Result<Result<Duration>, actix_web::error::BlockingError>,
+) -> HttpResponse {
+    match result {
+        Ok(Ok(duration)) => HttpResponse::Ok().json(MoveResponse {
+            duration_ms: duration.as_millis() as u64,
+        }),
+        Ok(Err(e)) if e.is::<ScriptRunning>() => script_conflict(&e),
+        Ok(Err(e)) if e.is::<Violation>() => HttpResponse::UnprocessableEntity().json(ErrorResponse {
+            error: format!("Rejected by safety envelope: {:#}", e),
+        }),
+        Ok(Err(e)) => HttpResponse::BadRequest().json(ErrorResponse {
+            error: format!("Move failed: {:#}", e),
+        }),
+        Err(e) => HttpResponse::InternalServerError().json(ErrorResponse {
+            error: format!("Move failed: {}", e),
+        }),
+    }
+}
+
+pub async fn run_server(port: u16, serial: SerialConfig) -> Result<()> {
     println!("Starting Abel Voice Service on http://localhost:{}", port);
     println!("Press Ctrl+C to stop");
+    match stt::from_env() {
+        Ok(speech) => println!("Speech-to-text: {}", speech.name()),
+        Err(e) => println!("Speech-to-text unavailable: {:#}", e),
+    }
+    match Interpreter::new() {
+        Ok(interpreter) => println!("Interpreter: {}", interpreter.backend_name()),
+        Err(e) => println!("Interpreter unavailable: {:#}", e),
+    }
+
+    // Discovering the port per request could prompt on the server's terminal mid-request
+    let serial = match serial.resolved() {
+        Ok(serial) => {
+            println!("Serial port: {}", serial.port.as_deref().unwrap_or_default());
+            Ok(serial)
+        }
+        Err(e) => {
+            println!("Serial port unavailable: {:#}", e);
+            Err(format!("{:#}", e))
+        }
+    };
+
+    let arm = web::Data::from(Arc::new(ServiceState {
+        serial,
+        driver: Mutex::new(None),
+        stop: StopSignal::default(),
+        teach: Mutex::new(None),
+        script_stopped: Mutex::new(None),
+        script_running: AtomicBool::new(false),
+    }));
 
-    HttpServer::new(|| {
+    HttpServer::new(move || {
         let cors = Cors::default()
             .allow_any_origin()
             .allow_any_method()
@@ -143,9 +1046,31 @@ pub async fn run_server(port: u16) -> Result<()> {
 
         App::new()
             .wrap(cors)
+            .app_data(arm.clone())
             .route("/health", web::get().to(health))
             .route("/transcribe", web::post().to(transcribe))
             .route("/interpret", web::post().to(interpret))
+            .route("/interpret/run", web::post().to(run_command))
+            .route("/plan", web::post().to(plan))
+            .route("/plan/run", web::post().to(run_plan))
+            .route("/script/run", web::post().to(run_script))
+            .route("/move", web::post().to(move_servo))
+            .route("/move/coordinated", web::post().to(move_coordinated))
+            .route("/move_to", web::post().to(move_to))
+            .route("/pose/{name}", web::post().to(move_pose))
+            .route("/home", web::post().to(home))
+            .route("/stop", web::post().to(stop))
+            .route("/state", web::get().to(arm_state))
+            .route("/sequences", web::get().to(list_sequences))
+            .route("/sequences/{name}", web::get().to(get_sequence))
+            .route("/sequences/{name}/run", web::post().to(run_sequence))
+            .route("/teach", web::get().to(teach_status))
+            .route("/teach", web::delete().to(teach_cancel))
+            .route("/teach/start", web::post().to(teach_start))
+            .route("/teach/jog", web::post().to(teach_jog))
+            .route("/teach/capture", web::post().to(teach_capture))
+            .route("/teach/undo", web::post().to(teach_undo))
+            .route("/teach/save", web::post().to(teach_save))
     })
     .bind(("127.0.0.1", port))?
     .run()
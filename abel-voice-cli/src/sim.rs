@@ -0,0 +1,549 @@
+use anyhow::{Context, Result};
+use colored::Colorize;
+use std::collections::VecDeque;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::trajectory::{self, Quintic};
+
+const UPDATE_INTERVAL: u64 = 5;
+
+/// `SEGMENT_TOLERANCE`: how far past the limits a segment may go
+const SEGMENT_TOLERANCE: f32 = 1.1;
+
+/// `VELOCITY_MATCH`: allowed jump between one segment's end and the next one's start, deg/s
+const VELOCITY_MATCH: f32 = 1.0;
+
+/// Mirror of the firmware's `Segment`
+#[derive(Clone, Copy, Default)]
+struct Segment {
+    target_angle: f32,
+    duration: u64,
+    start_velocity: f32,
+    end_velocity: f32,
+}
+
+/// Mirror of the firmware's `MotionProfile`
+#[derive(Clone, Default)]
+struct MotionProfile {
+    current_angle: f32,
+    target_angle: f32,
+    start_angle: f32,
+    current_velocity: f32,
+    current_acceleration: f32,
+    start_time: u64,
+    duration: u64,
+    moving: bool,
+    end_velocity: f32,
+    queue: VecDeque<Segment>,
+    quintic: Quintic,
+}
+
+impl MotionProfile {
+    /// `startSegment`
+    fn start_segment(&mut self, segment: Segment, start_time: u64) {
+        self.start_angle = self.target_angle;
+        self.target_angle = segment.target_angle;
+        self.start_time = start_time;
+        self.duration = segment.duration;
+        self.end_velocity = segment.end_velocity;
+        self.quintic = Quintic::with_velocities(
+            self.start_angle,
+            self.target_angle,
+            segment.start_velocity,
+            segment.end_velocity,
+            segment.duration as f32 / 1000.0,
+        );
+        self.moving = true;
+    }
+
+    /// `finalAngle`
+    fn final_angle(&self) -> f32 {
+        self.queue.back().map_or(self.target_angle, |segment| segment.target_angle)
+    }
+}
+
+/// Arduino `String::toInt()`: leading integer, 0 when there is none
+fn to_int(s: &str) -> i64 {
+    let s = s.trim_start();
+    let (sign, digits) = match s.as_bytes().first() {
+        Some(b'-') => (-1, &s[1..]),
+        Some(b'+') => (1, &s[1..]),
+        _ => (1, s),
+    };
+
+    let end = digits.find(|c: char| !c.is_ascii_digit()).unwrap_or(digits.len());
+    sign * digits[..end].parse::<i64>().unwrap_or(0)
+}
+
+/// Arduino `String::toFloat()`: leading decimal number, 0 when there is none
+fn to_float(s: &str) -> f32 {
+    let s = s.trim_start();
+    let end = s
+        .char_indices()
+        .find(|&(i, c)| !(c.is_ascii_digit() || c == '.' || (i == 0 && (c == '-' || c == '+'))))
+        .map_or(s.len(), |(i, _)| i);
+    s[..end].parse().unwrap_or(0.0)
+}
+
+/// Arduino `String::substring()`, which swaps reversed bounds and clamps to the length
+fn substring(s: &str, left: usize, right: usize) -> &str {
+    let (left, right) = if left > right { (right, left) } else { (left, right) };
+    if left >= s.len() {
+        return "";
+    }
+    &s[left..right.min(s.len())]
+}
+
+/// Software model of the ESP32 running Abel_Arm_Firmware.ino
+pub struct VirtualArm {
+    profiles: [MotionProfile; 4],
+    written: [i32; 4],
+    boot: Instant,
+    command_buffer: String,
+}
+
+impl VirtualArm {
+    /// Boots the arm and returns it together with the lines `setup()` prints
+    pub fn boot() -> (Self, Vec<String>) {
+        let mut arm = Self {
+            profiles: Default::default(),
+            written: [90; 4],
+            boot: Instant::now(),
+            command_buffer: String::new(),
+        };
+
+        for profile in arm.profiles.iter_mut() {
+            profile.current_angle = 90.0;
+            profile.target_angle = 90.0;
+        }
+
+        let banner = [
+            "Abel Arm Firmware v4.3",
+            "Quintic Polynomial Trajectory Planning",
+            "C2 Continuous Motion Control",
+            "Ready.",
+            "Command: #<servo>M<angle>T<duration>",
+            "Segment: #<servo>Q<angle>T<duration>V<v0>,<vf>",
+            "Stop: !",
+            "Status: ?",
+            "Constraints: v_max=120deg/s, a_max=200deg/s^2",
+        ];
+
+        (arm, banner.iter().map(|s| s.to_string()).collect())
+    }
+
+    fn millis(&self) -> u64 {
+        self.boot.elapsed().as_millis() as u64
+    }
+
+    /// Angles last written to the servos
+    pub fn servo_angles(&self) -> [i32; 4] {
+        self.written
+    }
+
+    /// Feeds received bytes through the firmware's line buffer, returning its output
+    pub fn receive(&mut self, bytes: &[u8]) -> Vec<String> {
+        let mut output = Vec::new();
+
+        for &b in bytes {
+            let c = b as char;
+            if c == '\n' || c == '\r' {
+                if !self.command_buffer.is_empty() {
+                    let cmd = std::mem::take(&mut self.command_buffer);
+                    output.extend(self.process_command(&cmd));
+                }
+            } else {
+                self.command_buffer.push(c);
+            }
+        }
+
+        output
+    }
+
+    /// Advances every moving servo along its trajectory, returning any warnings
+    pub fn update_motion(&mut self) -> Vec<String> {
+        let now = self.millis();
+        let mut output = Vec::new();
+
+        for i in 0..4 {
+            let profile = &mut self.profiles[i];
+            if !profile.moving {
+                continue;
+            }
+
+            let mut elapsed = now - profile.start_time;
+            if elapsed >= profile.duration {
+                if let Some(next) = profile.queue.pop_front() {
+                    let start = profile.start_time + profile.duration;
+                    profile.start_segment(next, start);
+                    elapsed = now - profile.start_time;
+                }
+            }
+            let t = elapsed as f32 / 1000.0;
+
+            if elapsed >= profile.duration {
+                if profile.end_velocity != 0.0 {
+                    output.push(format!(
+                        "WARN: Servo {} segment queue ran dry at {:.1}deg/s",
+                        i, profile.end_velocity
+                    ));
+                }
+                profile.end_velocity = 0.0;
+                profile.current_angle = profile.target_angle;
+                profile.current_velocity = 0.0;
+                profile.current_acceleration = 0.0;
+                profile.moving = false;
+            } else {
+                profile.current_angle = profile.quintic.position(t);
+                profile.current_velocity = profile.quintic.velocity(t);
+                profile.current_acceleration = profile.quintic.acceleration(t);
+            }
+
+            self.written[i] = profile.current_angle.round() as i32;
+        }
+
+        output
+    }
+
+    /// `stopAll`: holds every servo at its current interpolated angle
+    fn stop_all(&mut self) -> Vec<String> {
+        for (profile, written) in self.profiles.iter_mut().zip(self.written.iter_mut()) {
+            profile.queue.clear();
+            profile.end_velocity = 0.0;
+            profile.target_angle = profile.current_angle;
+            profile.current_velocity = 0.0;
+            profile.current_acceleration = 0.0;
+            profile.moving = false;
+            *written = profile.current_angle.round() as i32;
+        }
+
+        let angles: Vec<String> = self
+            .profiles
+            .iter()
+            .map(|p| format!("{:.1}", p.current_angle))
+            .collect();
+        vec![format!("STOPPED: {}", angles.join(" "))]
+    }
+
+    /// `printStatus`: one `STATUS` line per servo
+    fn status(&self) -> Vec<String> {
+        self.profiles
+            .iter()
+            .enumerate()
+            .map(|(i, p)| {
+                format!(
+                    "STATUS {} {:.1} {:.1} {:.1} {}",
+                    i,
+                    p.current_angle,
+                    p.final_angle(),
+                    p.current_velocity,
+                    p.moving as u8
+                )
+            })
+            .collect()
+    }
+
+    fn process_command(&mut self, cmd: &str) -> Vec<String> {
+        if cmd == "!" {
+            return self.stop_all();
+        }
+
+        if cmd == "?" {
+            return self.status();
+        }
+
+        if cmd.len() < 4 || !cmd.starts_with('#') {
+            return vec!["ERR: Invalid format".to_string()];
+        }
+
+        if cmd.contains('Q') {
+            return self.process_segment(cmd);
+        }
+
+        let (m_index, t_index) = match (cmd.find('M'), cmd.find('T')) {
+            (Some(m), Some(t)) => (m, t),
+            _ => return vec!["ERR: Invalid command format".to_string()],
+        };
+
+        let servo_num = to_int(substring(cmd, 1, m_index)) as i32;
+        let target_angle = to_int(substring(cmd, m_index + 1, t_index)) as i32;
+        let mut duration = to_int(substring(cmd, t_index + 1, cmd.len())) as u64;
+
+        if !(0..=3).contains(&servo_num) {
+            return vec!["ERR: Invalid servo (0-3)".to_string()];
+        }
+
+        if !(0..=180).contains(&target_angle) {
+            return vec!["ERR: Angle out of range (0-180)".to_string()];
+        }
+
+        if !(100..=10000).contains(&duration) {
+            return vec!["ERR: Duration out of range (100-10000ms)".to_string()];
+        }
+
+        let mut output = Vec::new();
+        let now = self.millis();
+        let profile = &mut self.profiles[servo_num as usize];
+
+        let delta_angle = (target_angle as f32 - profile.current_angle).abs();
+
+        let optimal_duration = trajectory::optimal_duration(delta_angle).as_millis() as u64;
+        if duration < optimal_duration {
+            output.push(format!(
+                "WARN: Duration adjusted {}ms -> {}ms (constraint violation)",
+                duration, optimal_duration
+            ));
+            duration = optimal_duration;
+        }
+
+        profile.queue.clear();
+        profile.end_velocity = 0.0;
+
+        profile.start_angle = profile.current_angle;
+        profile.target_angle = target_angle as f32;
+        profile.start_time = now;
+        profile.duration = duration;
+
+        let t = duration as f32 / 1000.0;
+        profile.quintic = Quintic::point_to_point(profile.start_angle, profile.target_angle, t);
+
+        profile.moving = true;
+
+        output.push(format!(
+            "QUINTIC: Servo {} {:.1}deg -> {}deg over {}ms (delta={:.1}deg)",
+            servo_num, profile.start_angle, target_angle, duration, delta_angle
+        ));
+
+        output
+    }
+
+    /// `processSegment`: `#<servo>Q<angle>T<duration>V<v0>,<vf>`
+    fn process_segment(&mut self, cmd: &str) -> Vec<String> {
+        let q_index = cmd.find('Q').unwrap_or(0);
+        let (t_index, v_index) = match (cmd.find('T'), cmd.find('V')) {
+            (Some(t), Some(v)) if q_index < t && t < v => (t, v),
+            _ => return vec!["ERR: Invalid segment format".to_string()],
+        };
+        let Some(comma_index) = cmd[v_index..].find(',').map(|i| i + v_index) else {
+            return vec!["ERR: Invalid segment format".to_string()];
+        };
+
+        let servo_num = to_int(substring(cmd, 1, q_index)) as i32;
+        let target_angle = to_int(substring(cmd, q_index + 1, t_index)) as i32;
+        let duration = to_int(substring(cmd, t_index + 1, v_index)) as u64;
+        let start_velocity = to_float(substring(cmd, v_index + 1, comma_index));
+        let end_velocity = to_float(substring(cmd, comma_index + 1, cmd.len()));
+
+        if !(0..=3).contains(&servo_num) {
+            return vec!["ERR: Invalid servo (0-3)".to_string()];
+        }
+
+        if !(0..=180).contains(&target_angle) {
+            return vec!["ERR: Angle out of range (0-180)".to_string()];
+        }
+
+        if !(100..=10000).contains(&duration) {
+            return vec!["ERR: Duration out of range (100-10000ms)".to_string()];
+        }
+
+        let limits = trajectory::FIRMWARE_CONSTRAINTS;
+        if start_velocity.abs() > limits.max_velocity || end_velocity.abs() > limits.max_velocity {
+            return vec!["ERR: Boundary velocity exceeds v_max".to_string()];
+        }
+
+        let now = self.millis();
+        let profile = &mut self.profiles[servo_num as usize];
+        if profile.queue.len() >= trajectory::SEGMENT_QUEUE {
+            return vec!["ERR: Segment queue full".to_string()];
+        }
+
+        let idle = !profile.moving && profile.queue.is_empty();
+        let from_angle = if idle { profile.current_angle } else { profile.final_angle() };
+        let from_velocity = match profile.queue.back() {
+            Some(last) => last.end_velocity,
+            None if profile.moving => profile.end_velocity,
+            None => 0.0,
+        };
+
+        if (start_velocity - from_velocity).abs() > VELOCITY_MATCH {
+            return vec![format!(
+                "ERR: Start velocity {:.1}deg/s does not match {:.1}deg/s",
+                start_velocity, from_velocity
+            )];
+        }
+
+        let t = duration as f32 / 1000.0;
+        let check = Quintic::with_velocities(from_angle, target_angle as f32, start_velocity, end_velocity, t);
+        let (peak_velocity, peak_acceleration) = (0..=20).map(|k| t * k as f32 / 20.0).fold((0.0f32, 0.0f32), |(v, a), t| {
+            (v.max(check.velocity(t).abs()), a.max(check.acceleration(t).abs()))
+        });
+        if peak_velocity > limits.max_velocity * SEGMENT_TOLERANCE
+            || peak_acceleration > limits.max_acceleration * SEGMENT_TOLERANCE
+        {
+            return vec![format!(
+                "ERR: Segment exceeds constraints (v={:.1}deg/s, a={:.1}deg/s^2)",
+                peak_velocity, peak_acceleration
+            )];
+        }
+
+        let segment = Segment {
+            target_angle: target_angle as f32,
+            duration,
+            start_velocity,
+            end_velocity,
+        };
+        if idle {
+            profile.target_angle = profile.current_angle;
+            profile.start_segment(segment, now);
+        } else {
+            profile.queue.push_back(segment);
+        }
+
+        vec![format!(
+            "SEGMENT: Servo {} {:.1}deg -> {}deg over {}ms (v={:.1}->{:.1}deg/s, queued={})",
+            servo_num,
+            from_angle,
+            target_angle,
+            duration,
+            start_velocity,
+            end_velocity,
+            profile.queue.len()
+        )]
+    }
+}
+
+/// Removes the convenience symlink when the simulator exits
+struct Link(PathBuf);
+
+impl Drop for Link {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+/// Runs the virtual arm on a pseudo-terminal until the process is killed
+#[cfg(unix)]
+pub fn run_simulator(link: Option<&Path>) -> Result<()> {
+    use serialport::{SerialPort, TTYPort};
+
+    let (mut master, slave) = TTYPort::pair().context("Failed to create pseudo-terminal")?;
+    let slave_path = slave.name().context("Pseudo-terminal has no device path")?;
+    master.set_timeout(Duration::from_millis(UPDATE_INTERVAL))?;
+
+    // Let the client own the slave side so we can tell when it connects and disconnects
+    drop(slave);
+
+    let _link = match link {
+        Some(path) => {
+            let _ = std::fs::remove_file(path);
+            std::os::unix::fs::symlink(&slave_path, path)
+                .with_context(|| format!("Failed to create symlink {}", path.display()))?;
+            Some(Link(path.to_path_buf()))
+        }
+        None => None,
+    };
+
+    let device = link.map(|p| p.display().to_string()).unwrap_or(slave_path.clone());
+
+    println!("{}", "🦾 Virtual Abel Arm".bright_cyan().bold());
+    println!("{}: {}", "Serial device".bright_white(), device.bright_yellow());
+    println!(
+        "{}",
+        format!("Try: abel-voice --port {} smooth", device).dimmed()
+    );
+    println!("{}", "Press Ctrl+C to stop".dimmed());
+    println!();
+
+    let (mut arm, _) = VirtualArm::boot();
+    let mut connected = false;
+    let mut buf = [0u8; 256];
+    let mut last_update = Instant::now();
+    let mut was_moving = [false; 4];
+
+    loop {
+        match master.read(&mut buf) {
+            Ok(n) => {
+                if !connected {
+                    connected = true;
+                    arm = on_connect(&mut master)?;
+                }
+
+                for line in String::from_utf8_lossy(&buf[..n]).split(['\n', '\r']) {
+                    if !line.is_empty() {
+                        println!("{} {}", "→".cyan(), line);
+                    }
+                }
+
+                for line in arm.receive(&buf[..n]) {
+                    reply(&mut master, &line)?;
+                }
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::TimedOut => {
+                if !connected {
+                    connected = true;
+                    arm = on_connect(&mut master)?;
+                }
+            }
+            // No client has the slave open
+            Err(_) => {
+                if connected {
+                    connected = false;
+                    println!("{}", "Client disconnected".dimmed());
+                }
+                std::thread::sleep(Duration::from_millis(UPDATE_INTERVAL));
+            }
+        }
+
+        if last_update.elapsed() >= Duration::from_millis(UPDATE_INTERVAL) {
+            last_update = Instant::now();
+            for line in arm.update_motion() {
+                reply(&mut master, &line)?;
+            }
+
+            for (i, was) in was_moving.iter_mut().enumerate() {
+                let moving = arm.profiles[i].moving;
+                if *was && !moving {
+                    println!(
+                        "{} Servo {} at {}°",
+                        "✓".green(),
+                        i,
+                        arm.servo_angles()[i]
+                    );
+                }
+                *was = moving;
+            }
+        }
+    }
+}
+
+#[cfg(not(unix))]
+pub fn run_simulator(_link: Option<&Path>) -> Result<()> {
+    anyhow::bail!("The simulator needs a Unix pseudo-terminal")
+}
+
+/// Opening the port resets a real ESP32, so reboot and print the banner again
+#[cfg(unix)]
+fn on_connect(master: &mut serialport::TTYPort) -> Result<VirtualArm> {
+    println!("{}", "Client connected, rebooting".dimmed());
+
+    // setup() waits a second before printing anything
+    std::thread::sleep(Duration::from_millis(1000));
+
+    let (arm, banner) = VirtualArm::boot();
+    for line in banner {
+        reply(master, &line)?;
+    }
+
+    Ok(arm)
+}
+
+#[cfg(unix)]
+fn reply(master: &mut serialport::TTYPort, line: &str) -> Result<()> {
+    println!("{} {}", "←".magenta(), line.dimmed());
+
+    // A client that hung up mid-reply isn't an error for the simulator
+    let _ = master.write_all(format!("{}\r\n", line).as_bytes());
+    Ok(())
+}
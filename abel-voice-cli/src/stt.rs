@@ -0,0 +1,76 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use std::path::PathBuf;
+
+use crate::whisper::WhisperClient;
+use crate::whisper_cpp::LocalWhisper;
+
+/// Turns recorded speech (WAV bytes from `audio::record_audio`, or an upload) into text
+#[async_trait]
+pub trait SpeechToText: Send + Sync {
+    /// Short description for logs, e.g. "OpenAI whisper-1"
+    fn name(&self) -> String;
+
+    async fn transcribe(&self, audio_data: &[u8]) -> Result<String>;
+}
+
+/// Which speech-to-text implementation to use, from `ABEL_STT`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Backend {
+    /// OpenAI's hosted Whisper (`OPENAI_API_KEY`)
+    OpenAi,
+    /// A self-hosted server speaking the OpenAI transcription API (`ABEL_STT_URL`)
+    OpenAiCompatible,
+    /// whisper.cpp run on this machine with a model from disk (`ABEL_WHISPER_MODEL`)
+    Local,
+}
+
+impl std::str::FromStr for Backend {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "" | "openai" => Ok(Backend::OpenAi),
+            "openai-compatible" | "compatible" => Ok(Backend::OpenAiCompatible),
+            "local" | "whisper.cpp" | "whisper-cpp" => Ok(Backend::Local),
+            other => anyhow::bail!(
+                "Unknown speech-to-text backend '{}' (use openai, openai-compatible or local)",
+                other
+            ),
+        }
+    }
+}
+
+/// The backend configured in the environment (or `.env`)
+///
+/// - `ABEL_STT`: `openai` (default), `openai-compatible` or `local`
+/// - `ABEL_STT_MODEL`: model name sent to the API, default `whisper-1`
+/// - `ABEL_STT_URL`, `ABEL_STT_API_KEY`: base URL and optional key of a compatible server
+/// - `ABEL_WHISPER_MODEL`, `ABEL_WHISPER_CPP`: ggml model file and whisper.cpp binary
+pub fn from_env() -> Result<Box<dyn SpeechToText>> {
+    let backend: Backend = std::env::var("ABEL_STT").unwrap_or_default().parse()?;
+    let model = std::env::var("ABEL_STT_MODEL")
+        .ok()
+        .filter(|m| !m.trim().is_empty())
+        .unwrap_or_else(|| "whisper-1".to_string());
+
+    Ok(match backend {
+        Backend::OpenAi => Box::new(WhisperClient::new()?.with_model(model)),
+        Backend::OpenAiCompatible => {
+            let url = std::env::var("ABEL_STT_URL")
+                .context("ABEL_STT_URL must be set to the server's base URL (e.g. http://lab-server:8000/v1)")?;
+            let api_key = std::env::var("ABEL_STT_API_KEY").ok().filter(|k| !k.trim().is_empty());
+            Box::new(WhisperClient::compatible(&url, api_key)?.with_model(model))
+        }
+        Backend::Local => {
+            let model = std::env::var_os("ABEL_WHISPER_MODEL")
+                .map(PathBuf::from)
+                .context("ABEL_WHISPER_MODEL must be set to a whisper.cpp ggml model file")?;
+            let binary = std::env::var("ABEL_WHISPER_CPP")
+                .ok()
+                .filter(|b| !b.trim().is_empty())
+                .unwrap_or_else(|| "whisper-cli".to_string());
+            Box::new(LocalWhisper::new(model, binary)?)
+        }
+    })
+}
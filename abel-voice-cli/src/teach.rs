@@ -0,0 +1,320 @@
+use anyhow::Result;
+use colored::Colorize;
+use console::{Key, Term};
+use dialoguer::{Confirm, Input};
+use serde::Serialize;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use crate::driver::{self, ArmDriver, ServoId, MAX_DURATION, MIN_DURATION};
+use crate::plan::{ActionPlan, JointTarget, Step};
+use crate::profile::CalibrationProfile;
+use crate::sequence::{self, Library, Sequence};
+use crate::serial::SerialConfig;
+use crate::wizard::{JOG_DURATION, STEP_SIZES};
+
+/// Longest pause kept when a waypoint is captured without moving
+const MAX_PAUSE: Duration = Duration::from_secs(10);
+
+/// A captured arm pose and how long after the previous one it was captured
+#[derive(Serialize, Clone, Copy, Debug)]
+pub struct Waypoint {
+    pub positions: [u8; 4],
+    pub elapsed_ms: u64,
+}
+
+/// Waypoints being recorded into a new sequence
+#[derive(Debug)]
+pub struct TeachSession {
+    pub name: String,
+    pub description: Option<String>,
+    waypoints: Vec<Waypoint>,
+    /// When the last waypoint was captured (or undone); timing starts from here
+    last: Instant,
+}
+
+impl TeachSession {
+    pub fn new(name: &str, description: Option<String>) -> Result<Self> {
+        Ok(Self {
+            name: sequence::normalize_name(name)?,
+            description: description.filter(|d| !d.trim().is_empty()),
+            waypoints: Vec::new(),
+            last: Instant::now(),
+        })
+    }
+
+    pub fn waypoints(&self) -> &[Waypoint] {
+        &self.waypoints
+    }
+
+    /// Records the arm's current pose with the time since the last capture
+    pub fn capture(&mut self, positions: [u8; 4]) -> &Waypoint {
+        let now = Instant::now();
+        let elapsed = if self.waypoints.is_empty() {
+            Duration::ZERO
+        } else {
+            now - self.last
+        };
+        self.last = now;
+
+        self.waypoints.push(Waypoint {
+            positions,
+            elapsed_ms: elapsed.as_millis() as u64,
+        });
+        self.waypoints.last().unwrap()
+    }
+
+    /// Drops the last waypoint; the next capture is timed from now
+    pub fn undo(&mut self) -> Option<Waypoint> {
+        self.last = Instant::now();
+        self.waypoints.pop()
+    }
+
+    /// Turns the waypoints into sequence steps
+    ///
+    /// The first waypoint moves every joint into place at the driver's default speed.
+    /// After that each waypoint moves only the joints that changed, taking as long as
+    /// the user took to get there; a waypoint where nothing moved becomes a wait.
+    pub fn to_sequence(&self) -> Result<Sequence> {
+        let Some(first) = self.waypoints.first() else {
+            anyhow::bail!("No waypoints captured");
+        };
+
+        let targets = |positions: [u8; 4], previous: Option<[u8; 4]>| -> Vec<JointTarget> {
+            ServoId::ALL
+                .into_iter()
+                .filter(|servo| previous.is_none_or(|p| p[servo.index()] != positions[servo.index()]))
+                .map(|servo| JointTarget {
+                    servo,
+                    angle: positions[servo.index()],
+                })
+                .collect()
+        };
+
+        let mut steps = vec![Step::CoordinatedMove {
+            moves: targets(first.positions, None),
+            duration_ms: None,
+        }];
+        for pair in self.waypoints.windows(2) {
+            let (previous, waypoint) = (pair[0], pair[1]);
+            let moves = targets(waypoint.positions, Some(previous.positions));
+
+            if moves.is_empty() {
+                steps.push(Step::Wait {
+                    ms: waypoint.elapsed_ms.min(MAX_PAUSE.as_millis() as u64),
+                });
+            } else {
+                let duration_ms = waypoint
+                    .elapsed_ms
+                    .clamp(MIN_DURATION.as_millis() as u64, MAX_DURATION.as_millis() as u64);
+                steps.push(Step::CoordinatedMove {
+                    moves,
+                    duration_ms: Some(duration_ms),
+                });
+            }
+        }
+
+        Ok(Sequence {
+            name: self.name.clone(),
+            description: self.description.clone(),
+            steps,
+            source: Default::default(),
+        })
+    }
+
+    /// Checks the sequence against the safety envelope and writes it to the library
+    ///
+    /// A user file with the same name is overwritten; a built-in one is overridden.
+    pub fn save(&self, profile: &CalibrationProfile) -> Result<PathBuf> {
+        let sequence = self.to_sequence()?;
+        let library = Library::load()?;
+
+        let plan = ActionPlan {
+            summary: sequence.description.clone(),
+            steps: sequence.steps.clone(),
+        };
+        plan.validate(profile, &library, [driver::HOME_ANGLE; 4])?;
+
+        let path = Library::path_for(library.get(&sequence.name).unwrap_or(&sequence))?;
+        sequence.save(&path)?;
+        Ok(path)
+    }
+}
+
+/// Moves one joint by `delta` degrees, stopping at its calibrated limits
+///
+/// Returns the joint's new angle.
+pub fn jog(driver: &mut ArmDriver, servo: ServoId, delta: i16) -> Result<u8> {
+    let cal = driver.profile().servo(servo);
+    let target = (driver.position(servo) as i32 + delta as i32).clamp(cal.min as i32, cal.max as i32) as u8;
+    if target != driver.position(servo) {
+        driver.move_servo(servo, target, JOG_DURATION)?;
+    }
+    Ok(target)
+}
+
+pub fn run_teach(name: &str, serial: &SerialConfig) -> Result<()> {
+    let term = Term::stdout();
+    if !term.is_term() {
+        anyhow::bail!("Teach mode needs an interactive terminal; use the HTTP /teach endpoints instead");
+    }
+
+    let mut session = TeachSession::new(name, None)?;
+    if let Ok(existing) = Library::load()?.get(&session.name) {
+        let replace = Confirm::new()
+            .with_prompt(format!("Sequence {} exists ({}), replace it?", existing.name, existing.source))
+            .default(false)
+            .interact()?;
+        if !replace {
+            return Ok(());
+        }
+    }
+
+    println!("{} {}", "🎓 Teaching sequence".bright_cyan().bold(), session.name.bright_white());
+    println!();
+    println!("{}", "Keys:".bright_white());
+    println!("  {}  jog the selected joint", "← →".cyan());
+    println!("  {}  select another joint", "↑ ↓".cyan());
+    println!("  {}  step size 1°/5°/10° (Tab cycles)", "1 5 0".cyan());
+    println!("  {}  capture a waypoint, {} undo the last one", "Space".cyan(), "Backspace".cyan());
+    println!("  {}  finish and save, {} cancel without saving", "Enter".cyan(), "Esc".cyan());
+    println!("{}", "Waypoints are timed: move between captures at the pace the arm should replay.".dimmed());
+    println!();
+
+    let mut driver = ArmDriver::open(serial)?;
+    let mut selected = 0;
+    let mut step = 1;
+
+    let path = loop {
+        render(&term, &driver, selected, step, session.waypoints().len())?;
+
+        let delta = match term.read_key()? {
+            Key::ArrowLeft => -(STEP_SIZES[step] as i16),
+            Key::ArrowRight => STEP_SIZES[step] as i16,
+            Key::ArrowUp => {
+                selected = (selected + ServoId::ALL.len() - 1) % ServoId::ALL.len();
+                continue;
+            }
+            Key::ArrowDown => {
+                selected = (selected + 1) % ServoId::ALL.len();
+                continue;
+            }
+            Key::Char('1') => {
+                step = 0;
+                continue;
+            }
+            Key::Char('5') => {
+                step = 1;
+                continue;
+            }
+            Key::Char('0') => {
+                step = 2;
+                continue;
+            }
+            Key::Tab => {
+                step = (step + 1) % STEP_SIZES.len();
+                continue;
+            }
+            Key::Char(' ') => {
+                let count = session.waypoints().len() + 1;
+                let waypoint = *session.capture(driver.positions());
+                term.clear_line()?;
+                println!("  {} Waypoint {} {}", "✓".green(), count, describe(&waypoint));
+                continue;
+            }
+            Key::Backspace => {
+                term.clear_line()?;
+                match session.undo() {
+                    Some(_) => println!("  {} Removed waypoint {}", "↶".yellow(), session.waypoints().len() + 1),
+                    None => println!("  {}", "No waypoints to remove".dimmed()),
+                }
+                continue;
+            }
+            Key::Enter if session.waypoints().is_empty() => {
+                term.clear_line()?;
+                println!("  {}", "Capture at least one waypoint with Space first".yellow());
+                continue;
+            }
+            Key::Enter => {
+                term.clear_line()?;
+                let description: String = Input::new()
+                    .with_prompt("Description (optional)")
+                    .with_initial_text(session.description.clone().unwrap_or_default())
+                    .allow_empty(true)
+                    .interact_text()?;
+                session.description = Some(description).filter(|d| !d.trim().is_empty());
+
+                // A waypoint the plan checks reject shouldn't cost the whole session
+                match session.save(driver.profile()) {
+                    Ok(path) => break path,
+                    Err(e) => {
+                        println!("  {} {:#}", "✗ Not saved:".red(), e);
+                        println!(
+                            "  {}",
+                            "Undo with Backspace or re-capture, then press Enter again".dimmed()
+                        );
+                        continue;
+                    }
+                }
+            }
+            Key::Escape | Key::Char('q') => {
+                term.clear_line()?;
+                println!("{}", "⊗ Teaching cancelled, nothing saved".yellow());
+                return Ok(());
+            }
+            _ => continue,
+        };
+
+        if let Err(e) = jog(&mut driver, ServoId::ALL[selected], delta) {
+            term.clear_line()?;
+            println!("  {}", e.to_string().red());
+        }
+    };
+
+    println!("{}: {}", "💾 Saved".green(), path.display());
+    println!(
+        "{}",
+        format!(
+            "Replay with `abel-voice seq run {}`, or ask for it by name",
+            session.name.to_lowercase()
+        )
+        .dimmed()
+    );
+
+    Ok(())
+}
+
+fn describe(waypoint: &Waypoint) -> String {
+    let angles: Vec<String> = ServoId::ALL
+        .iter()
+        .map(|servo| format!("{} {}°", servo.name(), waypoint.positions[servo.index()]))
+        .collect();
+    format!(
+        "{}  {}",
+        angles.join("  "),
+        format!("+{:.1}s", waypoint.elapsed_ms as f32 / 1000.0).dimmed()
+    )
+}
+
+fn render(term: &Term, driver: &ArmDriver, selected: usize, step: usize, captured: usize) -> Result<()> {
+    let joints: Vec<String> = ServoId::ALL
+        .iter()
+        .enumerate()
+        .map(|(i, &servo)| {
+            let angle = driver.position(servo);
+            if i == selected {
+                format!("{} ◀ {:>3}° ▶", servo.name(), angle).bright_yellow().bold().to_string()
+            } else {
+                format!("{} {:>3}°", servo.name(), angle).dimmed().to_string()
+            }
+        })
+        .collect();
+
+    term.clear_line()?;
+    term.write_str(&format!(
+        "  {}   {}",
+        joints.join("   "),
+        format!("step {}°, {} captured", STEP_SIZES[step], captured).dimmed()
+    ))?;
+    Ok(())
+}
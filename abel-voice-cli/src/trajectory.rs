@@ -0,0 +1,567 @@
+use anyhow::Result;
+use std::io::Write;
+use std::path::Path;
+use std::time::Duration;
+
+/// Motion limits of a servo, in degrees, seconds and their derivatives
+#[derive(Clone, Copy, Debug)]
+pub struct Constraints {
+    pub max_velocity: f32,
+    pub max_acceleration: f32,
+    pub max_jerk: f32,
+}
+
+/// Limits hardcoded in Abel_Arm_Firmware.ino
+pub const FIRMWARE_CONSTRAINTS: Constraints = Constraints {
+    max_velocity: 120.0,
+    max_acceleration: 200.0,
+    max_jerk: 600.0,
+};
+
+/// Shortest move the firmware will plan, in milliseconds
+const MIN_OPTIMAL_DURATION_MS: u64 = 200;
+
+/// Segments the firmware queues behind the one it is running (`SEGMENT_QUEUE`)
+pub const SEGMENT_QUEUE: usize = 8;
+
+/// Shortest segment the firmware accepts
+const MIN_SEGMENT: Duration = Duration::from_millis(100);
+
+/// Fraction of the firmware limits the planner aims for, leaving room for rounding
+const PLANNING_MARGIN: f32 = 0.9;
+
+/// Points per segment checked against the limits while planning
+const CHECK_SAMPLES: usize = 40;
+
+/// Rounds of stretching and damping before the planner gives up improving a spline
+const MAX_ITERATIONS: usize = 100;
+
+/// Quintic polynomial q(t) = a0 + a1*t + a2*t^2 + a3*t^3 + a4*t^4 + a5*t^5
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Quintic {
+    pub a0: f32,
+    pub a1: f32,
+    pub a2: f32,
+    pub a3: f32,
+    pub a4: f32,
+    pub a5: f32,
+}
+
+impl Quintic {
+    /// `calculateQuinticCoefficients`: rest-to-rest motion from `q0` to `qf` over `t` seconds
+    pub fn point_to_point(q0: f32, qf: f32, t: f32) -> Self {
+        Self {
+            a0: q0,
+            a1: 0.0,
+            a2: 0.0,
+            a3: 10.0 * (qf - q0) / (t * t * t),
+            a4: -15.0 * (qf - q0) / (t * t * t * t),
+            a5: 6.0 * (qf - q0) / (t * t * t * t * t),
+        }
+    }
+
+    /// `calculateQuinticCoefficients` with boundary velocities: starts at `v0` and ends at
+    /// `vf` deg/s, with zero acceleration at both ends so segments join smoothly
+    pub fn with_velocities(q0: f32, qf: f32, v0: f32, vf: f32, t: f32) -> Self {
+        let h = qf - q0;
+        Self {
+            a0: q0,
+            a1: v0,
+            a2: 0.0,
+            a3: (20.0 * h - (8.0 * vf + 12.0 * v0) * t) / (2.0 * t * t * t),
+            a4: (-30.0 * h + (14.0 * vf + 16.0 * v0) * t) / (2.0 * t * t * t * t),
+            a5: (12.0 * h - 6.0 * (vf + v0) * t) / (2.0 * t * t * t * t * t),
+        }
+    }
+
+    pub fn position(&self, t: f32) -> f32 {
+        self.a0
+            + self.a1 * t
+            + self.a2 * t * t
+            + self.a3 * t * t * t
+            + self.a4 * t * t * t * t
+            + self.a5 * t * t * t * t * t
+    }
+
+    pub fn velocity(&self, t: f32) -> f32 {
+        self.a1
+            + 2.0 * self.a2 * t
+            + 3.0 * self.a3 * t * t
+            + 4.0 * self.a4 * t * t * t
+            + 5.0 * self.a5 * t * t * t * t
+    }
+
+    pub fn acceleration(&self, t: f32) -> f32 {
+        2.0 * self.a2 + 6.0 * self.a3 * t + 12.0 * self.a4 * t * t + 20.0 * self.a5 * t * t * t
+    }
+
+    pub fn jerk(&self, t: f32) -> f32 {
+        6.0 * self.a3 + 24.0 * self.a4 * t + 60.0 * self.a5 * t * t
+    }
+}
+
+/// `calculateOptimalDuration`: shortest duration that respects v_max and a_max, plus 20% margin
+pub fn optimal_duration(delta_angle: f32) -> Duration {
+    let delta_angle = delta_angle.abs();
+    let limits = FIRMWARE_CONSTRAINTS;
+
+    // The firmware mixes double literals with float variables; keep the same promotions
+    let min_time_for_velocity = (1.875 * delta_angle as f64 / limits.max_velocity as f64) as f32;
+    let min_time_for_acceleration =
+        (5.77 * delta_angle as f64 / limits.max_acceleration as f64).sqrt() as f32;
+
+    let optimal_time = (min_time_for_velocity.max(min_time_for_acceleration) as f64 * 1.2) as f32;
+
+    let ms = (optimal_time as f64 * 1000.0) as u64;
+    Duration::from_millis(ms.max(MIN_OPTIMAL_DURATION_MS))
+}
+
+/// Duration the firmware will actually use for a requested move
+pub fn firmware_duration(delta_angle: f32, requested: Duration) -> Duration {
+    requested.max(optimal_duration(delta_angle))
+}
+
+/// One piece of a multi-waypoint trajectory, sent as `#<servo>Q<angle>T<duration>V<v0>,<vf>`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Segment {
+    pub start: f32,
+    pub target: f32,
+    pub duration: Duration,
+    /// Degrees per second at the start and end
+    pub start_velocity: f32,
+    pub end_velocity: f32,
+}
+
+impl Segment {
+    pub fn quintic(&self) -> Quintic {
+        Quintic::with_velocities(
+            self.start,
+            self.target,
+            self.start_velocity,
+            self.end_velocity,
+            self.duration.as_secs_f32(),
+        )
+    }
+
+    /// Highest speed and acceleration reached, sampled
+    fn peaks(&self) -> (f32, f32) {
+        let quintic = self.quintic();
+        let total = self.duration.as_secs_f32();
+        (0..=CHECK_SAMPLES)
+            .map(|i| i as f32 / CHECK_SAMPLES as f32 * total)
+            .fold((0.0f32, 0.0f32), |(v, a), t| {
+                (v.max(quintic.velocity(t).abs()), a.max(quintic.acceleration(t).abs()))
+            })
+    }
+
+    /// Whether the segment swings past either end on its way between them
+    fn overshoots(&self) -> bool {
+        let quintic = self.quintic();
+        let total = self.duration.as_secs_f32();
+        let (lo, hi) = (self.start.min(self.target), self.start.max(self.target));
+        (1..CHECK_SAMPLES).any(|i| {
+            let q = quintic.position(i as f32 / CHECK_SAMPLES as f32 * total);
+            q < lo - 0.05 || q > hi + 0.05
+        })
+    }
+}
+
+/// Smooth path through waypoints for several joints, without stopping at the via points
+///
+/// Every joint shares the same segment durations, so the joints reach each waypoint
+/// together. The arm starts and ends at rest; at a via point a joint keeps moving unless
+/// it changes direction there.
+#[derive(Clone, Debug)]
+pub struct Spline {
+    pub durations: Vec<Duration>,
+    /// For each joint, one segment per waypoint
+    pub segments: Vec<Vec<Segment>>,
+}
+
+impl Spline {
+    /// Plans a path from `start` through `waypoints` (one angle per joint in each)
+    ///
+    /// `min_durations` optionally sets the least time for each segment; segments are
+    /// stretched beyond that until they respect the firmware's velocity and
+    /// acceleration limits.
+    pub fn plan(start: &[f32], waypoints: &[Vec<f32>], min_durations: &[Option<Duration>]) -> Self {
+        let joints = start.len();
+        let mut knots = vec![start.to_vec()];
+        knots.extend(waypoints.iter().cloned());
+
+        let limits = FIRMWARE_CONSTRAINTS;
+        let cruise = limits.max_velocity * PLANNING_MARGIN * 0.5;
+
+        // Initial guess: each segment at a moderate cruising speed
+        let mut durations: Vec<Duration> = knots
+            .windows(2)
+            .enumerate()
+            .map(|(i, pair)| {
+                let longest = (0..joints).map(|j| (pair[1][j] - pair[0][j]).abs()).fold(0.0, f32::max);
+                let guess = Duration::from_millis((longest / cruise * 1000.0).ceil() as u64);
+                guess.max(min_durations.get(i).copied().flatten().unwrap_or_default()).max(MIN_SEGMENT)
+            })
+            .collect();
+        // Damping of each joint's velocity at each knot, lowered where a segment overshoots
+        let mut damping = vec![vec![1.0f32; joints]; knots.len()];
+
+        let mut spline = Self::build(&knots, &durations, &damping);
+        for _ in 0..MAX_ITERATIONS {
+            let mut changed = false;
+
+            for (i, duration) in durations.iter_mut().enumerate() {
+                let segments = spline.segments.iter().map(|joint| joint[i]);
+                let mut too_fast = false;
+                for (j, segment) in segments.enumerate() {
+                    let (velocity, acceleration) = segment.peaks();
+                    too_fast |= velocity > limits.max_velocity * PLANNING_MARGIN
+                        || acceleration > limits.max_acceleration * PLANNING_MARGIN;
+
+                    if segment.overshoots() {
+                        damping[i][j] *= 0.7;
+                        damping[i + 1][j] *= 0.7;
+                        changed = true;
+                    }
+                }
+
+                if too_fast {
+                    *duration = Duration::from_millis((duration.as_millis() as f32 * 1.1).ceil() as u64);
+                    changed = true;
+                }
+            }
+
+            if !changed {
+                break;
+            }
+            spline = Self::build(&knots, &durations, &damping);
+        }
+
+        spline
+    }
+
+    fn build(knots: &[Vec<f32>], durations: &[Duration], damping: &[Vec<f32>]) -> Self {
+        let joints = knots[0].len();
+        let limit = FIRMWARE_CONSTRAINTS.max_velocity * PLANNING_MARGIN;
+
+        let segments = (0..joints)
+            .map(|j| {
+                let slopes: Vec<f32> = knots
+                    .windows(2)
+                    .zip(durations)
+                    .map(|(pair, duration)| (pair[1][j] - pair[0][j]) / duration.as_secs_f32())
+                    .collect();
+
+                // At rest at both ends; at a via point the mean of the neighbouring slopes,
+                // or zero where the joint turns around. Rounded as the command sends it.
+                let velocities: Vec<f32> = (0..knots.len())
+                    .map(|k| {
+                        if k == 0 || k == knots.len() - 1 {
+                            return 0.0;
+                        }
+                        let (before, after) = (slopes[k - 1], slopes[k]);
+                        if before * after <= 0.0 {
+                            return 0.0;
+                        }
+                        let velocity = ((before + after) / 2.0 * damping[k][j]).clamp(-limit, limit);
+                        (velocity * 10.0).round() / 10.0
+                    })
+                    .collect();
+
+                knots
+                    .windows(2)
+                    .zip(durations)
+                    .enumerate()
+                    .map(|(i, (pair, &duration))| Segment {
+                        start: pair[0][j],
+                        target: pair[1][j],
+                        duration,
+                        start_velocity: velocities[i],
+                        end_velocity: velocities[i + 1],
+                    })
+                    .collect()
+            })
+            .collect();
+
+        Self {
+            durations: durations.to_vec(),
+            segments,
+        }
+    }
+
+    pub fn duration(&self) -> Duration {
+        self.durations.iter().sum()
+    }
+
+    /// When each segment ends, measured from the start of the trajectory
+    pub fn ends(&self) -> Vec<Duration> {
+        self.durations
+            .iter()
+            .scan(Duration::ZERO, |end, duration| {
+                *end += *duration;
+                Some(*end)
+            })
+            .collect()
+    }
+}
+
+/// One point of a sampled profile
+#[derive(Clone, Copy, Debug)]
+pub struct Sample {
+    pub time: f32,
+    pub position: f32,
+    pub velocity: f32,
+    pub acceleration: f32,
+    pub jerk: f32,
+}
+
+/// A single-servo move as the firmware will execute it
+#[derive(Clone, Copy, Debug)]
+pub struct Profile {
+    pub start: f32,
+    pub target: f32,
+    pub requested: Duration,
+    pub duration: Duration,
+    pub quintic: Quintic,
+}
+
+impl Profile {
+    /// Plans a move the same way `processCommand` does, including the duration clamp
+    pub fn plan(start: f32, target: f32, requested: Duration) -> Self {
+        let duration = firmware_duration(target - start, requested);
+        let quintic = Quintic::point_to_point(start, target, duration.as_secs_f32());
+
+        Self {
+            start,
+            target,
+            requested,
+            duration,
+            quintic,
+        }
+    }
+
+    pub fn is_adjusted(&self) -> bool {
+        self.duration != self.requested
+    }
+
+    fn delta(&self) -> f32 {
+        (self.target - self.start).abs()
+    }
+
+    /// Peak velocity, reached at t = T/2
+    pub fn peak_velocity(&self) -> f32 {
+        1.875 * self.delta() / self.duration.as_secs_f32()
+    }
+
+    /// Peak acceleration, reached at t = T(1/2 - sqrt(3)/6)
+    pub fn peak_acceleration(&self) -> f32 {
+        10.0 / 3f32.sqrt() * self.delta() / self.duration.as_secs_f32().powi(2)
+    }
+
+    /// Peak jerk, reached at the endpoints
+    pub fn peak_jerk(&self) -> f32 {
+        60.0 * self.delta() / self.duration.as_secs_f32().powi(3)
+    }
+
+    /// Describes every limit the move exceeds
+    pub fn violations(&self, limits: &Constraints) -> Vec<String> {
+        let mut violations = Vec::new();
+
+        let checks = [
+            ("velocity", self.peak_velocity(), limits.max_velocity, "deg/s"),
+            ("acceleration", self.peak_acceleration(), limits.max_acceleration, "deg/s^2"),
+            ("jerk", self.peak_jerk(), limits.max_jerk, "deg/s^3"),
+        ];
+        for (name, peak, limit, unit) in checks {
+            if peak > limit {
+                violations.push(format!(
+                    "peak {} {:.1}{} exceeds {:.0}{}",
+                    name, peak, unit, limit, unit
+                ));
+            }
+        }
+
+        violations
+    }
+
+    /// Samples the profile at `rate_hz`, including the final point
+    pub fn samples(&self, rate_hz: f32) -> Vec<Sample> {
+        let total = self.duration.as_secs_f32();
+        let steps = (total * rate_hz).ceil().max(1.0) as usize;
+
+        (0..=steps)
+            .map(|i| {
+                let time = (i as f32 / rate_hz).min(total);
+                Sample {
+                    time,
+                    position: self.quintic.position(time),
+                    velocity: self.quintic.velocity(time),
+                    acceleration: self.quintic.acceleration(time),
+                    jerk: self.quintic.jerk(time),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Writes samples as CSV with a header row
+pub fn export_csv(samples: &[Sample], path: &Path) -> Result<()> {
+    let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+
+    writeln!(file, "time_s,position_deg,velocity_deg_s,acceleration_deg_s2,jerk_deg_s3")?;
+    for s in samples {
+        writeln!(
+            file,
+            "{:.4},{:.4},{:.4},{:.4},{:.4}",
+            s.time, s.position, s.velocity, s.acceleration, s.jerk
+        )?;
+    }
+
+    file.flush()?;
+    Ok(())
+}
+
+/// Renders one column of the samples as a small ASCII chart
+pub fn plot(samples: &[Sample], value: impl Fn(&Sample) -> f32, width: usize, height: usize) -> Vec<String> {
+    if samples.is_empty() || width == 0 || height == 0 {
+        return Vec::new();
+    }
+
+    let values: Vec<f32> = (0..width)
+        .map(|col| value(&samples[col * (samples.len() - 1) / (width - 1).max(1)]))
+        .collect();
+
+    let min = values.iter().cloned().fold(f32::INFINITY, f32::min);
+    let max = values.iter().cloned().fold(f32::NEG_INFINITY, f32::max);
+    let range = if (max - min).abs() < f32::EPSILON { 1.0 } else { max - min };
+
+    let mut grid = vec![vec![' '; width]; height];
+    for (col, v) in values.iter().enumerate() {
+        let row = ((v - min) / range * (height - 1) as f32).round() as usize;
+        grid[height - 1 - row][col] = '•';
+    }
+
+    grid.into_iter()
+        .enumerate()
+        .map(|(i, row)| {
+            let label = if i == 0 {
+                format!("{:>8.1} ┤", max)
+            } else if i == height - 1 {
+                format!("{:>8.1} ┤", min)
+            } else {
+                format!("{:>8} │", "")
+            };
+            format!("{}{}", label, row.into_iter().collect::<String>())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn optimal_duration_respects_the_firmware_velocity_and_acceleration() {
+        let limits = FIRMWARE_CONSTRAINTS;
+        for delta in [1.0, 10.0, 45.0, 90.0, 180.0, -120.0] {
+            let profile = Profile::plan(90.0, 90.0 + delta, Duration::ZERO);
+            assert!(profile.duration >= Duration::from_millis(MIN_OPTIMAL_DURATION_MS));
+            assert!(profile.peak_velocity() <= limits.max_velocity, "{}°", delta);
+            assert!(profile.peak_acceleration() <= limits.max_acceleration, "{}°", delta);
+        }
+    }
+
+    #[test]
+    fn optimal_duration_has_a_floor_for_tiny_moves() {
+        assert_eq!(optimal_duration(0.0), Duration::from_millis(MIN_OPTIMAL_DURATION_MS));
+        assert_eq!(optimal_duration(0.5), Duration::from_millis(MIN_OPTIMAL_DURATION_MS));
+    }
+
+    #[test]
+    fn firmware_duration_stretches_short_requests_only() {
+        let optimal = optimal_duration(90.0);
+        assert_eq!(firmware_duration(90.0, Duration::from_millis(100)), optimal);
+        assert_eq!(firmware_duration(-90.0, Duration::from_millis(100)), optimal);
+        let slow = optimal + Duration::from_secs(1);
+        assert_eq!(firmware_duration(90.0, slow), slow);
+
+        assert!(Profile::plan(0.0, 90.0, Duration::from_millis(100)).is_adjusted());
+        assert!(!Profile::plan(0.0, 90.0, slow).is_adjusted());
+    }
+
+    #[test]
+    fn profile_starts_and_ends_at_rest_on_the_endpoints() {
+        let profile = Profile::plan(30.0, 150.0, Duration::from_secs(3));
+        let total = profile.duration.as_secs_f32();
+        assert!((profile.quintic.position(0.0) - 30.0).abs() < 1e-3);
+        assert!((profile.quintic.position(total) - 150.0).abs() < 1e-2);
+        assert!(profile.quintic.velocity(0.0).abs() < 1e-3);
+        assert!(profile.quintic.velocity(total).abs() < 1e-2);
+        assert!(profile.violations(&FIRMWARE_CONSTRAINTS).is_empty());
+    }
+
+    /// Highest speed and acceleration over every segment of every joint, densely sampled
+    fn spline_peaks(spline: &Spline) -> (f32, f32) {
+        let mut peaks = (0.0f32, 0.0f32);
+        for segment in spline.segments.iter().flatten() {
+            let quintic = segment.quintic();
+            let total = segment.duration.as_secs_f32();
+            for i in 0..=200 {
+                let t = i as f32 / 200.0 * total;
+                peaks.0 = peaks.0.max(quintic.velocity(t).abs());
+                peaks.1 = peaks.1.max(quintic.acceleration(t).abs());
+            }
+        }
+        peaks
+    }
+
+    #[test]
+    fn spline_stays_within_the_firmware_limits() {
+        let waypoints = vec![vec![30.0, 120.0, 60.0], vec![150.0, 60.0, 120.0], vec![160.0, 170.0, 20.0]];
+        let spline = Spline::plan(&[90.0, 90.0, 90.0], &waypoints, &[None, None, None]);
+
+        let (velocity, acceleration) = spline_peaks(&spline);
+        assert!(velocity <= FIRMWARE_CONSTRAINTS.max_velocity, "{} deg/s", velocity);
+        assert!(acceleration <= FIRMWARE_CONSTRAINTS.max_acceleration, "{} deg/s^2", acceleration);
+        assert!(spline.durations.iter().all(|&duration| duration >= MIN_SEGMENT));
+    }
+
+    #[test]
+    fn spline_passes_through_the_waypoints_starting_and_ending_at_rest() {
+        let start = [90.0, 90.0];
+        let waypoints = vec![vec![100.0, 60.0], vec![140.0, 30.0], vec![170.0, 40.0]];
+        let spline = Spline::plan(&start, &waypoints, &[None, None, None]);
+
+        for (joint, segments) in spline.segments.iter().enumerate() {
+            assert_eq!(segments.len(), waypoints.len());
+            assert_eq!(segments[0].start, start[joint]);
+            assert_eq!(segments[0].start_velocity, 0.0);
+            assert_eq!(segments[segments.len() - 1].end_velocity, 0.0);
+
+            for (segment, waypoint) in segments.iter().zip(&waypoints) {
+                let end = segment.quintic().position(segment.duration.as_secs_f32());
+                assert!((end - waypoint[joint]).abs() < 0.05, "joint {} ends at {}", joint, end);
+            }
+            // Consecutive segments join without a jump in speed
+            for pair in segments.windows(2) {
+                assert_eq!(pair[0].end_velocity, pair[1].start_velocity);
+            }
+        }
+        // The shoulder keeps moving through the first via point, and stops where it turns around
+        assert!(spline.segments[1][0].end_velocity < 0.0);
+        assert_eq!(spline.segments[1][1].end_velocity, 0.0);
+    }
+
+    #[test]
+    fn spline_respects_requested_segment_durations() {
+        let waypoints = vec![vec![91.0], vec![92.0], vec![170.0]];
+        let minimum = Duration::from_secs(2);
+        let spline = Spline::plan(&[90.0], &waypoints, &[None, Some(minimum), Some(Duration::from_millis(10))]);
+
+        assert!(spline.durations[0] >= MIN_SEGMENT);
+        assert!(spline.durations[1] >= minimum);
+        // Too short to reach 170° within the limits, so it is stretched
+        assert!(spline.durations[2] > Duration::from_millis(10));
+        assert_eq!(spline.duration(), spline.durations.iter().sum::<Duration>());
+        assert_eq!(spline.ends().last(), Some(&spline.duration()));
+    }
+}
@@ -0,0 +1,382 @@
+use anyhow::{Context, Result};
+use colored::Colorize;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// Packages generated scripts need, pinned as `name==version`
+const REQUIREMENTS: &str = include_str!("../python/requirements.txt");
+
+/// Oldest interpreter the safety shim runs on
+const MIN_PYTHON: (u32, u32) = (3, 8);
+
+/// Prints the interpreter version and the installed version of each package named in argv
+const INSPECT: &str = r#"
+import json, sys
+from importlib import metadata
+
+def installed(name):
+    try:
+        return metadata.version(name)
+    except metadata.PackageNotFoundError:
+        return None
+
+print(json.dumps({
+    "python": "%d.%d.%d" % sys.version_info[:3],
+    "packages": {name: installed(name) for name in sys.argv[1:]},
+}))
+"#;
+
+/// A pinned package from `python/requirements.txt`
+#[derive(Clone, Debug)]
+pub struct Requirement {
+    pub name: String,
+    pub version: String,
+}
+
+impl std::fmt::Display for Requirement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}=={}", self.name, self.version)
+    }
+}
+
+pub fn requirements() -> Vec<Requirement> {
+    REQUIREMENTS
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| line.split_once("=="))
+        .map(|(name, version)| Requirement {
+            name: name.trim().to_string(),
+            version: version.trim().to_string(),
+        })
+        .collect()
+}
+
+/// The virtual environment Python scripts run in
+#[derive(Clone, Debug)]
+pub struct PythonEnv {
+    pub path: PathBuf,
+    /// Interpreter the environment is created from
+    pub interpreter: String,
+    /// Install from these wheels only, without touching the network
+    pub wheel_dir: Option<PathBuf>,
+}
+
+#[derive(Deserialize)]
+struct Inspection {
+    python: String,
+    packages: HashMap<String, Option<String>>,
+}
+
+/// What `env status` found
+pub struct Status {
+    pub exists: bool,
+    /// The environment's Python version, or why it doesn't run
+    pub python: Result<String, String>,
+    /// Each requirement with the version installed, if any
+    pub packages: Vec<(Requirement, Option<String>)>,
+}
+
+impl Status {
+    pub fn is_ready(&self) -> bool {
+        self.python.is_ok() && self.outdated().is_empty()
+    }
+
+    /// Requirements that are missing or installed at another version
+    pub fn outdated(&self) -> Vec<&Requirement> {
+        self.packages
+            .iter()
+            .filter(|(requirement, installed)| installed.as_deref() != Some(requirement.version.as_str()))
+            .map(|(requirement, _)| requirement)
+            .collect()
+    }
+
+    /// One line explaining why the environment isn't ready
+    pub fn problem(&self) -> Option<String> {
+        if !self.exists {
+            return Some("not created yet".to_string());
+        }
+        if let Err(e) = &self.python {
+            return Some(e.clone());
+        }
+        let outdated = self.outdated();
+        if outdated.is_empty() {
+            return None;
+        }
+        let names: Vec<String> = outdated.iter().map(|r| r.to_string()).collect();
+        Some(format!("needs {}", names.join(", ")))
+    }
+
+    pub fn print(&self, env: &PythonEnv) {
+        println!("{}: {}", "Environment".bright_white(), env.path.display());
+        println!("{}: {}", "Interpreter".bright_white(), env.interpreter);
+        if let Some(dir) = &env.wheel_dir {
+            println!("{}: {}", "Wheel directory".bright_white(), dir.display());
+        }
+        println!();
+
+        if !self.exists {
+            println!("{} Not created yet", "✗".red());
+            return;
+        }
+        match &self.python {
+            Ok(version) => println!("  {} Python {}", "✓".green(), version),
+            Err(e) => {
+                println!("  {} Python: {}", "✗".red(), e);
+                return;
+            }
+        }
+
+        for (requirement, installed) in &self.packages {
+            match installed {
+                Some(version) if *version == requirement.version => {
+                    println!("  {} {} {}", "✓".green(), requirement.name, version)
+                }
+                Some(version) => println!(
+                    "  {} {} {} (requires {})",
+                    "✗".red(),
+                    requirement.name,
+                    version,
+                    requirement.version
+                ),
+                None => println!("  {} {} not installed (requires {})", "✗".red(), requirement.name, requirement.version),
+            }
+        }
+    }
+}
+
+impl PythonEnv {
+    /// Settings from `ABEL_VENV`, `ABEL_PYTHON` and `ABEL_WHEEL_DIR`
+    pub fn from_env() -> Result<Self> {
+        Self::new(
+            std::env::var("ABEL_PYTHON").ok(),
+            std::env::var_os("ABEL_WHEEL_DIR").map(PathBuf::from),
+        )
+    }
+
+    /// Environment at `ABEL_VENV` (default `~/.abel-voice-venv`) with the given overrides
+    pub fn new(interpreter: Option<String>, wheel_dir: Option<PathBuf>) -> Result<Self> {
+        let path = match std::env::var_os("ABEL_VENV") {
+            Some(path) => PathBuf::from(path),
+            None => dirs::home_dir()
+                .context("Could not find home directory")?
+                .join(".abel-voice-venv"),
+        };
+        let default_interpreter = if cfg!(windows) { "python" } else { "python3" };
+
+        Ok(Self {
+            path,
+            interpreter: interpreter
+                .filter(|i| !i.trim().is_empty())
+                .unwrap_or_else(|| default_interpreter.to_string()),
+            wheel_dir: wheel_dir.filter(|dir| !dir.as_os_str().is_empty()),
+        })
+    }
+
+    /// The environment's own interpreter
+    pub fn python(&self) -> PathBuf {
+        if cfg!(windows) {
+            self.path.join("Scripts").join("python.exe")
+        } else {
+            self.path.join("bin").join("python")
+        }
+    }
+
+    pub fn status(&self) -> Status {
+        let requirements = requirements();
+        let mut status = Status {
+            exists: self.path.exists(),
+            python: Err("not created yet".to_string()),
+            packages: requirements.iter().map(|r| (r.clone(), None)).collect(),
+        };
+        if !status.exists {
+            return status;
+        }
+
+        let output = Command::new(self.python())
+            .arg("-I")
+            .arg("-c")
+            .arg(INSPECT)
+            .args(requirements.iter().map(|r| &r.name))
+            .output();
+        let inspection = match output {
+            Err(e) => Err(format!("{} does not run ({})", self.python().display(), e)),
+            Ok(output) if !output.status.success() => Err(last_line(&output.stderr)),
+            Ok(output) => serde_json::from_slice::<Inspection>(&output.stdout).map_err(|e| e.to_string()),
+        };
+        let inspection = match inspection {
+            Ok(inspection) => inspection,
+            Err(e) => {
+                status.python = Err(e);
+                return status;
+            }
+        };
+
+        status.python = check_version(&inspection.python).map(|_| inspection.python.clone());
+        for (requirement, installed) in &mut status.packages {
+            *installed = inspection.packages.get(&requirement.name).cloned().flatten();
+        }
+        status
+    }
+
+    /// Creates or repairs the environment if it isn't ready, before a script runs
+    pub fn ensure(&self) -> Result<()> {
+        let status = self.status();
+        if status.is_ready() {
+            return Ok(());
+        }
+
+        if status.exists {
+            println!(
+                "{} Python environment {}, repairing...",
+                "⚠".yellow(),
+                status.problem().unwrap_or_default()
+            );
+        }
+        self.repair().map(|_| ())
+    }
+
+    /// Recreates a broken environment and installs missing or mismatched packages
+    pub fn repair(&self) -> Result<Status> {
+        let mut status = self.status();
+
+        if status.python.is_err() {
+            self.check_interpreter()?;
+            if status.exists {
+                self.remove()?;
+            }
+            self.create()?;
+            status = self.status();
+            if let Err(e) = &status.python {
+                anyhow::bail!("The new environment does not work: {}", e);
+            }
+        }
+
+        let outdated: Vec<Requirement> = status.outdated().into_iter().cloned().collect();
+        if !outdated.is_empty() {
+            self.install(&outdated)?;
+            status = self.status();
+        }
+
+        if let Some(problem) = status.problem() {
+            anyhow::bail!("Python environment is still not ready: {}", problem);
+        }
+        Ok(status)
+    }
+
+    /// Deletes the environment and builds it again from scratch
+    pub fn reset(&self) -> Result<Status> {
+        if self.path.exists() {
+            self.check_interpreter()?;
+            self.remove()?;
+        }
+        self.repair()
+    }
+
+    /// Fails before anything is deleted if the environment couldn't be created again
+    fn check_interpreter(&self) -> Result<()> {
+        let output = Command::new(&self.interpreter)
+            .arg("-c")
+            .arg("import sys; print('%d.%d.%d' % sys.version_info[:3])")
+            .output()
+            .with_context(|| {
+                format!(
+                    "Failed to run {}. Is Python 3 installed? Set ABEL_PYTHON to its path",
+                    self.interpreter
+                )
+            })?;
+        if !output.status.success() {
+            anyhow::bail!("{} failed: {}", self.interpreter, last_line(&output.stderr));
+        }
+
+        check_version(String::from_utf8_lossy(&output.stdout).trim()).map_err(anyhow::Error::msg)
+    }
+
+    fn remove(&self) -> Result<()> {
+        // Only ever delete something that is recognisably a virtual environment
+        if !self.path.join("pyvenv.cfg").exists() {
+            anyhow::bail!(
+                "{} does not look like a virtual environment (no pyvenv.cfg); remove it yourself or point ABEL_VENV elsewhere",
+                self.path.display()
+            );
+        }
+
+        println!("Removing {}...", self.path.display());
+        std::fs::remove_dir_all(&self.path).with_context(|| format!("Failed to remove {}", self.path.display()))
+    }
+
+    fn create(&self) -> Result<()> {
+        println!("Creating virtual environment with {}...", self.interpreter);
+        let output = Command::new(&self.interpreter)
+            .arg("-m")
+            .arg("venv")
+            .arg(&self.path)
+            .output()
+            .with_context(|| format!("Failed to run {}", self.interpreter))?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!("Failed to create venv:\n{}", stderr);
+        }
+
+        Ok(())
+    }
+
+    fn install(&self, packages: &[Requirement]) -> Result<()> {
+        let specs: Vec<String> = packages.iter().map(|r| r.to_string()).collect();
+
+        let mut command = Command::new(self.python());
+        command.args(["-m", "pip", "install", "--disable-pip-version-check"]);
+        match &self.wheel_dir {
+            Some(dir) => {
+                if !dir.is_dir() {
+                    anyhow::bail!("Wheel directory {} does not exist", dir.display());
+                }
+                println!("Installing {} from {}...", specs.join(", "), dir.display());
+                command.arg("--no-index").arg("--find-links").arg(dir);
+            }
+            None => println!("Installing {}...", specs.join(", ")),
+        }
+
+        let output = command
+            .args(&specs)
+            .output()
+            .with_context(|| format!("Failed to install {}", specs.join(", ")))?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            let hint = if self.wheel_dir.is_some() {
+                ""
+            } else {
+                "\nOffline? Set ABEL_WHEEL_DIR to a directory of wheels (pip download -d <dir> -r python/requirements.txt)"
+            };
+            anyhow::bail!("Failed to install {}:\n{}{}", specs.join(", "), stderr, hint);
+        }
+
+        Ok(())
+    }
+}
+
+fn check_version(version: &str) -> Result<(), String> {
+    let mut parts = version.split('.').map(|part| part.parse::<u32>().unwrap_or(0));
+    let found = (parts.next().unwrap_or(0), parts.next().unwrap_or(0));
+    if found < MIN_PYTHON {
+        return Err(format!(
+            "Python {} is too old, {}.{} or newer is required",
+            version, MIN_PYTHON.0, MIN_PYTHON.1
+        ));
+    }
+    Ok(())
+}
+
+fn last_line(stderr: &[u8]) -> String {
+    String::from_utf8_lossy(stderr)
+        .lines()
+        .rfind(|line| !line.trim().is_empty())
+        .unwrap_or("interpreter failed")
+        .trim()
+        .to_string()
+}
+
@@ -1,14 +1,23 @@
 use anyhow::{Context, Result};
+use async_trait::async_trait;
 use reqwest::multipart;
 use serde::Deserialize;
 
+use crate::stt::SpeechToText;
+
+const OPENAI_URL: &str = "https://api.openai.com/v1";
+
 #[derive(Deserialize)]
 struct WhisperResponse {
     text: String,
 }
 
+/// Client for OpenAI's transcription API, or a self-hosted server that speaks it
 pub struct WhisperClient {
-    api_key: String,
+    /// Up to and including the version, e.g. `https://api.openai.com/v1`
+    base_url: String,
+    api_key: Option<String>,
+    model: String,
     client: reqwest::Client,
 }
 
@@ -18,14 +27,47 @@ impl WhisperClient {
             .context("OPENAI_API_KEY environment variable not set")?;
 
         Ok(Self {
+            base_url: OPENAI_URL.to_string(),
+            api_key: Some(api_key),
+            model: "whisper-1".to_string(),
+            client: reqwest::Client::new(),
+        })
+    }
+
+    /// A server such as faster-whisper-server or LocalAI at `base_url`; many need no key
+    pub fn compatible(base_url: &str, api_key: Option<String>) -> Result<Self> {
+        let base_url = base_url.trim().trim_end_matches('/');
+        if !base_url.starts_with("http://") && !base_url.starts_with("https://") {
+            anyhow::bail!("Speech-to-text URL must start with http:// or https:// (got '{}')", base_url);
+        }
+
+        Ok(Self {
+            base_url: base_url.to_string(),
             api_key,
+            model: "whisper-1".to_string(),
             client: reqwest::Client::new(),
         })
     }
 
-    pub async fn transcribe(&self, audio_data: &[u8]) -> Result<String> {
+    pub fn with_model(mut self, model: String) -> Self {
+        self.model = model;
+        self
+    }
+}
+
+#[async_trait]
+impl SpeechToText for WhisperClient {
+    fn name(&self) -> String {
+        if self.base_url == OPENAI_URL {
+            format!("OpenAI {}", self.model)
+        } else {
+            format!("{} at {}", self.model, self.base_url)
+        }
+    }
+
+    async fn transcribe(&self, audio_data: &[u8]) -> Result<String> {
         let form = multipart::Form::new()
-            .text("model", "whisper-1")
+            .text("model", self.model.clone())
             .part(
                 "file",
                 multipart::Part::bytes(audio_data.to_vec())
@@ -33,14 +75,18 @@ impl WhisperClient {
                     .mime_str("audio/wav")?,
             );
 
-        let response = self
+        let mut request = self
             .client
-            .post("https://api.openai.com/v1/audio/transcriptions")
-            .header("Authorization", format!("Bearer {}", self.api_key))
-            .multipart(form)
+            .post(format!("{}/audio/transcriptions", self.base_url))
+            .multipart(form);
+        if let Some(api_key) = &self.api_key {
+            request = request.header("Authorization", format!("Bearer {}", api_key));
+        }
+
+        let response = request
             .send()
             .await
-            .context("Failed to send transcription request")?;
+            .with_context(|| format!("Failed to send transcription request to {}", self.base_url))?;
 
         let status = response.status();
         if !status.is_success() {
@@ -0,0 +1,127 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use std::io::Cursor;
+use std::path::PathBuf;
+use tokio::process::Command;
+
+use crate::stt::SpeechToText;
+
+/// Sample rate whisper.cpp models expect
+const WHISPER_RATE: u32 = 16_000;
+
+/// Offline transcription with the whisper.cpp command-line tool and a ggml model on disk
+pub struct LocalWhisper {
+    model: PathBuf,
+    /// `whisper-cli` (or the older `main`) from a whisper.cpp build
+    binary: String,
+}
+
+impl LocalWhisper {
+    pub fn new(model: PathBuf, binary: String) -> Result<Self> {
+        if !model.is_file() {
+            anyhow::bail!(
+                "Whisper model {} not found; download a ggml model such as ggml-base.en.bin",
+                model.display()
+            );
+        }
+        Ok(Self { model, binary })
+    }
+}
+
+#[async_trait]
+impl SpeechToText for LocalWhisper {
+    fn name(&self) -> String {
+        let model = self.model.file_name().unwrap_or_default().to_string_lossy();
+        format!("whisper.cpp {}", model)
+    }
+
+    async fn transcribe(&self, audio_data: &[u8]) -> Result<String> {
+        let wav = to_whisper_wav(audio_data)?;
+        let mut file = tempfile::Builder::new().suffix(".wav").tempfile()?;
+        std::io::Write::write_all(&mut file, &wav)?;
+
+        let output = Command::new(&self.binary)
+            .arg("-m")
+            .arg(&self.model)
+            .arg("-f")
+            .arg(file.path())
+            .args(["--no-timestamps", "--no-prints"])
+            .output()
+            .await
+            .with_context(|| {
+                format!(
+                    "Failed to run {}. Is whisper.cpp installed? Set ABEL_WHISPER_CPP to its whisper-cli",
+                    self.binary
+                )
+            })?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            anyhow::bail!("{} failed: {}", self.binary, stderr.trim());
+        }
+
+        let text: Vec<&str> = std::str::from_utf8(&output.stdout)
+            .context("whisper.cpp printed invalid UTF-8")?
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !is_annotation(line))
+            .collect();
+        Ok(text.join(" "))
+    }
+}
+
+/// Non-speech markers whisper.cpp prints for silence, e.g. `[BLANK_AUDIO]` or `(music)`
+fn is_annotation(line: &str) -> bool {
+    (line.starts_with('[') && line.ends_with(']')) || (line.starts_with('(') && line.ends_with(')'))
+}
+
+/// Converts any WAV to the 16kHz mono 16-bit audio whisper.cpp reads
+fn to_whisper_wav(audio_data: &[u8]) -> Result<Vec<u8>> {
+    let mut reader = hound::WavReader::new(Cursor::new(audio_data))
+        .context("Local speech-to-text needs WAV audio")?;
+    let spec = reader.spec();
+
+    let samples: Vec<f32> = match spec.sample_format {
+        hound::SampleFormat::Float => reader.samples::<f32>().collect::<Result<_, _>>()?,
+        hound::SampleFormat::Int => {
+            let scale = (1i64 << (spec.bits_per_sample - 1)) as f32;
+            reader
+                .samples::<i32>()
+                .map(|s| s.map(|s| s as f32 / scale))
+                .collect::<Result<_, _>>()?
+        }
+    };
+
+    // Average the channels, then resample linearly
+    let channels = spec.channels.max(1) as usize;
+    let mono: Vec<f32> = samples
+        .chunks(channels)
+        .map(|frame| frame.iter().sum::<f32>() / frame.len() as f32)
+        .collect();
+    let ratio = spec.sample_rate as f64 / WHISPER_RATE as f64;
+    let length = (mono.len() as f64 / ratio) as usize;
+    let resampled = (0..length).map(|i| {
+        let position = i as f64 * ratio;
+        let index = position as usize;
+        let fraction = (position - index as f64) as f32;
+        let next = mono.get(index + 1).copied().unwrap_or(mono[index]);
+        mono[index] * (1.0 - fraction) + next * fraction
+    });
+
+    let mut out = Cursor::new(Vec::new());
+    let mut writer = hound::WavWriter::new(
+        &mut out,
+        hound::WavSpec {
+            channels: 1,
+            sample_rate: WHISPER_RATE,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        },
+    )?;
+    for sample in resampled {
+        writer.write_sample((sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16)?;
+    }
+    writer.finalize()?;
+
+    Ok(out.into_inner())
+}
@@ -0,0 +1,356 @@
+use anyhow::Result;
+use colored::Colorize;
+use console::{Key, Term};
+use dialoguer::Confirm;
+use std::time::Duration;
+
+use crate::driver::{ArmDriver, ServoId, MAX_ANGLE};
+use crate::profile::{CalibrationProfile, Pose};
+use crate::serial::SerialConfig;
+
+/// Jog step sizes, selected with 1/5/0 or cycled with Tab
+pub const STEP_SIZES: [u8; 3] = [1, 5, 10];
+
+/// Duration of a single jog; short so holding an arrow key feels responsive
+pub const JOG_DURATION: Duration = Duration::from_millis(200);
+
+/// The dry pick-and-place runs this many times slower than normal moves
+const VALIDATION_SLOWDOWN: f32 = 2.0;
+
+/// Guided calibration: limits, gripper points and poses, checked with a dry run before saving
+struct Wizard {
+    term: Term,
+    driver: ArmDriver,
+    profile: CalibrationProfile,
+    step: usize,
+}
+
+pub fn run_wizard(serial: &SerialConfig) -> Result<()> {
+    let term = Term::stdout();
+    if !term.is_term() {
+        anyhow::bail!("The calibration wizard needs an interactive terminal");
+    }
+
+    println!("{}", "🧭 Guided Calibration Wizard".bright_cyan().bold());
+    println!();
+    println!("{}", "Keys:".bright_white());
+    println!("  {}  jog the selected joint", "← →".cyan());
+    println!("  {}  select another joint (poses)", "↑ ↓".cyan());
+    println!("  {}  step size 1°/5°/10° (Tab cycles)", "1 5 0".cyan());
+    println!("  {}  record, {} cancel without saving", "Enter".cyan(), "Esc".cyan());
+    println!();
+
+    let profile = CalibrationProfile::load()?;
+
+    // Jog freely until the new limits are recorded; trims still apply
+    let driver = ArmDriver::open_with_profile(serial, profile.without_limits())?;
+
+    let mut wizard = Wizard {
+        term,
+        driver,
+        profile,
+        step: 1,
+    };
+
+    if wizard.run()? {
+        let path = wizard.profile.save()?;
+        println!("{}: {}", "💾 Saved".green(), path.display());
+        println!("{}", "✓ Calibration complete".green());
+    } else {
+        println!("{}", "⊗ Calibration cancelled, nothing saved".yellow());
+    }
+
+    Ok(())
+}
+
+impl Wizard {
+    /// Walks through every step; false when the user cancelled
+    fn run(&mut self) -> Result<bool> {
+        let home = self.profile.home_moves();
+        std::thread::sleep(self.driver.move_coordinated(&home)?);
+
+        for servo in ServoId::ALL {
+            if !self.find_limits(servo)? {
+                return Ok(false);
+            }
+        }
+
+        if !self.record_gripper()? {
+            return Ok(false);
+        }
+
+        loop {
+            for name in ["pick", "place"] {
+                if !self.record_pose(name)? {
+                    return Ok(false);
+                }
+            }
+
+            self.profile.validate()?;
+
+            println!();
+            println!(
+                "{}",
+                format!("Dry pick-and-place at 1/{} speed, no object needed", VALIDATION_SLOWDOWN).bright_white()
+            );
+            let passed = match self.dry_run() {
+                Ok(()) => Confirm::new()
+                    .with_prompt("Did the arm reach both poses cleanly?")
+                    .default(true)
+                    .interact()?,
+                Err(e) => {
+                    println!("{}: {}", "✗ Dry run failed".red(), e);
+                    false
+                }
+            };
+
+            if passed {
+                return Ok(true);
+            }
+
+            if !Confirm::new()
+                .with_prompt("Record the pick and place poses again?")
+                .default(true)
+                .interact()?
+            {
+                return Ok(false);
+            }
+        }
+    }
+
+    fn find_limits(&mut self, servo: ServoId) -> Result<bool> {
+        println!();
+        println!("{}: {}", "Calibrating".green(), servo.name().bright_white());
+
+        let start = self.driver.position(servo);
+
+        let Some(min) = self.jog_one(servo, start, "jog to the lower mechanical limit", false)? else {
+            return Ok(false);
+        };
+        let Some(max) = self.jog_one(servo, min, "jog to the upper mechanical limit", false)? else {
+            return Ok(false);
+        };
+
+        let (min, max) = (min.min(max), min.max(max));
+        let cal = self.profile.servo_mut(servo);
+        cal.min = min;
+        cal.max = max;
+        cal.home = cal.home.clamp(min, max);
+        let home = cal.home;
+        println!("{} {} range {}-{}°, home {}°", "✓".green(), servo.name(), min, max, home);
+
+        // Park it before moving on so it doesn't sit against the stop
+        std::thread::sleep(self.driver.move_coordinated(&[(servo, home)])?);
+
+        Ok(true)
+    }
+
+    fn record_gripper(&mut self) -> Result<bool> {
+        println!();
+        println!("{}: {}", "Calibrating".green(), "Gripper points".bright_white());
+
+        let points = [
+            ("open", "jog to fully open", self.profile.gripper.open),
+            ("touch", "jog until the jaws just touch an object", self.profile.gripper.touch),
+            ("closed", "jog to a secure grip", self.profile.gripper.closed),
+        ];
+
+        for (name, hint, start) in points {
+            let Some(angle) = self.jog_one(ServoId::Gripper, start, hint, true)? else {
+                return Ok(false);
+            };
+            match name {
+                "open" => self.profile.gripper.open = angle,
+                "touch" => self.profile.gripper.touch = angle,
+                _ => self.profile.gripper.closed = angle,
+            }
+            println!("{} Gripper {} = {}°", "✓".green(), name, angle);
+        }
+
+        Ok(true)
+    }
+
+    fn record_pose(&mut self, name: &str) -> Result<bool> {
+        println!();
+        println!("{}: {}", "Recording pose".green(), name.bright_white());
+
+        let start = self.profile.poses.get(name).copied().unwrap_or(Pose {
+            base: self.profile.servos.base.home,
+            shoulder: self.profile.servos.shoulder.home,
+            elbow: self.profile.servos.elbow.home,
+            gripper: None,
+        });
+        let joints = [
+            (ServoId::Base, start.base),
+            (ServoId::Shoulder, start.shoulder),
+            (ServoId::Elbow, start.elbow),
+        ];
+
+        let hint = format!("jog the gripper to the {} position", name);
+        let Some(angles) = self.jog(&joints, &hint, true)? else {
+            return Ok(false);
+        };
+
+        let pose = Pose {
+            base: angles[0],
+            shoulder: angles[1],
+            elbow: angles[2],
+            gripper: None,
+        };
+        println!(
+            "{} Pose '{}' = Base {}° Shoulder {}° Elbow {}°",
+            "✓".green(),
+            name,
+            pose.base,
+            pose.shoulder,
+            pose.elbow
+        );
+        self.profile.poses.insert(name.to_string(), pose);
+
+        Ok(true)
+    }
+
+    /// Picks and places with an empty gripper, enforcing the new limits
+    fn dry_run(&mut self) -> Result<()> {
+        self.driver.set_profile(self.profile.clone());
+
+        let gripper = self.profile.gripper;
+        let pick = self.profile.pose("pick")?.moves();
+        let place = self.profile.pose("place")?.moves();
+        let home = self.profile.home_moves();
+
+        let steps: [(&str, Vec<(ServoId, u8)>); 8] = [
+            ("Home", home.clone()),
+            ("Open gripper", vec![(ServoId::Gripper, gripper.open)]),
+            ("Pick pose", pick),
+            ("Close gripper", vec![(ServoId::Gripper, gripper.closed)]),
+            ("Lift", home.iter().copied().filter(|&(s, _)| s != ServoId::Gripper).collect()),
+            ("Place pose", place),
+            ("Open gripper", vec![(ServoId::Gripper, gripper.open)]),
+            ("Home", home),
+        ];
+
+        let result = steps.into_iter().try_for_each(|(label, moves)| {
+            let duration = self.driver.move_coordinated_scaled(&moves, VALIDATION_SLOWDOWN)?;
+            println!("  {} {} ({}ms)", "➜".cyan(), label, duration.as_millis());
+            std::thread::sleep(duration);
+            Ok(())
+        });
+
+        // Back to free jogging in case the poses get recorded again
+        self.driver.set_profile(self.profile.without_limits());
+        result
+    }
+
+    fn jog_one(&mut self, servo: ServoId, start: u8, hint: &str, limited: bool) -> Result<Option<u8>> {
+        Ok(self.jog(&[(servo, start)], hint, limited)?.map(|angles| angles[0]))
+    }
+
+    /// Lets the user jog the joints with the arrow keys until Enter (Some) or Esc (None)
+    ///
+    /// With `limited`, jogging stays inside the limits recorded earlier in the wizard.
+    fn jog(&mut self, joints: &[(ServoId, u8)], hint: &str, limited: bool) -> Result<Option<Vec<u8>>> {
+        println!("  {}", hint.dimmed());
+
+        let servos: Vec<ServoId> = joints.iter().map(|&(s, _)| s).collect();
+        let mut angles: Vec<u8> = joints.iter().map(|&(_, a)| a).collect();
+        let mut selected = 0;
+
+        let bounds = |servo: ServoId, profile: &CalibrationProfile| {
+            if limited {
+                let cal = profile.servo(servo);
+                (cal.min, cal.max)
+            } else {
+                (0, MAX_ANGLE)
+            }
+        };
+        for (angle, &servo) in angles.iter_mut().zip(&servos) {
+            let (lo, hi) = bounds(servo, &self.profile);
+            *angle = (*angle).clamp(lo, hi);
+        }
+
+        let moves: Vec<(ServoId, u8)> = servos.iter().copied().zip(angles.iter().copied()).collect();
+        std::thread::sleep(self.driver.move_coordinated(&moves)?);
+
+        loop {
+            self.render(&servos, &angles, selected)?;
+
+            let step = STEP_SIZES[self.step] as i16;
+            let delta = match self.term.read_key()? {
+                Key::ArrowLeft => -step,
+                Key::ArrowRight => step,
+                Key::ArrowUp if servos.len() > 1 => {
+                    selected = (selected + servos.len() - 1) % servos.len();
+                    continue;
+                }
+                Key::ArrowDown if servos.len() > 1 => {
+                    selected = (selected + 1) % servos.len();
+                    continue;
+                }
+                Key::Char('1') => {
+                    self.step = 0;
+                    continue;
+                }
+                Key::Char('5') => {
+                    self.step = 1;
+                    continue;
+                }
+                Key::Char('0') => {
+                    self.step = 2;
+                    continue;
+                }
+                Key::Tab => {
+                    self.step = (self.step + 1) % STEP_SIZES.len();
+                    continue;
+                }
+                Key::Enter => {
+                    self.term.clear_line()?;
+                    return Ok(Some(angles));
+                }
+                Key::Escape | Key::Char('q') => {
+                    self.term.clear_line()?;
+                    return Ok(None);
+                }
+                _ => continue,
+            };
+
+            let servo = servos[selected];
+            let (lo, hi) = bounds(servo, &self.profile);
+            let target = (angles[selected] as i16 + delta).clamp(lo as i16, hi as i16) as u8;
+            if target == angles[selected] {
+                continue;
+            }
+
+            match self.driver.move_servo(servo, target, JOG_DURATION) {
+                Ok(_) => angles[selected] = target,
+                Err(e) => {
+                    self.term.clear_line()?;
+                    println!("  {}", e.to_string().red());
+                }
+            }
+        }
+    }
+
+    fn render(&self, servos: &[ServoId], angles: &[u8], selected: usize) -> Result<()> {
+        let joints: Vec<String> = servos
+            .iter()
+            .zip(angles)
+            .enumerate()
+            .map(|(i, (servo, angle))| {
+                if i == selected {
+                    format!("{} ◀ {:>3}° ▶", servo.name(), angle).bright_yellow().bold().to_string()
+                } else {
+                    format!("{} {:>3}°", servo.name(), angle).dimmed().to_string()
+                }
+            })
+            .collect();
+
+        self.term.clear_line()?;
+        self.term.write_str(&format!(
+            "  {}   {}",
+            joints.join("   "),
+            format!("step {}°", STEP_SIZES[self.step]).dimmed()
+        ))?;
+        Ok(())
+    }
+}
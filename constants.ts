@@ -19,7 +19,8 @@ export const INITIAL_POSITIONS = {
   [ServoId.Gripper]: 90,
 };
 
-// Sequences
+// Sequences: copies of abel-voice-cli/sequences, used when the voice service
+// isn't running to provide the library itself
 export const SEQUENCES = {
   WAVE: [
     { servo: ServoId.Base, angle: 60, delay: 300 },
@@ -1,5 +1,5 @@
 /*
- * Abel Arm - ESP32-C3 Servo Controller Firmware v4.0
+ * Abel Arm - ESP32-C3 Servo Controller Firmware v4.3
  * Advanced Motion Control with Quintic Polynomial Trajectory Planning
  *
  * Features:
@@ -9,9 +9,14 @@
  * - Adaptive trajectory planning based on movement distance
  * - Velocity and acceleration constraints based on servo capabilities
  * - Coordinated multi-axis motion support
+ * - Queued trajectory segments with boundary velocities, for smooth multi-waypoint paths
  *
  * Protocol: #<servo>M<angle>T<duration>\n
  * Example: #0M90T1000\n (move servo 0 to 90 degrees over 1 second)
+ * Segment: #<servo>Q<angle>T<duration>V<v0>,<vf>\n (queued after the servo's current motion)
+ * Example: #1Q50T600V0,-60.5\n (start at rest, pass 50 degrees at -60.5 deg/s)
+ * Emergency stop: !\n (hold every servo where it is)
+ * Status query: ?\n (one STATUS line per servo)
  */
 
 #include <ESP32Servo.h>
@@ -25,6 +30,23 @@ const float MAX_VELOCITY = 120.0;      // Maximum angular velocity (deg/s)
 const float MAX_ACCELERATION = 200.0;  // Maximum angular acceleration (deg/s^2)
 const float MAX_JERK = 600.0;          // Maximum jerk (deg/s^3)
 
+// Segments allowed to exceed the limits by this factor; the host plans below them
+const float SEGMENT_TOLERANCE = 1.1;
+
+// Start velocity may differ from the previous segment's end velocity by this much (deg/s)
+const float VELOCITY_MATCH = 1.0;
+
+// Segments each servo can queue behind the one it is running
+const int SEGMENT_QUEUE = 8;
+
+// A queued trajectory segment: target, duration and boundary velocities
+struct Segment {
+  float targetAngle;
+  unsigned long duration;
+  float startVelocity;
+  float endVelocity;
+};
+
 // Motion profile for each servo
 struct MotionProfile {
   float currentAngle;
@@ -40,6 +62,14 @@ struct MotionProfile {
   unsigned long duration;
   bool moving;
 
+  // Velocity the current trajectory ends with; non-zero for segments passing a via point
+  float endVelocity;
+
+  // Segments waiting to run after the current trajectory, oldest first
+  Segment queue[SEGMENT_QUEUE];
+  int queueHead;
+  int queueCount;
+
   // Quintic polynomial coefficients: q(t) = a0 + a1*t + a2*t^2 + a3*t^3 + a4*t^4 + a5*t^5
   float a0, a1, a2, a3, a4, a5;
 
@@ -72,6 +102,20 @@ void calculateQuinticCoefficients(MotionProfile &profile, float T) {
   profile.a5 = 6.0 * (qf - q0) / (T * T * T * T * T);
 }
 
+// Quintic segment with boundary velocities
+// Boundary conditions: q(0)=q0, q(T)=qf, v(0)=v0, v(T)=vf, a(0)=0, a(T)=0
+// Zero acceleration at both ends keeps consecutive segments C2 continuous
+void calculateSegmentCoefficients(MotionProfile &profile, float T, float v0, float vf) {
+  float h = profile.targetAngle - profile.startAngle;
+
+  profile.a0 = profile.startAngle;
+  profile.a1 = v0;
+  profile.a2 = 0.0;
+  profile.a3 = (20.0 * h - (8.0 * vf + 12.0 * v0) * T) / (2.0 * T * T * T);
+  profile.a4 = (-30.0 * h + (14.0 * vf + 16.0 * v0) * T) / (2.0 * T * T * T * T);
+  profile.a5 = (12.0 * h - 6.0 * (vf + v0) * T) / (2.0 * T * T * T * T * T);
+}
+
 // Evaluate position at time t using quintic polynomial
 float evaluateQuinticPosition(const MotionProfile &profile, float t) {
   return profile.a0
@@ -127,7 +171,7 @@ void setup() {
   Serial0.begin(115200);
   delay(1000);
 
-  Serial0.println("Abel Arm Firmware v4.0");
+  Serial0.println("Abel Arm Firmware v4.3");
   Serial0.println("Quintic Polynomial Trajectory Planning");
   Serial0.println("C2 Continuous Motion Control");
 
@@ -139,6 +183,9 @@ void setup() {
     profiles[i].currentVelocity = 0.0;
     profiles[i].currentAcceleration = 0.0;
     profiles[i].moving = false;
+    profiles[i].endVelocity = 0.0;
+    profiles[i].queueHead = 0;
+    profiles[i].queueCount = 0;
     servos[i].write(90);
   }
 
@@ -153,6 +200,9 @@ void setup() {
 
   Serial0.println("Ready.");
   Serial0.println("Command: #<servo>M<angle>T<duration>");
+  Serial0.println("Segment: #<servo>Q<angle>T<duration>V<v0>,<vf>");
+  Serial0.println("Stop: !");
+  Serial0.println("Status: ?");
   Serial0.println("Constraints: v_max=120deg/s, a_max=200deg/s^2");
 }
 
@@ -178,20 +228,60 @@ void loop() {
   }
 }
 
+// Starts a segment where the previous trajectory ended, at `startTime`
+void startSegment(MotionProfile &profile, const Segment &segment, unsigned long startTime) {
+  profile.startAngle = profile.targetAngle;
+  profile.targetAngle = segment.targetAngle;
+  profile.startTime = startTime;
+  profile.duration = segment.duration;
+  profile.endVelocity = segment.endVelocity;
+  profile.trajectoryType = MotionProfile::QUINTIC;
+  calculateSegmentCoefficients(profile, (float)segment.duration / 1000.0,
+                               segment.startVelocity, segment.endVelocity);
+  profile.moving = true;
+}
+
+// Angle the servo ends up at once everything queued has run
+float finalAngle(const MotionProfile &profile) {
+  if (profile.queueCount == 0) return profile.targetAngle;
+  int last = (profile.queueHead + profile.queueCount - 1) % SEGMENT_QUEUE;
+  return profile.queue[last].targetAngle;
+}
+
 void updateMotion() {
   for (int i = 0; i < 4; i++) {
     if (!profiles[i].moving) continue;
 
     unsigned long elapsed = millis() - profiles[i].startTime;
+
+    // Chain straight into the next queued segment, timed from when this one ended
+    if (elapsed >= profiles[i].duration && profiles[i].queueCount > 0) {
+      Segment next = profiles[i].queue[profiles[i].queueHead];
+      profiles[i].queueHead = (profiles[i].queueHead + 1) % SEGMENT_QUEUE;
+      profiles[i].queueCount--;
+      startSegment(profiles[i], next, profiles[i].startTime + profiles[i].duration);
+      elapsed = millis() - profiles[i].startTime;
+    }
+
     float t = (float)elapsed / 1000.0; // Convert to seconds
     float T = (float)profiles[i].duration / 1000.0;
 
     if (elapsed >= profiles[i].duration) {
+      if (profiles[i].endVelocity != 0.0) {
+        // The host didn't send the next segment in time, so the servo stops short
+        Serial0.print("WARN: Servo ");
+        Serial0.print(i);
+        Serial0.print(" segment queue ran dry at ");
+        Serial0.print(profiles[i].endVelocity, 1);
+        Serial0.println("deg/s");
+      }
+
       // Motion complete - ensure exact final position
       profiles[i].currentAngle = profiles[i].targetAngle;
       profiles[i].currentVelocity = 0.0;
       profiles[i].currentAcceleration = 0.0;
       profiles[i].moving = false;
+      profiles[i].endVelocity = 0.0;
       servos[i].write((int)round(profiles[i].currentAngle));
     } else {
       // Calculate position, velocity, and acceleration using quintic trajectory
@@ -205,12 +295,63 @@ void updateMotion() {
   }
 }
 
+// Emergency stop: freeze every servo at its current interpolated angle
+void stopAll() {
+  for (int i = 0; i < 4; i++) {
+    profiles[i].queueCount = 0;
+    profiles[i].endVelocity = 0.0;
+    profiles[i].targetAngle = profiles[i].currentAngle;
+    profiles[i].currentVelocity = 0.0;
+    profiles[i].currentAcceleration = 0.0;
+    profiles[i].moving = false;
+    servos[i].write((int)round(profiles[i].currentAngle));
+  }
+
+  Serial0.print("STOPPED:");
+  for (int i = 0; i < 4; i++) {
+    Serial0.print(" ");
+    Serial0.print(profiles[i].currentAngle, 1);
+  }
+  Serial0.println();
+}
+
+// Status query: STATUS <servo> <current> <target> <velocity> <moving>
+void printStatus() {
+  for (int i = 0; i < 4; i++) {
+    Serial0.print("STATUS ");
+    Serial0.print(i);
+    Serial0.print(" ");
+    Serial0.print(profiles[i].currentAngle, 1);
+    Serial0.print(" ");
+    Serial0.print(finalAngle(profiles[i]), 1);
+    Serial0.print(" ");
+    Serial0.print(profiles[i].currentVelocity, 1);
+    Serial0.print(" ");
+    Serial0.println(profiles[i].moving ? 1 : 0);
+  }
+}
+
 void processCommand(String cmd) {
+  if (cmd == "!") {
+    stopAll();
+    return;
+  }
+
+  if (cmd == "?") {
+    printStatus();
+    return;
+  }
+
   if (cmd.length() < 4 || cmd.charAt(0) != '#') {
     Serial0.println("ERR: Invalid format");
     return;
   }
 
+  if (cmd.indexOf('Q') != -1) {
+    processSegment(cmd);
+    return;
+  }
+
   // Parse: #<servo>M<angle>T<duration>
   int mIndex = cmd.indexOf('M');
   int tIndex = cmd.indexOf('T');
@@ -259,6 +400,10 @@ void processCommand(String cmd) {
     duration = optimalDuration;
   }
 
+  // A point-to-point move replaces any queued segments
+  profiles[servoNum].queueCount = 0;
+  profiles[servoNum].endVelocity = 0.0;
+
   // Initialize motion profile
   profiles[servoNum].startAngle = profiles[servoNum].currentAngle;
   profiles[servoNum].targetAngle = (float)targetAngle;
@@ -285,3 +430,117 @@ void processCommand(String cmd) {
   Serial0.print(deltaAngle, 1);
   Serial0.println("deg)");
 }
+
+// Parse: #<servo>Q<angle>T<duration>V<v0>,<vf>
+// Queues a segment that starts where the servo's last motion ends. Durations are not
+// adjusted, since that would desynchronise the joints; segments over the limits are
+// rejected instead.
+void processSegment(String cmd) {
+  int qIndex = cmd.indexOf('Q');
+  int tIndex = cmd.indexOf('T');
+  int vIndex = cmd.indexOf('V');
+  int commaIndex = cmd.indexOf(',', vIndex);
+
+  if (tIndex == -1 || vIndex == -1 || commaIndex == -1 || !(qIndex < tIndex && tIndex < vIndex)) {
+    Serial0.println("ERR: Invalid segment format");
+    return;
+  }
+
+  int servoNum = cmd.substring(1, qIndex).toInt();
+  int targetAngle = cmd.substring(qIndex + 1, tIndex).toInt();
+  unsigned long duration = cmd.substring(tIndex + 1, vIndex).toInt();
+  float startVelocity = cmd.substring(vIndex + 1, commaIndex).toFloat();
+  float endVelocity = cmd.substring(commaIndex + 1).toFloat();
+
+  if (servoNum < 0 || servoNum > 3) {
+    Serial0.println("ERR: Invalid servo (0-3)");
+    return;
+  }
+
+  if (targetAngle < 0 || targetAngle > 180) {
+    Serial0.println("ERR: Angle out of range (0-180)");
+    return;
+  }
+
+  if (duration < 100 || duration > 10000) {
+    Serial0.println("ERR: Duration out of range (100-10000ms)");
+    return;
+  }
+
+  if (abs(startVelocity) > MAX_VELOCITY || abs(endVelocity) > MAX_VELOCITY) {
+    Serial0.println("ERR: Boundary velocity exceeds v_max");
+    return;
+  }
+
+  MotionProfile &profile = profiles[servoNum];
+  if (profile.queueCount >= SEGMENT_QUEUE) {
+    Serial0.println("ERR: Segment queue full");
+    return;
+  }
+
+  // Where and how fast the servo will be when this segment starts
+  bool idle = !profile.moving && profile.queueCount == 0;
+  float fromAngle = idle ? profile.currentAngle : finalAngle(profile);
+  float fromVelocity = 0.0;
+  if (profile.queueCount > 0) {
+    fromVelocity = profile.queue[(profile.queueHead + profile.queueCount - 1) % SEGMENT_QUEUE].endVelocity;
+  } else if (profile.moving) {
+    fromVelocity = profile.endVelocity;
+  }
+
+  if (abs(startVelocity - fromVelocity) > VELOCITY_MATCH) {
+    Serial0.print("ERR: Start velocity ");
+    Serial0.print(startVelocity, 1);
+    Serial0.print("deg/s does not match ");
+    Serial0.print(fromVelocity, 1);
+    Serial0.println("deg/s");
+    return;
+  }
+
+  // Check the planned segment against the limits before accepting it
+  MotionProfile check;
+  check.startAngle = fromAngle;
+  check.targetAngle = (float)targetAngle;
+  float T = (float)duration / 1000.0;
+  calculateSegmentCoefficients(check, T, startVelocity, endVelocity);
+  float peakVelocity = 0.0;
+  float peakAcceleration = 0.0;
+  for (int k = 0; k <= 20; k++) {
+    float t = T * k / 20.0;
+    peakVelocity = max(peakVelocity, (float)abs(evaluateQuinticVelocity(check, t)));
+    peakAcceleration = max(peakAcceleration, (float)abs(evaluateQuinticAcceleration(check, t)));
+  }
+  if (peakVelocity > MAX_VELOCITY * SEGMENT_TOLERANCE || peakAcceleration > MAX_ACCELERATION * SEGMENT_TOLERANCE) {
+    Serial0.print("ERR: Segment exceeds constraints (v=");
+    Serial0.print(peakVelocity, 1);
+    Serial0.print("deg/s, a=");
+    Serial0.print(peakAcceleration, 1);
+    Serial0.println("deg/s^2)");
+    return;
+  }
+
+  Segment segment = {(float)targetAngle, duration, startVelocity, endVelocity};
+  if (idle) {
+    profile.targetAngle = profile.currentAngle;
+    startSegment(profile, segment, millis());
+  } else {
+    profile.queue[(profile.queueHead + profile.queueCount) % SEGMENT_QUEUE] = segment;
+    profile.queueCount++;
+  }
+
+  Serial0.print("SEGMENT: Servo ");
+  Serial0.print(servoNum);
+  Serial0.print(" ");
+  Serial0.print(fromAngle, 1);
+  Serial0.print("deg -> ");
+  Serial0.print(targetAngle);
+  Serial0.print("deg over ");
+  Serial0.print(duration);
+  Serial0.print("ms (v=");
+  Serial0.print(startVelocity, 1);
+  Serial0.print("->");
+  Serial0.print(endVelocity, 1);
+  Serial0.print("deg/s, queued=");
+  Serial0.print(profile.queueCount);
+  Serial0.println(")");
+}
@@ -4,8 +4,15 @@ import { voiceService } from '../services/voiceService';
 import { RobotState, ServoId, Mood, LogMessage } from '../types';
 import { INITIAL_POSITIONS, ABEL_QUOTES, SEQUENCES } from '../constants';
 
+type SequenceStep = { servo: ServoId, angle: number, delay: number };
+
+// Longest smooth move moveServo sends, so multi-step commands wait for the arm
+const MOVE_SETTLE_MS = 800;
+
 export const useAbel = () => {
   const [isConnected, setIsConnected] = useState(false);
+  // Built-in copies until the voice service provides the CLI's sequence library
+  const [sequences, setSequences] = useState<Record<string, SequenceStep[]>>(SEQUENCES);
   const [positions, setPositions] = useState<RobotState>(INITIAL_POSITIONS);
   const [mood, setMood] = useState<Mood>('neutral');
   const [logs, setLogs] = useState<LogMessage[]>([]);
@@ -47,6 +54,22 @@ export const useAbel = () => {
     checkSupport();
   }, [addLog]);
 
+  // Use the CLI's sequence definitions when the voice service is running
+  useEffect(() => {
+    voiceService.fetchSequences().then(definitions => {
+      if (!definitions) return;
+
+      const loaded: Record<string, SequenceStep[]> = {};
+      for (const definition of definitions) {
+        if (definition.frames) {
+          loaded[definition.name] = definition.frames;
+        }
+      }
+      setSequences(prev => ({ ...prev, ...loaded }));
+      addLog(`Loaded ${Object.keys(loaded).length} sequences from the voice service.`, "System");
+    });
+  }, [addLog]);
+
   const speak = useCallback(() => {
     const quote = ABEL_QUOTES[Math.floor(Math.random() * ABEL_QUOTES.length)];
     addLog(quote, 'Abel');
@@ -156,6 +179,7 @@ export const useAbel = () => {
   // Stop any running sequence immediately
   const stopSequence = useCallback(async () => {
     stopSequenceRef.current = true;
+    await serialService.sendStop();
     setIsRunningSequence(false);
     setMood('neutral');
     addLog("Emergency stop activated.", "System");
@@ -166,7 +190,7 @@ export const useAbel = () => {
   }, [addLog, goHome]);
 
   // Execute a predefined sequence of moves
-  const runSequence = useCallback(async (sequence: { servo: ServoId, angle: number, delay: number }[]) => {
+  const runSequence = useCallback(async (sequence: SequenceStep[]) => {
     if (!isConnected) {
       addLog("I can't move if I'm not connected...", "Abel");
       return;
@@ -220,18 +244,28 @@ export const useAbel = () => {
       addLog(`Heard: "${transcript}"`, "System");
 
       const command = await voiceService.interpretCommand(transcript);
-
-      // Handle multi-command
-      if (command.action === 'multi' && command.commands) {
-        for (const cmd of command.commands) {
-          await executeCommand(cmd);
-        }
-      } else {
-        await executeCommand(command);
-      }
+      await executeCommand(command);
 
       async function executeCommand(cmd: typeof command) {
         switch (cmd.action) {
+          case 'multi':
+            for (const step of cmd.commands || []) {
+              await executeCommand(step);
+              // moveServo returns once the command is sent; let the arm arrive before the next step
+              if (step.action === 'move' || step.action === 'parallel') {
+                await new Promise(resolve => setTimeout(resolve, MOVE_SETTLE_MS));
+              }
+            }
+            break;
+          case 'parallel':
+            addLog(`Moving ${(cmd.commands || []).length} servos together`, "System");
+            await Promise.all((cmd.commands || []).map(step => executeCommand(step)));
+            break;
+          case 'wait':
+            if (cmd.ms) {
+              await new Promise(resolve => setTimeout(resolve, cmd.ms));
+            }
+            break;
           case 'move':
             if (cmd.servo !== undefined && cmd.angle !== undefined) {
               addLog(`Moving servo ${cmd.servo} to ${cmd.angle}°`, "System");
@@ -240,7 +274,7 @@ export const useAbel = () => {
             break;
           case 'sequence':
             if (cmd.sequenceName) {
-              const seq = (SEQUENCES as any)[cmd.sequenceName];
+              const seq = sequences[cmd.sequenceName];
               if (seq) {
                 addLog(`Running sequence: ${cmd.sequenceName}`, "System");
                 await runSequence(seq);
@@ -266,7 +300,7 @@ export const useAbel = () => {
     } finally {
       setIsListening(false);
     }
-  }, [addLog, moveServo, runSequence, goHome, stopSequence]);
+  }, [addLog, moveServo, runSequence, goHome, stopSequence, sequences]);
 
   const stopListening = useCallback(() => {
     voiceService.stop();
@@ -281,6 +315,7 @@ export const useAbel = () => {
     logs,
     isRunningSequence,
     isListening,
+    sequences,
     connect,
     disconnect,
     moveServo,
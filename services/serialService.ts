@@ -164,6 +164,21 @@ class SerialService {
       console.error("Error writing to serial port:", e);
     }
   }
+
+  // Emergency stop (firmware v4.1): every servo holds its current interpolated angle
+  async sendStop() {
+    if (!this.writer) {
+      return;
+    }
+
+    console.log('[SERIAL] Emergency stop');
+
+    try {
+      await this.writer.write(this.textEncoder.encode('!\n'));
+    } catch (e) {
+      console.error("Error writing to serial port:", e);
+    }
+  }
 }
 
 export const serialService = new SerialService();
\ No newline at end of file
@@ -1,12 +1,41 @@
 const GEMINI_API_KEY = import.meta.env.VITE_GEMINI_API_KEY;
 const GEMINI_API_URL = 'https://generativelanguage.googleapis.com/v1beta/models/gemini-2.0-flash-exp:generateContent';
 
+export interface ScriptOutputLine {
+  stream: 'stdout' | 'stderr';
+  line: string;
+}
+
+export interface ExecutionReport {
+  exit_code: number | null;
+  duration_ms: number;
+  stdout: string;
+  stderr: string;
+}
+
+export interface SequenceFrame {
+  servo: number;
+  angle: number;
+  delay: number;
+}
+
+export interface SequenceDefinition {
+  name: string;
+  description?: string;
+  source: string;
+  steps: Record<string, unknown>[];
+  frames?: SequenceFrame[];
+}
+
 interface VoiceCommand {
-  action: 'move' | 'sequence' | 'home' | 'stop' | 'unknown' | 'multi';
+  action: 'move' | 'sequence' | 'home' | 'stop' | 'unknown' | 'multi' | 'parallel' | 'wait';
   servo?: number;
   angle?: number;
   sequenceName?: string;
+  // 'multi' runs its commands in order, 'parallel' starts them together
   commands?: VoiceCommand[];
+  // Length of a 'wait'
+  ms?: number;
   message?: string;
 }
 
@@ -131,13 +160,7 @@ class VoiceService {
         if (response.ok) {
           const data = await response.json();
           this.serviceAvailable = true;
-          return {
-            action: data.action,
-            servo: data.servo,
-            angle: data.angle,
-            sequenceName: data.sequence_name,
-            message: data.message
-          };
+          return this.convertServiceResponse(data);
         }
       } catch (error) {
         console.log('Service unavailable, falling back to browser API');
@@ -205,6 +228,40 @@ class VoiceService {
     }
   }
 
+  // The service's /interpret response, including the steps of multi-step commands
+  private convertServiceResponse(data: any): VoiceCommand {
+    // Cartesian targets come back with the service's IK solution
+    if (data.action === 'move_to' && data.joints) {
+      return {
+        action: 'parallel',
+        commands: [
+          { action: 'move', servo: 0, angle: Math.round(data.joints.base) },
+          { action: 'move', servo: 1, angle: Math.round(data.joints.shoulder) },
+          { action: 'move', servo: 2, angle: Math.round(data.joints.elbow) },
+        ]
+      };
+    }
+
+    if (data.action === 'parallel') {
+      // A Cartesian target inside a group is just more servos starting together
+      const commands = (data.commands || []).flatMap((cmd: any) => {
+        const converted = this.convertServiceResponse(cmd);
+        return converted.action === 'parallel' ? converted.commands || [] : [converted];
+      });
+      return { action: 'parallel', commands };
+    }
+
+    return {
+      action: data.action,
+      servo: data.servo,
+      angle: data.angle,
+      sequenceName: data.sequence_name,
+      commands: data.commands?.map((cmd: any) => this.convertServiceResponse(cmd)),
+      ms: data.ms,
+      message: data.message
+    };
+  }
+
   private convertFunctionCallToCommand(functionCall: any): VoiceCommand {
     const name = functionCall.name;
     const args = functionCall.args || {};
@@ -230,6 +287,61 @@ class VoiceService {
     }
   }
 
+  /**
+   * Fetches the sequence library from the local service, or null when it isn't running.
+   * Frames are the moves each sequence makes, in the same format as SEQUENCES in constants.ts.
+   */
+  async fetchSequences(): Promise<SequenceDefinition[] | null> {
+    try {
+      const response = await fetch(`${this.serviceUrl}/sequences`, {
+        signal: AbortSignal.timeout(2000)
+      });
+      return response.ok ? await response.json() : null;
+    } catch {
+      return null;
+    }
+  }
+
+  /**
+   * Runs a Python script on the local service, calling onLine for each line it prints.
+   * The service streams server-sent events and ends with the execution report.
+   */
+  async runScript(script: string, onLine: (line: ScriptOutputLine) => void): Promise<ExecutionReport> {
+    const response = await fetch(`${this.serviceUrl}/script/run`, {
+      method: 'POST',
+      headers: { 'Content-Type': 'application/json' },
+      body: JSON.stringify({ script }),
+    });
+
+    if (!response.ok || !response.body) {
+      const data = await response.json().catch(() => ({}));
+      throw new Error(data.error || `Script request failed (${response.status})`);
+    }
+
+    const reader = response.body.pipeThrough(new TextDecoderStream()).getReader();
+    let buffered = '';
+
+    while (true) {
+      const { value, done } = await reader.read();
+      if (done) break;
+      buffered += value;
+
+      let end;
+      while ((end = buffered.indexOf('\n\n')) >= 0) {
+        const message = buffered.slice(0, end);
+        buffered = buffered.slice(end + 2);
+
+        const event = message.match(/^event: (.*)$/m)?.[1];
+        const data = JSON.parse(message.match(/^data: (.*)$/m)?.[1] ?? 'null');
+        if (event === 'output') onLine(data);
+        if (event === 'done') return data;
+        if (event === 'error') throw new Error(data.error);
+      }
+    }
+
+    throw new Error('Script stream ended without a report');
+  }
+
   stop() {
     if (this.recognition && this.isListening) {
       this.recognition.stop();